@@ -0,0 +1,31 @@
+-- # XMonad keymap
+keybinds = ([
+-- ## Basics
+-- Recompile and restart XMonad
+("M-C-q",       spawn "xmonad --recompile; xmonad --restart")
+-- Refresh XMonad
+, ("M-C-r",       refresh)
+-- Kill current window
+, ("M-x",         kill)
+
+-- ## Workspace navigation
+-- "M-<Workspace key>" Move to workspace x
+-- "M-S-<Workspace key>" Move current window to workspace x
+-- Switch to last workspace
+, ("M-<Tab>",       toggleRecentWS)
+-- Switch to next workspace
+, ("M-<Page_Up>",   nextWS)
+-- Switch to previous workspace
+, ("M-<Page_Down>", prevWS)
+-- Exec the action of the current workspace
+, ("M-<Return>",    chooseAction wsActions)
+
+-- ## Window navigation
+-- "M-↑→↓←" Navigate through windows
+-- "M-S-↑→↓←" Swap windows
+-- Focus next window up
+, ("M-k",         windows W.focusUp)
+-- Focus next window down
+, ("M-j",         windows W.focusDown)
+
+-- #
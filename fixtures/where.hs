@@ -0,0 +1,24 @@
+{-# LANGUAGE OverloadedStrings #-}
+import XMonad
+import XMonad.Util.EZConfig (additionalKeysP)
+
+main :: IO ()
+main = xmonad $ def `additionalKeysP` myKeys
+  where
+        {- # Keymap in a where clause -}
+    myKeys =
+            {- ## Apps -}
+            {- : Launched with spawn -}
+            -- Terminal
+        [ ("M-<Return>", spawn "alacritty")
+                {- Browser -}
+        , ("M-b", spawn "firefox")
+	    {- "M-p" Launcher, bound elsewhere -}
+      {- ## Windows
+      -}
+	-- Kill the focused window
+        , ("M-q", kill)
+            {- ! Debug -}
+        , ("M-d", spawn "xmessage debug")
+        ]
+    {- # -}
@@ -0,0 +1,250 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Onboarding of a config never annotated: the EZConfig keybinds without
+// description comment get one, suggested from their action, and the
+// keymap gets its `-- #` boundaries and a section if it has none. Only
+// the keybinds whose tuple fits on one line are found.
+
+use eyre::Result;
+use std::{
+    fs,
+    io::{self, Write},
+};
+use tracing::{debug, instrument};
+
+use crate::{
+    app::read_error,
+    parser::{action_description, parse_boundary, parse_keybind_tuple},
+};
+
+const DEFAULT_TITLE: &str = "Keymap";
+// the keybinds are parsed in sections only
+const DEFAULT_SECTION: &str = "General";
+// description of a keybind whose action tells nothing
+const TODO_DESCRIPTION: &str = "TODO";
+
+/// A keybind definition without description comment above it
+#[derive(Debug, PartialEq)]
+pub struct Undescribed {
+    // index of its line
+    pub line: usize,
+    pub definition: String,
+    pub suggestion: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Scan {
+    pub undescribed: Vec<Undescribed>,
+    // lines the keymap starts and ends at, when it has no boundaries yet
+    pub boundaries: Option<(usize, usize)>,
+}
+
+/// Finds the keybinds to describe, asks for their description unless
+/// `write` is set, then writes the annotated config
+#[instrument]
+pub fn run(path: &str, write: bool) -> Result<String> {
+    let content = fs::read_to_string(path).map_err(|e| read_error(path, e))?;
+    let scan = scan(&content);
+    debug!("{} keybinds without description", scan.undescribed.len());
+    if scan.undescribed.is_empty() && scan.boundaries.is_none() {
+        return Ok("Every keybind is described".to_owned());
+    }
+    let descriptions = if write {
+        scan.undescribed
+            .iter()
+            .map(|u| (u.line, u.suggestion.clone()))
+            .collect()
+    } else {
+        ask(&scan.undescribed)?
+    };
+    fs::write(path, apply(&content, &descriptions, scan.boundaries))?;
+    let mut report = format!("{} descriptions added to {path}", descriptions.len());
+    if scan.boundaries.is_some() {
+        report.push_str(", the keymap is enclosed in `-- #` boundaries");
+    }
+    Ok(report)
+}
+
+/// Lists the keybinds without description, between the boundaries if
+/// any, otherwise in the whole file
+pub fn scan(content: &str) -> Scan {
+    let lines: Vec<&str> = content.lines().collect();
+    let boundary_lines: Vec<usize> = (0..lines.len())
+        .filter(|i| parse_boundary(&format!("{}\n", lines[*i])).is_ok())
+        .collect();
+    // the parser only reads between the boundaries
+    let region = match boundary_lines.as_slice() {
+        [] => 0..lines.len(),
+        [start] => start + 1..lines.len(),
+        [start, end, ..] => start + 1..*end,
+    };
+    let mut undescribed = vec![];
+    let mut keybind_lines = vec![];
+    for i in region {
+        let Some(action) = keybind_action(lines[i]) else {
+            continue;
+        };
+        keybind_lines.push(i);
+        if i.checked_sub(1).is_some_and(|p| is_description(lines[p])) {
+            continue;
+        }
+        let suggestion = action
+            .map(action_description)
+            .filter(|d| !d.is_empty())
+            .unwrap_or_else(|| TODO_DESCRIPTION.to_owned());
+        undescribed.push(Undescribed {
+            line: i,
+            definition: lines[i].trim().to_owned(),
+            suggestion,
+        });
+    }
+    let boundaries = match (keybind_lines.first(), keybind_lines.last()) {
+        (Some(first), Some(last)) if boundary_lines.is_empty() => {
+            // above the comments of the first keybind
+            let start = (0..*first)
+                .rev()
+                .take_while(|i| is_comment(lines[*i]))
+                .last()
+                .unwrap_or(*first);
+            Some((start, *last))
+        }
+        _ => None,
+    };
+    Scan {
+        undescribed,
+        boundaries,
+    }
+}
+
+// the action of the EZConfig tuple starting the line, if any
+fn keybind_action(line: &str) -> Option<Option<&str>> {
+    let definition = line
+        .trim_start()
+        .trim_start_matches(['[', ','])
+        .trim_start();
+    parse_keybind_tuple(definition)
+        .ok()
+        .map(|(_, (_, action))| action)
+}
+
+fn is_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("--") || line.starts_with("{-")
+}
+
+// a comment which is not a boundary, a section or a pragma
+fn is_description(line: &str) -> bool {
+    let line = line.trim_start();
+    line.strip_prefix("--")
+        .or_else(|| line.strip_prefix("{-"))
+        .is_some_and(|rest| !rest.trim_start().starts_with(['#', ':']))
+}
+
+fn indent(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+fn ask(undescribed: &[Undescribed]) -> Result<Vec<(usize, String)>> {
+    println!("Enter keeps the suggested description, `-` skips the keybind, `q` stops");
+    let mut descriptions = vec![];
+    for keybind in undescribed {
+        print!(
+            "{}: {}\n  [{}] ",
+            keybind.line + 1,
+            keybind.definition,
+            keybind.suggestion
+        );
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            break;
+        }
+        match answer.trim() {
+            "" => descriptions.push((keybind.line, keybind.suggestion.clone())),
+            "-" => {}
+            "q" => break,
+            description => descriptions.push((keybind.line, description.to_owned())),
+        }
+    }
+    Ok(descriptions)
+}
+
+/// Inserts the description comments above their keybind, indented like
+/// it, and the boundaries around the keymap, which starts with a section
+pub fn apply(
+    content: &str,
+    descriptions: &[(usize, String)],
+    boundaries: Option<(usize, usize)>,
+) -> String {
+    let mut annotated = vec![];
+    for (i, line) in content.lines().enumerate() {
+        if boundaries.is_some_and(|(start, _)| start == i) {
+            annotated.push(format!("{}-- # {DEFAULT_TITLE}", indent(line)));
+            annotated.push(format!("{}-- ## {DEFAULT_SECTION}", indent(line)));
+        }
+        if let Some((_, description)) = descriptions.iter().find(|(l, _)| *l == i) {
+            annotated.push(format!("{}-- {description}", indent(line)));
+        }
+        annotated.push(line.to_owned());
+        if boundaries.is_some_and(|(_, end)| end == i) {
+            annotated.push(format!("{}-- #", indent(line)));
+        }
+    }
+    let mut annotated = annotated.join("\n");
+    if content.ends_with('\n') {
+        annotated.push('\n');
+    }
+    annotated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Haddock, Parser};
+
+    const CONFIG: &str = "\
+main = xmonad $ def `additionalKeysP` keys
+keys =
+  -- Launcher
+  [ (\"M-p\", spawn \"dmenu_run\")
+  , (\"M-S-c\", kill)
+  , (\"M-q\", spawn \"xmonad --restart\") -- restart
+  , ((mod1Mask, xK_F4), kill)
+  ]
+";
+
+    #[test]
+    fn unannotated_config() {
+        let found = scan(CONFIG);
+        assert_eq!(found.boundaries, Some((2, 5)));
+        let suggestions: Vec<_> = found
+            .undescribed
+            .iter()
+            .map(|u| (u.line, u.suggestion.as_str()))
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![(4, "kill"), (5, "spawn xmonad --restart")]
+        );
+
+        let descriptions = vec![(4, "Close the window".to_owned())];
+        let annotated = apply(CONFIG, &descriptions, found.boundaries);
+        assert!(annotated.contains(
+            "  -- # Keymap\n  -- ## General\n  -- Launcher\n  [ (\"M-p\", spawn \"dmenu_run\")\n  \
+             -- Close the window\n  , (\"M-S-c\", kill)\n"
+        ));
+        assert!(annotated.ends_with("-- restart\n  -- #\n  , ((mod1Mask, xK_F4), kill)\n  ]\n"));
+        // annotated once, nothing left to do
+        let rescan = scan(&apply(&annotated, &[(8, "Restart".to_owned())], None));
+        assert_eq!(rescan.undescribed, vec![]);
+        assert_eq!(rescan.boundaries, None);
+
+        let tokens = Parser::new(&annotated, Haddock::Strip, false)
+            .tokens()
+            .unwrap();
+        let keys: Vec<_> = tokens.keybinds().into_iter().map(|k| k.keys).collect();
+        assert_eq!(keys, vec!["M-p", "M-S-c"]);
+    }
+}
@@ -2,38 +2,152 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use crate::parser::Parser;
-use crate::token::{ScoredKeybind, Tokens};
-use crate::user_config::{self, UserConfig, FONT_SIZE, TITLE_FONT_SIZE};
+use crate::cache::ParseCache;
+use crate::check;
+use crate::compare;
+use crate::compose;
+use crate::display::{Desktop, Renderer, WindowMode};
+use crate::dump;
+use crate::exit::{Exit, Failure};
+use crate::export::{self, ClipboardFormat};
+use crate::gesture::Pinch;
+use crate::hotkey::{self, Hotkey};
+use crate::http::{self, SharedKeymap};
+use crate::includes;
+use crate::instance;
+use crate::layout::Layout;
+use crate::parser::{Haddock, ParseFailure, Parser};
+use crate::screen::{Listing, Panel, Screen};
+use crate::search::{filter_tokens, suggest, Matcher, SearchMode, SearchResults, SearchWeights};
+use crate::source::{self, SourceConfig};
+use crate::template::RowTemplate;
+use crate::theme::{DayNight, AUTO_TIME};
+use crate::token::{ActionKind, Chord, KeyStyle, ScoredKeybind, SectionSort, Tokens};
+use crate::tray;
+use crate::ui::{self, Density, Metrics, INPUT_ID, KEYMAP_ID};
+use crate::usage;
+use crate::user_config::{ThemeOverride, UserConfig, FONT_SIZE, TITLE_FONT_SIZE};
+use crate::view_model::ViewModel;
+use crate::watch;
 
+use clap::ValueEnum;
 use eyre::{eyre, Result};
-use fuzzy_matcher::skim::SkimMatcherV2;
-use iced::alignment::Horizontal;
-use iced::futures::TryFutureExt;
-use iced::widget::{self, column, container, horizontal_rule, scrollable, text, text_input, Text};
-use iced::{event, font, keyboard, subscription, theme, Color, Event, Font, Subscription, Theme};
-use iced::{executor, Application, Command, Element, Length, Padding};
-
-// TODO once stable drop once_cell crate and use `std::sync::{LazyLock, OnceLock}`
-use once_cell::sync::{Lazy, OnceCell};
-use std::fmt;
-use tokio::fs;
-use tracing::{debug, error, info, instrument, trace};
-
-// tokens parsed from xmonad config declared as static as it will
-// not change during the whole app lifetime
-static TOKENS: OnceCell<Tokens> = OnceCell::new();
-
-static FUZZY_MATCHER: Lazy<SkimMatcherV2> = Lazy::new(SkimMatcherV2::default);
-static INPUT_ID: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
+use iced::futures::future::{abortable, AbortHandle};
+use iced::widget::{self, column, container, horizontal_rule, scrollable, text, text_input};
+use iced::{event, font, keyboard, subscription, theme, touch, Event, Font, Subscription, Theme};
+use iced::{executor, window, Application, Command, Element, Length, Padding};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+use std::{borrow::Cow, env, fmt, io, sync::Arc, time::Duration};
+use tokio::{fs, process, time};
+use tracing::{debug, error, info, instrument, trace, warn};
+
 const DEFAULT_TITLE: &str = "Keymap";
 pub const FONT_MONO: Font = Font::with_name("JetBrains Mono");
+// how long keybinds added by a reload stay highlighted
+const HIGHLIGHT_DURATION: Duration = Duration::from_secs(3);
+// how long the match jumped to stays highlighted
+const FLASH_DURATION: Duration = Duration::from_millis(1500);
+// how long a notice, e.g. of a failed reload, stays shown
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+// font sizes factor of the presentation mode
+const PRESENTATION_SCALE: f32 = 2.0;
+// font sizes factor of the accessible mode
+const ACCESSIBLE_SCALE: f32 = 1.5;
+const ACCESSIBLE_THEME: &str = "high-contrast";
+// how often the time is checked to switch the `auto-time` theme
+const THEME_CLOCK_INTERVAL: Duration = Duration::from_secs(60);
+// path reading the config from the standard input, e.g. piped
+pub const STDIN_PATH: &str = "-";
+// reading from a network filesystem can hang
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+// default maximum width of the keys column, in characters
+const KEY_COLUMN: u16 = 24;
+// width of the window until resized, the default one of iced
+const WINDOW_WIDTH: f32 = 1024.0;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AppConfig {
+    pub profile: Profile,
     pub config_path: String,
+    // search query the app starts with
+    pub query: Option<String>,
+    // focus the search field once loaded, to type right away
+    pub focus_search: bool,
+    // titles of the sections collapsed at startup
+    pub collapsed_sections: Vec<String>,
+    // title of the section scrolled to once loaded
+    pub initial_section: Option<String>,
+    // ask before executing the command of a keybind
+    pub confirm_exec: bool,
+    // also fuzzy match the action expressions
+    pub search_actions: bool,
+    pub search_weights: SearchWeights,
+    pub search_mode: SearchMode,
+    pub search_matcher: Matcher,
+    // path of the keybinds usage log
+    pub usage_log: Option<String>,
+    pub haddock: Haddock,
+    // describe the keybinds without comment by their action
+    pub fallback_description: bool,
+    // the ignored keybinds are shown, greyed out, until toggled
+    pub show_ignored: bool,
+    // XKB layout the keys are shown with
+    pub layout: Option<Layout>,
+    // notation the keys are rendered with
+    pub key_style: KeyStyle,
+    // layout of the keybind rows, the default one when not set
+    pub row_template: Option<RowTemplate>,
+    pub clipboard_format: ClipboardFormat,
+    pub section_sort: SectionSort,
+    // start in presentation mode, showing the next section every
+    // `present_interval` seconds if set
+    pub present: bool,
+    pub present_interval: Option<u64>,
+    // only a filter line over the keybinds, see `minimal_view`
+    pub minimal: bool,
+    // `--pick`, the line printed for the keybind picked once the window
+    // is closed
+    pub pick: Option<Pick>,
+    // developer preview, the config is reloaded on change and the
+    // running instance is left alone
+    pub preview: bool,
+    // path of the keymap rendered side by side with this one
+    pub compare: Option<String>,
+    // high contrast, large fonts and labeled buttons, see `accessible`
+    pub accessible: bool,
+    // problems found in apekey.toml
+    pub warnings: Vec<String>,
+    // hide the window when it loses the focus, like a popup
+    pub close_on_focus_loss: bool,
+    // global hotkey toggling the window
+    pub hotkey: Option<Hotkey>,
+    // icon in the system tray
+    pub tray: bool,
+    // port of the local HTTP endpoint serving the keymap, see `http`
+    pub http_port: Option<u16>,
+    pub renderer: Renderer,
+    // windowed, maximized or fullscreen, when shown
+    pub window_mode: WindowMode,
+    // commands generating extra sections, run on each load
+    pub sources: Vec<SourceConfig>,
+    // path of the apekey.toml in use, if any
+    pub user_config_path: Option<String>,
     pub ui: Ui,
+    // name of the built-in theme the palette is based on
+    pub theme_name: String,
+    // what was set on the command line, applied again when the config is
+    // reloaded
+    pub overrides: Overrides,
+    // keymap loaded before the window opened, e.g. by `--pick`, shown
+    // without reading and parsing the config again
+    pub keymap: Option<Tokens>,
     pub theme: Theme,
+    // switch times and themes of the `auto-time` theme
+    pub day_night: Option<DayNight>,
+    // detected at startup, see `AppConfig::new`
+    pub desktop: Desktop,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +157,131 @@ pub struct Ui {
     pub keybind_size: u16,
     pub text_size: u16,
     pub error_size: u16,
+    // window background opacity, 1.0 is opaque
+    pub opacity: f32,
+    // maximum width of the keys column in characters, longer keys wrap
+    pub key_column: u16,
+    pub metrics: Metrics,
+    // width of the window, the long sections flow into sub-columns when
+    // wide enough
+    pub window_width: f32,
+}
+
+impl AppConfig {
+    /// The config for low-vision users, with the high contrast theme,
+    /// larger fonts and the icon buttons labeled
+    pub fn accessible(self) -> AppConfig {
+        let theme = crate::theme::builtin(ACCESSIBLE_THEME).unwrap_or(Theme::Dark);
+        AppConfig {
+            ui: Ui {
+                opacity: 1.0,
+                ..self.ui.scaled(ACCESSIBLE_SCALE)
+            },
+            theme_name: ACCESSIBLE_THEME.to_owned(),
+            theme,
+            day_night: None,
+            accessible: true,
+            ..self
+        }
+    }
+}
+
+/// What was set on the command line, it wins over apekey.toml at startup
+/// and when it is reloaded
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub profile: Option<Profile>,
+    // path of the keymap, from `--path`, `--profile` or a command
+    pub config_path: Option<String>,
+    pub query: Option<String>,
+    pub present: bool,
+    pub present_interval: Option<u64>,
+    pub minimal: bool,
+    pub pick: Option<Pick>,
+    pub preview: bool,
+    pub compare: Option<String>,
+    pub accessible: bool,
+    pub theme: ThemeOverride,
+}
+
+/// The keybind picked in `--pick` mode, as a line of its template. The
+/// line is shared across the reloads of the config and read by main once
+/// the window is closed.
+#[derive(Debug, Clone)]
+pub struct Pick {
+    pub template: RowTemplate,
+    pub picked: Arc<OnceCell<String>>,
+}
+
+impl Pick {
+    pub fn new(template: RowTemplate) -> Self {
+        Pick {
+            template,
+            picked: Arc::default(),
+        }
+    }
+
+    /// The line of the picked keybind, none if the window was closed
+    /// without picking
+    pub fn line(&self) -> Option<&str> {
+        self.picked.get().map(String::as_str)
+    }
+}
+
+impl Overrides {
+    /// Sets the keymap path, the profile and the theme of the config
+    pub fn apply(&self, config: &mut UserConfig) {
+        if let Some(path) = &self.config_path {
+            config.xmonad_config = path.clone();
+        }
+        if let Some(profile) = self.profile {
+            config.profile = Some(profile);
+        }
+        self.theme.apply(config);
+    }
+
+    /// The config of apekey.toml with what was set on the command line
+    pub fn config(&self, mut user_config: UserConfig, desktop: Desktop) -> AppConfig {
+        user_config.apply_wm(desktop.wm.as_deref());
+        self.apply(&mut user_config);
+        let profile = user_config.profile.unwrap_or_default();
+        let config = AppConfig::new(user_config, desktop);
+        let config = AppConfig {
+            profile,
+            query: self.query.clone().or(config.query),
+            present: self.present,
+            present_interval: self.present_interval,
+            minimal: self.minimal,
+            pick: self.pick.clone(),
+            preview: self.preview,
+            compare: self.compare.clone(),
+            overrides: self.clone(),
+            ..config
+        };
+        if self.accessible {
+            config.accessible()
+        } else {
+            config
+        }
+    }
+}
+
+impl Ui {
+    /// Returns the font sizes multiplied by `factor`
+    fn scaled(&self, factor: f32) -> Ui {
+        let scale = |size: u16| (f32::from(size) * factor).round() as u16;
+        Ui {
+            title_size: scale(self.title_size),
+            section_size: scale(self.section_size),
+            keybind_size: scale(self.keybind_size),
+            text_size: scale(self.text_size),
+            error_size: scale(self.error_size),
+            opacity: self.opacity,
+            key_column: self.key_column,
+            metrics: self.metrics,
+            window_width: self.window_width,
+        }
+    }
 }
 
 impl Default for Ui {
@@ -53,67 +292,477 @@ impl Default for Ui {
             keybind_size: FONT_SIZE,
             text_size: FONT_SIZE,
             error_size: FONT_SIZE,
+            opacity: 1.0,
+            key_column: KEY_COLUMN,
+            metrics: Metrics::from(Density::default()),
+            window_width: WINDOW_WIDTH,
         }
     }
 }
 
 pub struct Apekey {
     state: State,
-    input_value: String,
-    // this field is used to store the matching keybinds when fuzzy
-    // searching
-    tokens: Vec<ScoredKeybind>,
+    // what the keymap view shows, see `view_model`
+    view: ViewModel,
+    // the keymap rendered side by side with mine, see `compare`
+    compared: Option<Tokens>,
+    // true while the config is re-read, the current keymap stays rendered
+    reloading: bool,
+    // incremented on each load, the results of a superseded load are
+    // discarded
+    generation: u64,
+    // aborts the pending read or parse task of the current load
+    pipeline: Option<AbortHandle>,
+    // keymap of each file read, see `cache`
+    parsed: ParseCache,
+    show_diagnostics: bool,
+    // conflicts found in the loaded keymap
+    keymap_warnings: Vec<String>,
+    show_about: bool,
+    // false when the window was hidden by another apekey launch
+    visible: bool,
+    // command waiting for the user confirmation before being executed
+    pending_command: Option<String>,
+    // with the chord picked in the picker
+    show_chord_picker: bool,
+    // match jumped to, highlighted until the flash numbered the same ends
+    flashed: Option<ScoredKeybind>,
+    flash: u64,
+    // notice shown under the header until the toast numbered the same
+    // expires, e.g. a reload failed and the previous keymap is kept
+    toast: Option<String>,
+    toasts: u64,
+    // font sizes factor set by pinching the touchpad or touchscreen
+    zoom: f32,
+    pinch: Pinch,
+    // the keymap served by the HTTP endpoint, if enabled
+    served_keymap: SharedKeymap,
     config: AppConfig,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    ConfigRead(String),
-    ConfigError(String),
-    ParsingDone(Tokens),
-    ParsingError(String),
+    ConfigRead(u64, String),
+    ConfigError(u64, String),
+    ParsingDone(u64, Tokens),
+    // with the line the parser failed at, if known
+    ParsingError(u64, String, Option<usize>),
+    // the keymap compared with mine
+    ComparedParsed(u64, Tokens),
+    LoadCancelled(u64),
     InputChanged(String),
-    TokensFiltered(Vec<ScoredKeybind>),
+    TokensFiltered(SearchResults),
+    SuggestionsFound(String, Vec<ScoredKeybind>),
+    ActionFilterSelected(ActionFilter),
+    ActionFilterCycled,
+    LayoutFilterSelected(LayoutFilter),
+    AllSectionsToggled,
+    EscapePressed,
+    SectionToggled(String),
+    GroupingToggled,
+    SearchModeToggled,
+    // switches between fuzzy and regex matching
+    MatcherToggled,
+    // goes to the next or previous match in the `jump` search mode
+    MatchCycled(isize),
+    FlashExpired(u64),
+    ToastExpired(u64),
+    ChordPickerToggled,
+    // index in `token::MODIFIERS`
+    ChordModifierToggled(usize),
+    ChordKeySelected(String),
+    ChordCleared,
+    DiagnosticsDismissed,
+    AboutToggled,
+    IgnoredToggled,
+    PresentationToggled,
+    PresentationEnded,
+    PresentationMoved(isize),
     TabPressed { shift: bool },
+    ToggleWindow,
+    // time to check whether the `auto-time` theme changes
+    ThemeClockTicked,
+    ShowWindow,
+    // cycles between the xmonad and the XCompose keymaps
+    ProfileSwitched,
+    Quit,
+    // the line of the keybind picked in `--pick` mode
+    Picked(String),
+    FocusLost,
+    WindowResized(u32),
+    Touch(touch::Event),
+    Reload,
+    UserConfigChanged,
+    UserConfigRead(Result<Box<UserConfig>, String>),
+    HighlightExpired,
+    SearchSubmitted,
+    Execute(String),
+    CopySource(String),
+    // copies the keybinds listed, the search results if searching
+    ResultsCopied,
+    ExecuteConfirmed,
+    ExecuteCancelled,
+    Executed(Result<(), String>),
+    OpenEditor,
+    // a file the keymap is read from, clicked in the header
+    OpenFile(String),
+    EditorOpened(Result<(), String>),
     FontLoaded(Result<(), font::Error>),
 }
 
 impl fmt::Display for Message {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let message = match self {
-            Message::ConfigRead(_) => "ConfigRead".into(),
-            Message::ConfigError(_) => "ConfigError".into(),
-            Message::ParsingDone(_) => "ParsingDone".into(),
-            Message::ParsingError(_) => "ParsingError".into(),
+            Message::ConfigRead(generation, _) => format!("ConfigRead #{generation}"),
+            Message::ConfigError(generation, _) => format!("ConfigError #{generation}"),
+            Message::ParsingDone(generation, _) => format!("ParsingDone #{generation}"),
+            Message::ParsingError(generation, ..) => format!("ParsingError #{generation}"),
+            Message::ComparedParsed(generation, _) => format!("ComparedParsed #{generation}"),
+            Message::LoadCancelled(generation) => format!("LoadCancelled #{generation}"),
             Message::InputChanged(input) => format!("InputChanged: {input}"),
             Message::TokensFiltered(_) => "TokensFiltered".into(),
+            Message::SuggestionsFound(query, _) => format!("SuggestionsFound: {query}"),
+            Message::ActionFilterSelected(filter) => format!("ActionFilterSelected: {filter}"),
+            Message::ActionFilterCycled => "ActionFilterCycled".into(),
+            Message::LayoutFilterSelected(filter) => format!("LayoutFilterSelected: {filter}"),
+            Message::AllSectionsToggled => "AllSectionsToggled".into(),
+            Message::EscapePressed => "EscapePressed".into(),
+            Message::SectionToggled(title) => format!("SectionToggled: {title}"),
+            Message::GroupingToggled => "GroupingToggled".into(),
+            Message::SearchModeToggled => "SearchModeToggled".into(),
+            Message::MatcherToggled => "MatcherToggled".into(),
+            Message::MatchCycled(step) => format!("MatchCycled: {step}"),
+            Message::FlashExpired(flash) => format!("FlashExpired #{flash}"),
+            Message::ToastExpired(toast) => format!("ToastExpired #{toast}"),
+            Message::ChordPickerToggled => "ChordPickerToggled".into(),
+            Message::ChordModifierToggled(modifier) => format!("ChordModifierToggled: {modifier}"),
+            Message::ChordKeySelected(key) => format!("ChordKeySelected: {key}"),
+            Message::ChordCleared => "ChordCleared".into(),
+            Message::DiagnosticsDismissed => "DiagnosticsDismissed".into(),
+            Message::AboutToggled => "AboutToggled".into(),
+            Message::IgnoredToggled => "IgnoredToggled".into(),
+            Message::PresentationToggled => "PresentationToggled".into(),
+            Message::PresentationEnded => "PresentationEnded".into(),
+            Message::PresentationMoved(step) => format!("PresentationMoved: {step}"),
             Message::TabPressed { shift } => format!("TabPressed, shift {shift}"),
+            Message::ToggleWindow => "ToggleWindow".into(),
+            Message::ThemeClockTicked => "ThemeClockTicked".into(),
+            Message::ShowWindow => "ShowWindow".into(),
+            Message::ProfileSwitched => "ProfileSwitched".into(),
+            Message::Quit => "Quit".into(),
+            Message::Picked(line) => format!("Picked: {line}"),
+            Message::FocusLost => "FocusLost".into(),
+            Message::WindowResized(width) => format!("WindowResized: {width}"),
+            Message::Touch(event) => format!("Touch: {event:?}"),
+            Message::Reload => "Reload".into(),
+            Message::UserConfigChanged => "UserConfigChanged".into(),
+            Message::UserConfigRead(_) => "UserConfigRead".into(),
+            Message::HighlightExpired => "HighlightExpired".into(),
+            Message::SearchSubmitted => "SearchSubmitted".into(),
+            Message::Execute(command) => format!("Execute: {command}"),
+            Message::CopySource(_) => "CopySource".into(),
+            Message::ResultsCopied => "ResultsCopied".into(),
+            Message::ExecuteConfirmed => "ExecuteConfirmed".into(),
+            Message::ExecuteCancelled => "ExecuteCancelled".into(),
+            Message::Executed(_) => "Executed".into(),
+            Message::OpenEditor => "OpenEditor".into(),
+            Message::OpenFile(path) => format!("OpenFile: {path}"),
+            Message::EditorOpened(_) => "EditorOpened".into(),
             Message::FontLoaded(_) => "FontLoaded".into(),
         };
         write!(f, "{message}")
     }
 }
 
+impl Apekey {
+    /// Scrolls to the current match and flashes it, its section is
+    /// expanded if collapsed
+    fn jump_to_match(&mut self) -> Command<Message> {
+        let Some((keybind, offset)) = self.view.jump_target() else {
+            return Command::none();
+        };
+        self.flash += 1;
+        self.flashed = Some(keybind);
+        let flash = self.flash;
+        Command::batch(vec![
+            scrollable::snap_to(
+                KEYMAP_ID.clone(),
+                scrollable::RelativeOffset { x: 0.0, y: offset },
+            ),
+            Command::perform(time::sleep(FLASH_DURATION), move |_| {
+                Message::FlashExpired(flash)
+            }),
+        ])
+    }
+
+    /// What the window shows, decided from the state apart from the
+    /// widgets, see `view`
+    fn screen(&self) -> Screen<'_> {
+        match &self.state {
+            State::ReadingConfig => Screen::Progress("▪▫▫ Reading xmonad.hs"),
+            State::ParsingConfig => Screen::Progress("▪▪▫ Parsing keymap   "),
+            State::Presenting { section } => Screen::Presenting {
+                keymap: self.view.displayed_keymap(),
+                section: *section,
+            },
+            State::Error { err, .. } => Screen::Error(err),
+            State::RenderKeybinds => {
+                let mut panels = vec![];
+                if !self.config.minimal {
+                    if self.show_chord_picker {
+                        panels.push(Panel::ChordPicker);
+                    }
+                    if self.show_about {
+                        panels.push(Panel::About);
+                    }
+                    if self.show_diagnostics {
+                        let warnings: Vec<String> = self
+                            .config
+                            .warnings
+                            .iter()
+                            .chain(&self.keymap_warnings)
+                            .cloned()
+                            .collect();
+                        if !warnings.is_empty() {
+                            panels.push(Panel::Diagnostics(warnings));
+                        }
+                    }
+                }
+                if let Some(command) = &self.pending_command {
+                    panels.push(Panel::Confirmation(command));
+                }
+                if let Some(toast) = &self.toast {
+                    panels.push(Panel::Toast(toast));
+                }
+                if self.config.minimal {
+                    return Screen::Minimal {
+                        query: &self.view.input_value,
+                        panels,
+                        listing: self.listing(),
+                    };
+                }
+                Screen::Keymap {
+                    title: (self.view.displayed_keymap().title.clone())
+                        .unwrap_or_else(|| DEFAULT_TITLE.to_owned()),
+                    search: &self.view,
+                    panels,
+                    listing: self.listing(),
+                }
+            }
+        }
+    }
+
+    /// The keybinds under the header, the minimal mode lists them without
+    /// sections nor comparison
+    fn listing(&self) -> Listing<'_> {
+        let minimal = self.config.minimal;
+        if self.view.input_value.is_empty() {
+            return match &self.compared {
+                Some(compared) if !minimal => Listing::Comparison {
+                    rows: compare::align(&self.view.displayed_keymap(), compared),
+                    title: compared.title.as_deref(),
+                },
+                _ if minimal => Listing::Matches(
+                    (self.view.filtered_keymap().sections.iter())
+                        .flat_map(|section| section.keybinds.iter().map(ScoredKeybind::from))
+                        .collect(),
+                ),
+                _ => Listing::Sections {
+                    keymap: self.view.filtered_keymap(),
+                    collapsed: &self.view.collapsed,
+                },
+            };
+        }
+        if self.view.matching().next().is_none() {
+            return Listing::Empty {
+                query: &self.view.input_value,
+                suggestions: &self.view.suggestions,
+            };
+        }
+        match self.view.search_mode {
+            SearchMode::Jump if !minimal => {
+                let mut keymap = self.view.filtered_keymap().into_owned();
+                if let Some((section, row)) = self.flashed.as_ref().and_then(|k| keymap.position(k))
+                {
+                    keymap.sections[section].keybinds[row].highlight = true;
+                }
+                Listing::Sections {
+                    keymap: Cow::Owned(keymap),
+                    collapsed: &self.view.collapsed,
+                }
+            }
+            SearchMode::Sections if !minimal => {
+                let matches: Vec<ScoredKeybind> = self.view.matching().cloned().collect();
+                Listing::Sections {
+                    keymap: Cow::Owned(self.view.displayed_keymap().matching(&matches)),
+                    collapsed: &self.view.collapsed,
+                }
+            }
+            _ => Listing::Matches(self.view.matching().cloned().collect()),
+        }
+    }
+
+    fn panel_view(
+        &self,
+        panel: Panel,
+        config: &AppConfig,
+        palette: &theme::Palette,
+    ) -> Element<'static, Message> {
+        match panel {
+            Panel::ChordPicker => {
+                ui::chord_picker(&self.view.chord, self.view.keymap.chord_keys(), config)
+            }
+            Panel::About => ui::about(&self.view.keymap, config, palette),
+            Panel::Diagnostics(warnings) => ui::diagnostics(&warnings, config, palette),
+            Panel::Confirmation(command) => ui::confirmation(command, config),
+            Panel::Toast(toast) => {
+                ui::toast(toast, Message::ToastExpired(self.toasts), config, palette)
+            }
+        }
+    }
+
+    fn listing_view(
+        &self,
+        listing: Listing,
+        config: &AppConfig,
+        palette: &theme::Palette,
+    ) -> widget::Scrollable<'static, Message> {
+        match listing {
+            Listing::Sections { keymap, collapsed } => {
+                ui::keymap_scrollable(keymap.view(config, palette, collapsed))
+            }
+            Listing::Matches(matches) if config.minimal => {
+                ui::dense_list(matches.into_iter(), config, palette)
+            }
+            Listing::Matches(matches) => {
+                ui::keymap_scrollable(matches.iter().fold(column![], |column, keybind| {
+                    column
+                        .push(keybind.view(config, palette))
+                        .spacing(config.ui.metrics.row_spacing)
+                        .padding(config.ui.metrics.keymap_padding)
+                }))
+            }
+            Listing::Comparison { rows, title } => {
+                ui::keymap_scrollable(ui::comparison(&rows, title, config, palette))
+            }
+            Listing::Empty { query, suggestions } => {
+                scrollable(ui::empty_state(query, suggestions, config, palette))
+            }
+        }
+    }
+
+    /// Shows a notice for a few seconds
+    fn show_toast(&mut self, toast: String) -> Command<Message> {
+        self.toasts += 1;
+        self.toast = Some(toast);
+        let toasts = self.toasts;
+        Command::perform(time::sleep(TOAST_DURATION), move |_| {
+            Message::ToastExpired(toasts)
+        })
+    }
+
+    /// A failed reload keeps the keymap loaded, e.g. a file read while
+    /// being saved, an error is shown when nothing was loaded yet
+    fn load_failed(&mut self, err: String, line: Option<usize>) -> Command<Message> {
+        self.pipeline = None;
+        if std::mem::take(&mut self.reloading) {
+            warn!("reload failed, the previous keymap is kept: {err}");
+            let reason = err.lines().next().unwrap_or_default();
+            return self.show_toast(format!("Reload failed, previous keymap kept: {reason}"));
+        }
+        error!("{}", err);
+        self.state = State::Error { err, line };
+        Command::none()
+    }
+
+    /// Reads the config in a new load generation, cancelling the pending
+    /// one if any
+    fn load_config(&mut self) -> Command<Message> {
+        self.generation += 1;
+        let generation = self.generation;
+        if let Some(pipeline) = self.pipeline.take() {
+            debug!("cancelling load #{}", generation - 1);
+            pipeline.abort();
+        }
+        let (task, handle) = abortable(read_config(self.config.config_path.clone()));
+        self.pipeline = Some(handle);
+        Command::perform(task, move |result| match result {
+            Ok(Ok(content)) => Message::ConfigRead(generation, content),
+            Ok(Err(e)) => Message::ConfigError(generation, e.to_string()),
+            Err(_) => Message::LoadCancelled(generation),
+        })
+    }
+
+    fn is_superseded(&self, generation: u64) -> bool {
+        if generation != self.generation {
+            debug!("discarding the result of the superseded load #{generation}");
+        }
+        generation != self.generation
+    }
+
+    /// The config with the font sizes scaled by the pinch zoom
+    fn zoomed_config(&self) -> Cow<'_, AppConfig> {
+        if self.zoom == 1.0 {
+            Cow::Borrowed(&self.config)
+        } else {
+            Cow::Owned(AppConfig {
+                ui: self.config.ui.scaled(self.zoom),
+                ..self.config.clone()
+            })
+        }
+    }
+}
+
 impl Application for Apekey {
     type Executor = executor::Default;
     type Flags = AppConfig;
     type Message = Message;
     type Theme = Theme;
 
-    fn new(flags: AppConfig) -> (Apekey, Command<Message>) {
-        let path = flags.config_path.clone();
-        (
-            Apekey {
-                tokens: vec![],
-                input_value: "".to_owned(),
-                state: State::ReadingConfig,
-                config: flags,
+    fn new(mut flags: AppConfig) -> (Apekey, Command<Message>) {
+        // the compared keymap is parsed along with the config
+        let keymap = flags.keymap.take().filter(|_| flags.compare.is_none());
+        let mut app = Apekey {
+            view: ViewModel {
+                collapsed: flags.collapsed_sections.iter().cloned().collect(),
+                search_mode: flags.search_mode,
+                matcher: flags.search_matcher,
+                show_ignored: flags.show_ignored,
+                ..ViewModel::default()
             },
+            flashed: None,
+            flash: 0,
+            toast: None,
+            toasts: 0,
+            show_chord_picker: false,
+            show_diagnostics: !flags.warnings.is_empty(),
+            show_about: false,
+            keymap_warnings: vec![],
+            visible: true,
+            pending_command: None,
+            state: State::ReadingConfig,
+            compared: None,
+            reloading: false,
+            generation: 0,
+            pipeline: None,
+            parsed: ParseCache::default(),
+            zoom: 1.0,
+            pinch: Pinch::default(),
+            served_keymap: SharedKeymap::default(),
+            config: flags,
+        };
+        app.view
+            .set_query(app.config.query.clone().unwrap_or_default());
+        let load = match keymap {
+            Some(tokens) => app.update(Message::ParsingDone(app.generation, tokens)),
+            None => app.load_config(),
+        };
+        let show = show_window(app.config.window_mode);
+        (
+            app,
             Command::batch(vec![
-                Command::perform(read_config(path), |result| match result {
-                    Ok(content) => Message::ConfigRead(content),
-                    Err(e) => Message::ConfigError(e.to_string()),
-                }),
+                load,
+                show,
                 font::load(include_bytes!("../assets/fonts/JetBrainsMono-Regular.ttf").as_slice())
                     .map(Message::FontLoaded),
             ]),
@@ -126,7 +775,7 @@ impl Application for Apekey {
 
     #[instrument(skip_all)]
     fn subscription(&self) -> Subscription<Message> {
-        subscription::events_with(|event, status| match (event, status) {
+        let keyboard = subscription::events_with(|event, status| match (event, status) {
             (
                 Event::Keyboard(keyboard::Event::KeyPressed {
                     key_code: keyboard::KeyCode::Tab,
@@ -137,59 +786,464 @@ impl Application for Apekey {
             ) => Some(Message::TabPressed {
                 shift: modifiers.shift(),
             }),
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: keyboard::KeyCode::I,
+                    modifiers,
+                }),
+                _,
+            ) if modifiers.control() => Some(Message::AboutToggled),
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: keyboard::KeyCode::H,
+                    modifiers,
+                }),
+                _,
+            ) if modifiers.control() => Some(Message::IgnoredToggled),
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: keyboard::KeyCode::R,
+                    modifiers,
+                }),
+                _,
+            ) if modifiers.control() => Some(Message::MatcherToggled),
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: keyboard::KeyCode::C,
+                    modifiers,
+                }),
+                _,
+            ) if modifiers.control() && modifiers.shift() => Some(Message::ResultsCopied),
+            (Event::Window(window::Event::Unfocused), _) => Some(Message::FocusLost),
+            (Event::Window(window::Event::Resized { width, .. }), _) => {
+                Some(Message::WindowResized(width))
+            }
+            (Event::Touch(event), _) => Some(Message::Touch(event)),
+            (Event::Keyboard(keyboard::Event::CharacterReceived('r')), event::Status::Ignored) => {
+                Some(Message::Reload)
+            }
+            (Event::Keyboard(keyboard::Event::CharacterReceived('g')), event::Status::Ignored) => {
+                Some(Message::GroupingToggled)
+            }
+            (Event::Keyboard(keyboard::Event::CharacterReceived('p')), event::Status::Ignored) => {
+                Some(Message::PresentationToggled)
+            }
+            (Event::Keyboard(keyboard::Event::CharacterReceived('e')), event::Status::Ignored) => {
+                Some(Message::OpenEditor)
+            }
+            (Event::Keyboard(keyboard::Event::CharacterReceived('f')), event::Status::Ignored) => {
+                Some(Message::ActionFilterCycled)
+            }
+            (Event::Keyboard(keyboard::Event::CharacterReceived('c')), event::Status::Ignored) => {
+                Some(Message::AllSectionsToggled)
+            }
+            (Event::Keyboard(keyboard::Event::CharacterReceived('n')), event::Status::Ignored) => {
+                Some(Message::MatchCycled(1))
+            }
+            (Event::Keyboard(keyboard::Event::CharacterReceived('N')), event::Status::Ignored) => {
+                Some(Message::MatchCycled(-1))
+            }
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed { key_code, .. }),
+                event::Status::Ignored,
+            ) => match key_code {
+                keyboard::KeyCode::Escape => Some(Message::EscapePressed),
+                keyboard::KeyCode::Left => Some(Message::PresentationMoved(-1)),
+                keyboard::KeyCode::Right => Some(Message::PresentationMoved(1)),
+                _ => None,
+            },
             _ => None,
-        })
+        });
+        let mut subscriptions = vec![keyboard];
+        if let Some(path) = &self.config.user_config_path {
+            subscriptions.push(watch::subscription(
+                path.clone(),
+                Message::UserConfigChanged,
+            ));
+        }
+        if self.config.preview {
+            subscriptions.push(watch::subscription(
+                self.config.config_path.clone(),
+                Message::Reload,
+            ));
+        } else if self.config.pick.is_none() && self.config.compare.is_none() {
+            // a picker is spawned by a script and a comparison is opened
+            // next to the running instance, neither is the instance the
+            // next launches toggle
+            subscriptions.push(instance::subscription());
+            if let Some(hotkey) = &self.config.hotkey {
+                subscriptions.push(hotkey::subscription(hotkey.clone()));
+            }
+            if self.config.tray {
+                subscriptions.push(tray::subscription());
+            }
+            if let Some(port) = self.config.http_port {
+                subscriptions.push(http::subscription(
+                    port,
+                    self.served_keymap.clone(),
+                    self.config.search_matcher,
+                    self.config.search_actions,
+                    self.config.search_weights,
+                ));
+            }
+        }
+        if self.config.day_night.is_some() {
+            subscriptions
+                .push(iced::time::every(THEME_CLOCK_INTERVAL).map(|_| Message::ThemeClockTicked));
+        }
+        if let (State::Presenting { .. }, Some(interval)) =
+            (&self.state, self.config.present_interval)
+        {
+            subscriptions.push(
+                iced::time::every(Duration::from_secs(interval.max(1)))
+                    .map(|_| Message::PresentationMoved(1)),
+            );
+        }
+        Subscription::batch(subscriptions)
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Message> {
         trace!("{}", message);
         match message {
-            Message::ConfigRead(config) => {
+            Message::ConfigRead(generation, config) => {
+                if self.is_superseded(generation) {
+                    return Command::none();
+                }
                 info!("xmonad configuration file was read successfully.");
-                self.state = State::ParsingConfig;
-                Command::perform(parse(config), |result| match result {
-                    Ok(tokens) => Message::ParsingDone(tokens),
-                    Err(e) => Message::ParsingError(e.to_string()),
-                })
-            }
-            Message::ParsingDone(tokens) => {
-                TOKENS.set(tokens).unwrap();
-                let tokens = TOKENS.get().unwrap();
+                if !self.reloading {
+                    self.state = State::ParsingConfig;
+                }
+                let (task, handle) =
+                    abortable(parse(config, self.config.clone(), self.parsed.clone()));
+                self.pipeline = Some(handle);
+                let parsing = Command::perform(task, move |result| match result {
+                    Ok(Ok(tokens)) => Message::ParsingDone(generation, tokens),
+                    Ok(Err(e)) => Message::ParsingError(
+                        generation,
+                        e.to_string(),
+                        e.downcast_ref::<ParseFailure>().map(|f| f.line),
+                    ),
+                    Err(_) => Message::LoadCancelled(generation),
+                });
+                match &self.config.compare {
+                    Some(path) => Command::batch(vec![
+                        parsing,
+                        Command::perform(
+                            parse_compared(path.clone(), self.config.clone(), self.parsed.clone()),
+                            move |result| match result {
+                                Ok(tokens) => Message::ComparedParsed(generation, tokens),
+                                Err(e) => Message::ConfigError(generation, e.to_string()),
+                            },
+                        ),
+                    ]),
+                    None => parsing,
+                }
+            }
+            Message::ComparedParsed(generation, tokens) => {
+                if !self.is_superseded(generation) {
+                    self.compared = Some(tokens);
+                }
+                Command::none()
+            }
+            Message::LoadCancelled(generation) => {
+                debug!("load #{generation} cancelled");
+                Command::none()
+            }
+            Message::ParsingDone(generation, mut tokens) => {
+                if self.is_superseded(generation) {
+                    return Command::none();
+                }
+                self.pipeline = None;
                 info!(
                     "parsing done, sections {}, keybinds {}",
                     tokens.section_count(),
                     tokens.keybind_count()
                 );
-                self.state = State::RenderKeybinds;
-                Command::none()
+                let mut commands = vec![];
+                if self.reloading {
+                    tokens.highlight_new(&self.view.keymap);
+                    commands.push(Command::perform(time::sleep(HIGHLIGHT_DURATION), |_| {
+                        Message::HighlightExpired
+                    }));
+                } else if self.config.focus_search {
+                    commands.push(text_input::focus(INPUT_ID.clone()));
+                }
+                let warnings = check::keymap_warnings(&tokens);
+                if !warnings.is_empty() && warnings != self.keymap_warnings {
+                    self.show_diagnostics = true;
+                }
+                self.keymap_warnings = warnings;
+                if self.config.http_port.is_some() {
+                    if let Ok(mut served) = self.served_keymap.write() {
+                        *served = tokens.clone();
+                    }
+                }
+                self.view.keymap = tokens;
+                if let Some(offset) = self
+                    .config
+                    .initial_section
+                    .as_deref()
+                    .filter(|_| !self.reloading)
+                    .and_then(|title| self.view.section_offset(title))
+                {
+                    commands.push(scrollable::snap_to(
+                        KEYMAP_ID.clone(),
+                        scrollable::RelativeOffset { x: 0.0, y: offset },
+                    ));
+                }
+                self.state = if self.config.present && !self.reloading {
+                    State::Presenting { section: 0 }
+                } else {
+                    State::RenderKeybinds
+                };
+                self.reloading = false;
+                // the app was started with a query, or a search is
+                // ongoing during a reload, filter right away
+                if !self.view.input_value.is_empty() {
+                    commands.push(Command::perform(
+                        filter_tokens(
+                            self.view.keymap.keybinds(),
+                            self.view.input_value.clone(),
+                            self.view.matcher,
+                            self.config.search_actions,
+                            self.config.search_weights,
+                        ),
+                        Message::TokensFiltered,
+                    ));
+                }
+                Command::batch(commands)
             }
-            Message::ConfigError(err) => {
-                error!("{}", err);
-                self.state = State::Error(err);
-                Command::none()
+            Message::Reload => {
+                match self.state {
+                    // a pending reload is superseded by this one
+                    State::RenderKeybinds => self.reloading = true,
+                    // e.g. a fixed config in preview
+                    State::Error { .. } | State::ParsingConfig => self.state = State::ReadingConfig,
+                    State::ReadingConfig => {}
+                    State::Presenting { .. } => return Command::none(),
+                }
+                info!("reloading xmonad configuration");
+                self.load_config()
+            }
+            Message::UserConfigChanged => {
+                info!("apekey config changed, applying it");
+                Command::perform(
+                    read_user_config(self.config.user_config_path.clone()),
+                    Message::UserConfigRead,
+                )
             }
-            Message::ParsingError(err) => {
-                error!("{}", err);
-                self.state = State::Error(err);
+            Message::UserConfigRead(result) => {
+                match result {
+                    Ok(user_config) => {
+                        let window_width = self.config.ui.window_width;
+                        self.config = self
+                            .config
+                            .overrides
+                            .config(*user_config, self.config.desktop.clone());
+                        self.config.ui.window_width = window_width;
+                        self.show_diagnostics = !self.config.warnings.is_empty();
+                    }
+                    // e.g. saved while being edited, the current config stays
+                    Err(e) => {
+                        warn!("failed to read the apekey config: {e}");
+                        self.config.warnings = vec![e];
+                        self.show_diagnostics = true;
+                        return Command::none();
+                    }
+                }
+                // the parsing options, layout or usage log may have changed
+                self.update(Message::Reload)
+            }
+            Message::HighlightExpired => {
+                self.view.keymap.clear_highlight();
+                for keybind in &mut self.view.tokens {
+                    keybind.highlight = false;
+                }
                 Command::none()
             }
+            Message::ConfigError(generation, err) => {
+                if self.is_superseded(generation) {
+                    return Command::none();
+                }
+                self.load_failed(err, None)
+            }
+            Message::ParsingError(generation, err, line) => {
+                if self.is_superseded(generation) {
+                    return Command::none();
+                }
+                self.load_failed(err, line)
+            }
             Message::InputChanged(value) => {
-                self.input_value = value.clone();
+                self.view.set_query(value.clone());
                 if value.is_empty() {
                     Command::none()
                 } else {
                     Command::perform(
                         filter_tokens(
-                            TOKENS.get().expect("TOKENS not initialized!").keybinds(),
+                            self.view.keymap.keybinds(),
                             value,
+                            self.view.matcher,
+                            self.config.search_actions,
+                            self.config.search_weights,
                         ),
-                        |tokens| -> Message { Message::TokensFiltered(tokens) },
+                        Message::TokensFiltered,
+                    )
+                }
+            }
+            Message::TokensFiltered(results) => {
+                let jump = self.view.set_results(results);
+                info!(
+                    "fuzzy sorting done, matching tokens {}",
+                    self.view.tokens.len()
+                );
+                // only a fuzzy search has suggestions, a regex matching
+                // nothing is taken as is
+                if self.view.tokens.is_empty()
+                    && self.view.is_searching()
+                    && self.view.matcher == Matcher::Fuzzy
+                {
+                    let query = self.view.input_value.clone();
+                    Command::perform(
+                        suggest(self.view.keymap.keybinds(), query.clone()),
+                        move |suggestions| Message::SuggestionsFound(query.clone(), suggestions),
                     )
+                } else if jump {
+                    self.jump_to_match()
+                } else {
+                    Command::none()
+                }
+            }
+            Message::SuggestionsFound(query, suggestions) => {
+                // the query may have changed while looking for suggestions
+                if query == self.view.input_value {
+                    self.view.suggestions = suggestions;
+                }
+                Command::none()
+            }
+            Message::ActionFilterSelected(filter) => {
+                self.view.action_filter = filter;
+                Command::none()
+            }
+            Message::ActionFilterCycled => {
+                self.view.cycle_action_filter();
+                Command::none()
+            }
+            Message::LayoutFilterSelected(filter) => {
+                self.view.layout_filter = filter;
+                Command::none()
+            }
+            Message::AllSectionsToggled => {
+                self.view.toggle_all_sections();
+                Command::none()
+            }
+            // closes what is open, one thing at a time
+            Message::EscapePressed => {
+                if let State::Presenting { .. } = self.state {
+                    self.update(Message::PresentationEnded)
+                } else if self.pending_command.is_some() {
+                    self.update(Message::ExecuteCancelled)
+                } else if self.show_about {
+                    self.update(Message::AboutToggled)
+                } else if self.show_diagnostics {
+                    self.update(Message::DiagnosticsDismissed)
+                } else if self.config.pick.is_some() {
+                    self.update(Message::Quit)
+                } else {
+                    Command::none()
                 }
             }
-            Message::TokensFiltered(tokens) => {
-                self.tokens = tokens;
-                info!("fuzzy sorting done, matching tokens {}", self.tokens.len());
+            Message::SectionToggled(title) => {
+                self.view.toggle_section(title);
+                Command::none()
+            }
+            Message::GroupingToggled => {
+                self.view.by_modifier = !self.view.by_modifier;
+                Command::none()
+            }
+            Message::SearchModeToggled => {
+                if self.view.cycle_search_mode() {
+                    self.jump_to_match()
+                } else {
+                    Command::none()
+                }
+            }
+            Message::MatcherToggled => {
+                self.view.matcher = self.view.matcher.toggle();
+                info!("{:?} matching", self.view.matcher);
+                self.update(Message::InputChanged(self.view.input_value.clone()))
+            }
+            Message::MatchCycled(step) => {
+                if self.view.cycle_match(step) {
+                    self.jump_to_match()
+                } else {
+                    Command::none()
+                }
+            }
+            Message::ToastExpired(toast) => {
+                if toast == self.toasts {
+                    self.toast = None;
+                }
+                Command::none()
+            }
+            Message::FlashExpired(flash) => {
+                if flash == self.flash {
+                    self.flashed = None;
+                }
+                Command::none()
+            }
+            Message::ChordPickerToggled => {
+                self.show_chord_picker = !self.show_chord_picker;
+                // a hidden chord does not filter the keymap
+                self.view.chord = Chord::default();
+                Command::none()
+            }
+            Message::ChordModifierToggled(modifier) => {
+                self.view.chord.toggle(modifier);
+                Command::none()
+            }
+            Message::ChordKeySelected(key) => {
+                self.view.chord.key = Some(key);
+                Command::none()
+            }
+            Message::ChordCleared => {
+                self.view.chord = Chord::default();
+                Command::none()
+            }
+            Message::DiagnosticsDismissed => {
+                self.show_diagnostics = false;
+                Command::none()
+            }
+            Message::AboutToggled => {
+                self.show_about = !self.show_about;
+                Command::none()
+            }
+            Message::IgnoredToggled => {
+                self.view.show_ignored = !self.view.show_ignored;
+                Command::none()
+            }
+            Message::PresentationToggled => match self.state {
+                State::RenderKeybinds => {
+                    self.state = State::Presenting { section: 0 };
+                    Command::none()
+                }
+                State::Presenting { .. } => self.update(Message::PresentationEnded),
+                _ => Command::none(),
+            },
+            Message::PresentationEnded => {
+                if let State::Presenting { .. } = self.state {
+                    self.state = State::RenderKeybinds;
+                    return text_input::focus(INPUT_ID.clone());
+                }
+                Command::none()
+            }
+            Message::PresentationMoved(step) => {
+                if let State::Presenting { section } = self.state {
+                    // wraps around so that the timer loops over the sections
+                    let count = self.view.displayed_keymap().section_count().max(1) as isize;
+                    self.state = State::Presenting {
+                        section: (section as isize + step).rem_euclid(count) as usize,
+                    };
+                }
                 Command::none()
             }
             Message::TabPressed { shift } => {
@@ -201,6 +1255,168 @@ impl Application for Apekey {
                     widget::focus_next()
                 }
             }
+            Message::ToggleWindow => {
+                self.visible = !self.visible;
+                debug!("message: toggle window, visible {}", self.visible);
+                if self.visible {
+                    Command::batch(vec![
+                        show_window(self.config.window_mode),
+                        window::gain_focus(),
+                    ])
+                } else {
+                    window::change_mode(window::Mode::Hidden)
+                }
+            }
+            Message::ThemeClockTicked => {
+                if let Some(day_night) = &self.config.day_night {
+                    let (name, theme) = day_night.theme(crate::theme::local_time());
+                    if name != self.config.theme_name {
+                        info!("switching to the {name} theme");
+                        self.config.theme_name = name.to_owned();
+                        self.config.theme = theme;
+                    }
+                }
+                Command::none()
+            }
+            Message::ShowWindow => {
+                if self.visible {
+                    window::gain_focus()
+                } else {
+                    self.update(Message::ToggleWindow)
+                }
+            }
+            Message::ProfileSwitched => {
+                let (profile, path) = match self.config.profile {
+                    Profile::Xmonad => (Profile::Xcompose, compose::default_path()),
+                    // the standard input of a dump was read already
+                    Profile::Xcompose | Profile::Dump => {
+                        let mut user_config =
+                            UserConfig::try_read(self.config.user_config_path.clone())
+                                .unwrap_or_default();
+                        user_config.apply_wm(self.config.desktop.wm.as_deref());
+                        (Profile::Xmonad, user_config.xmonad_config)
+                    }
+                };
+                info!("switching to the {profile:?} profile, {path}");
+                // kept when apekey.toml is reloaded, like `--profile`
+                self.config.overrides.profile = Some(profile);
+                self.config.overrides.config_path = Some(path.clone());
+                self.config.profile = profile;
+                self.config.config_path = path;
+                self.update(Message::Reload)
+            }
+            Message::Quit => window::close(),
+            Message::Picked(line) => {
+                if let Some(pick) = &self.config.pick {
+                    pick.picked.set(line).ok();
+                }
+                window::close()
+            }
+            Message::Touch(event) => {
+                if let Some(zoom) = self.pinch.update(event, self.zoom) {
+                    self.zoom = zoom;
+                }
+                Command::none()
+            }
+            Message::WindowResized(width) => {
+                self.config.ui.window_width = width as f32;
+                Command::none()
+            }
+            Message::FocusLost => {
+                // a hidden picker could not be shown again, the script
+                // running it would wait forever
+                if self.config.close_on_focus_loss && self.config.pick.is_some() {
+                    self.update(Message::Quit)
+                } else if self.config.close_on_focus_loss && self.visible {
+                    self.update(Message::ToggleWindow)
+                } else {
+                    Command::none()
+                }
+            }
+            // Enter confirms the pending command
+            Message::SearchSubmitted if self.pending_command.is_some() => {
+                self.update(Message::ExecuteConfirmed)
+            }
+            Message::SearchSubmitted if self.view.search_mode == SearchMode::Jump => {
+                self.update(Message::MatchCycled(1))
+            }
+            Message::SearchSubmitted => {
+                if let Some(pick) = &self.config.pick {
+                    return match self.view.best_match() {
+                        Some(keybind) => {
+                            let line = pick
+                                .template
+                                .render(export::row_value(keybind, &self.config), |_, text| text);
+                            self.update(Message::Picked(line))
+                        }
+                        None => Command::none(),
+                    };
+                }
+                // executes the best match, if it spawns a command
+                match self.view.best_command() {
+                    Some(command) => self.update(Message::Execute(command)),
+                    None => Command::none(),
+                }
+            }
+            Message::Execute(command) => {
+                if self.config.confirm_exec {
+                    self.pending_command = Some(command);
+                    Command::none()
+                } else {
+                    Command::perform(execute(command), Message::Executed)
+                }
+            }
+            Message::CopySource(source) => {
+                info!("keybind source copied to the clipboard");
+                iced::clipboard::write(source)
+            }
+            Message::ResultsCopied => {
+                let results = self.view.results();
+                info!(
+                    "{} keybinds copied to the clipboard",
+                    results.keybind_count()
+                );
+                iced::clipboard::write(export::render_clipboard(
+                    &results,
+                    &self.config,
+                    self.config.clipboard_format,
+                ))
+            }
+            Message::ExecuteConfirmed => match self.pending_command.take() {
+                Some(command) => Command::perform(execute(command), Message::Executed),
+                None => Command::none(),
+            },
+            Message::ExecuteCancelled => {
+                self.pending_command = None;
+                Command::none()
+            }
+            Message::Executed(result) => match result {
+                Ok(()) => {
+                    // the job is done, get out of the way
+                    self.visible = false;
+                    window::change_mode(window::Mode::Hidden)
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    Command::none()
+                }
+            },
+            Message::OpenEditor => match self.state {
+                State::Error { line, .. } => Command::perform(
+                    open_in_editor(self.config.config_path.clone(), line),
+                    Message::EditorOpened,
+                ),
+                _ => Command::none(),
+            },
+            Message::OpenFile(path) => {
+                Command::perform(open_in_editor(path, None), Message::EditorOpened)
+            }
+            Message::EditorOpened(result) => {
+                if let Err(e) = result {
+                    error!("{}", e);
+                }
+                Command::none()
+            }
             Message::FontLoaded(_) => {
                 debug!("message: font loaded");
                 Command::none()
@@ -209,155 +1425,718 @@ impl Application for Apekey {
     }
 
     #[instrument(skip_all)]
-    fn view(&self) -> Element<Self::Message> {
+    fn view(&self) -> Element<'_, Self::Message> {
         let palette = self.theme().palette();
-        match &self.state {
-            State::ReadingConfig => container(Text::new("▪▫▫ Reading xmonad.hs").font(FONT_MONO))
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .padding(20)
-                .center_x()
-                .center_y()
-                .into(),
-            State::ParsingConfig => container(Text::new("▪▪▫ Parsing keymap   ").font(FONT_MONO))
+        match self.screen() {
+            Screen::Progress(label) => ui::progress(label),
+            Screen::Keymap {
+                title,
+                search,
+                panels,
+                listing,
+            } => {
+                debug!("rendering keybinds");
+                let config = self.zoomed_config();
+                let search_bar = ui::search_bar(search, self.show_chord_picker, &config);
+                let title = text(title).size(config.ui.title_size);
+                let header = panels.into_iter().fold(
+                    column![
+                        column![title, ui::sources_header(&config, &palette)].spacing(4),
+                        search_bar
+                    ]
+                    .spacing(config.ui.metrics.header_spacing),
+                    |header, panel| header.push(self.panel_view(panel, &config, &palette)),
+                );
+
+                container(column![
+                    container(header)
+                        .padding(config.ui.metrics.header_padding)
+                        .width(Length::Fill),
+                    horizontal_rule(config.ui.metrics.rule_thickness),
+                    self.listing_view(listing, &config, &palette)
+                        .height(Length::Fill)
+                ])
                 .width(Length::Fill)
                 .height(Length::Fill)
-                .padding(20)
                 .center_x()
                 .center_y()
-                .into(),
-            State::RenderKeybinds => {
+                .into()
+            }
+            // the filter line over the keybinds, the matching ones when
+            // searching, without title, section headers nor search controls
+            Screen::Minimal {
+                query,
+                panels,
+                listing,
+            } => {
                 debug!("rendering keybinds");
-                let tokens = TOKENS.get().unwrap();
-                let text_input = container(
-                    text_input("Search", &self.input_value)
-                        .id(INPUT_ID.clone())
-                        .padding(10)
-                        .width(Length::Fixed(180.0))
-                        .size(self.config.ui.text_size)
-                        .on_input(Message::InputChanged),
-                )
-                .width(Length::Fill)
-                .align_x(Horizontal::Right);
-
-                let default_title = DEFAULT_TITLE.to_string();
-                let title = text(tokens.title.as_ref().unwrap_or(&default_title))
-                    .size(self.config.ui.title_size);
-
-                let keybinds = if self.input_value.is_empty() {
-                    scrollable(tokens.view(&self.config, &palette))
-                } else {
-                    scrollable(self.tokens.iter().fold(column![], |column, keybind| {
-                        column
-                            .push(keybind.view(&self.config, &palette))
-                            .width(Length::Fill)
-                            .spacing(8)
-                            .padding(Padding::from([35, 30, 30, 30])) // top, right, bottom, left
-                    }))
+                let config = self.zoomed_config();
+                let content = panels.into_iter().fold(
+                    column![ui::filter_line(query, &config)]
+                        .spacing(config.ui.metrics.row_spacing)
+                        .padding(config.ui.metrics.row_spacing),
+                    |content, panel| content.push(self.panel_view(panel, &config, &palette)),
+                );
+                content
+                    .push(
+                        self.listing_view(listing, &config, &palette)
+                            .height(Length::Fill),
+                    )
+                    .into()
+            }
+            Screen::Presenting { keymap, section } => {
+                let config = AppConfig {
+                    ui: self.config.ui.scaled(PRESENTATION_SCALE * self.zoom),
+                    ..self.config.clone()
                 };
-
+                let content = match keymap.sections.get(section) {
+                    Some(section) => section.view(&config, &palette, None),
+                    None => text("Empty keymap").size(config.ui.section_size).into(),
+                };
+                let footer = text(format!(
+                    "{} / {}   ← → to navigate, Esc to exit",
+                    section + 1,
+                    keymap.section_count()
+                ))
+                .size(self.config.ui.text_size)
+                .style(ui::muted(&palette));
                 container(column![
-                    container(column![title, text_input].spacing(14))
-                        .padding(20)
-                        .width(Length::Fill),
-                    horizontal_rule(1),
-                    keybinds.height(Length::Fill)
+                    scrollable(container(content).padding(40).width(Length::Fill))
+                        .height(Length::Fill),
+                    footer
                 ])
                 .width(Length::Fill)
                 .height(Length::Fill)
+                .padding(20)
                 .center_x()
-                .center_y()
                 .into()
             }
-            State::Error(err) => container(
-                Text::new(err)
-                    .size(self.config.ui.error_size)
-                    .style(palette.danger)
-                    .width(Length::Fixed(400.0)),
-            )
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .padding(20)
-            .center_x()
-            .center_y()
-            .into(),
+            Screen::Error(err) => ui::error_panel(err, &self.config, &palette),
         }
     }
 
     fn theme(&self) -> Theme {
         self.config.theme.clone()
     }
-}
 
-#[instrument(skip_all)]
-async fn filter_tokens(mut tokens: Vec<ScoredKeybind>, pattern: String) -> Vec<ScoredKeybind> {
-    for token in &mut tokens {
-        token.score = FUZZY_MATCHER.fuzzy(&token.to_string(), &pattern, true);
+    fn style(&self) -> theme::Application {
+        if self.config.ui.opacity < 1.0 {
+            theme::Application::Custom(Box::new(crate::theme::Translucent(self.config.ui.opacity)))
+        } else {
+            theme::Application::Default
+        }
     }
+}
 
-    let mut filtered: Vec<ScoredKeybind> = tokens
-        .into_iter()
-        // only retains keybind tokens with a matching score
-        .filter(|token| token.score.is_some())
-        .collect();
+/// Spawns the command in a shell, apekey does not wait for it to exit
+async fn execute(command: String) -> Result<(), String> {
+    info!("executing {command}");
+    process::Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to execute {command}\n{e}"))
+}
+
+/// Reads apekey.toml again, e.g. once changed
+async fn read_user_config(path: Option<String>) -> Result<Box<UserConfig>, String> {
+    UserConfig::try_read(path)
+        .map(Box::new)
+        .map_err(|e| e.to_string())
+}
 
-    // sort by fuzzy score
-    filtered.sort_by(|a, b| {
-        b.score
-            .as_ref()
-            .unwrap()
-            .0
-            .cmp(&a.score.as_ref().unwrap().0)
-    });
-    filtered
+/// Opens the file at the line in `$EDITOR`, with the `+line` argument most
+/// editors understand, or with `xdg-open` when it is not set
+async fn open_in_editor(path: String, line: Option<usize>) -> Result<(), String> {
+    let mut command = match env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => {
+            // the editor may come with arguments, e.g. `alacritty -e nvim`
+            let mut command = process::Command::new("sh");
+            command.arg("-c").arg(match line {
+                Some(line) => format!("{editor} +{line} \"$0\""),
+                None => format!("{editor} \"$0\""),
+            });
+            command
+        }
+        _ => process::Command::new("xdg-open"),
+    };
+    info!("opening {path} in the editor");
+    command
+        .arg(&path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {path} in the editor\n{e}"))
 }
 
-async fn parse(config: String) -> Result<Tokens> {
-    let parser = Parser(config);
-    parser.parse().await
+/// Shows the window in the configured mode
+fn show_window(mode: WindowMode) -> Command<Message> {
+    match mode {
+        WindowMode::Windowed => window::change_mode(window::Mode::Windowed),
+        WindowMode::Maximized => Command::batch(vec![
+            window::change_mode(window::Mode::Windowed),
+            window::maximize(true),
+        ]),
+        WindowMode::Fullscreen => window::change_mode(window::Mode::Fullscreen),
+    }
+}
+
+/// Reads and parses the keymap compared with mine, without the sources
+/// and the usage of mine
+async fn parse_compared(path: String, config: AppConfig, cache: ParseCache) -> Result<Tokens> {
+    let content = read_config(path.clone())
+        .await
+        .map_err(|e| eyre!("{path}: {e}"))?;
+    let config = AppConfig {
+        config_path: path.clone(),
+        sources: vec![],
+        usage_log: None,
+        ..config
+    };
+    parse(content, config, cache)
+        .await
+        .map_err(|e| eyre!("{path}: {e}"))
+}
+
+/// Parses the keymap of `config.config_path`, unless its content did not
+/// change since the last time, then adds the sources, the usage and the
+/// layout which can change anytime
+async fn parse(content: String, config: AppConfig, cache: ParseCache) -> Result<Tokens> {
+    // the modules may change without the entry changing
+    let include_warnings = match config.profile {
+        Profile::Xmonad => {
+            includes::diagnostics(&config.config_path, &content, &config.sources).await
+        }
+        Profile::Xcompose | Profile::Dump => vec![],
+    };
+    let options = format!(
+        "{:?} {:?} {}",
+        config.profile, config.haddock, config.fallback_description
+    );
+    let mut tokens = cache.parse(
+        &config.config_path,
+        &content,
+        options,
+        |content| match config.profile {
+            Profile::Xmonad => {
+                Parser::new(content, config.haddock, config.fallback_description).tokens()
+            }
+            Profile::Xcompose => Ok(compose::parse(content)),
+            Profile::Dump => Ok(dump::parse(content)),
+        },
+    )?;
+    tokens.sections.extend(
+        source::sections(
+            &config.sources,
+            config.haddock,
+            config.fallback_description,
+            &cache,
+        )
+        .await,
+    );
+    tokens.sort_sections(config.section_sort);
+    tokens.warnings.extend(include_warnings);
+    if let Some(usage) = match config.usage_log {
+        Some(path) => usage::read_usage(path).await,
+        None => None,
+    } {
+        tokens.set_usage(&usage);
+    }
+    // after the usage, the log holds the keys as written in the config
+    if let Some(layout) = &config.layout {
+        tokens.translate_keys(layout);
+    }
+    Ok(tokens)
 }
 
 impl From<UserConfig> for AppConfig {
     fn from(config: UserConfig) -> Self {
+        AppConfig::new(config, Desktop::default())
+    }
+}
+
+impl AppConfig {
+    /// The config of apekey.toml, the default font sizes scaled to the
+    /// monitor of the desktop unless `autoscale = false` and its keyboard
+    /// layout used when `keyboard_layout = true`
+    pub fn new(config: UserConfig, desktop: Desktop) -> Self {
+        let colors = config.colors();
         let font_config = config.font.unwrap_or_default();
+        let window = config.window.unwrap_or_default();
+        let layout = config.layout.unwrap_or_default();
+        let density = Metrics::from(config.density.unwrap_or_default());
+        let scale = match font_config.autoscale {
+            Some(false) => 1.0,
+            _ => desktop.font_scale,
+        };
+        debug!("font scale {scale}");
+        let font_size = (f32::from(FONT_SIZE) * scale).round() as u16;
+        let title_size = (f32::from(TITLE_FONT_SIZE) * scale).round() as u16;
+        let day_night = (config.theme.as_deref() == Some(AUTO_TIME))
+            .then(|| DayNight::new(&config.auto_time.unwrap_or_default(), colors.as_ref()));
+        // an unknown theme is reported by the config linting
+        let (theme_name, theme) = match &day_night {
+            Some(day_night) => {
+                let (name, theme) = day_night.theme(crate::theme::local_time());
+                (name.to_owned(), theme)
+            }
+            None => {
+                let (name, theme) = config
+                    .theme
+                    .and_then(|name| crate::theme::builtin(&name).map(|theme| (name, theme)))
+                    .unwrap_or_else(|| (crate::theme::THEMES[0].to_owned(), Theme::Dark));
+                match &colors {
+                    Some(colors) => (name, crate::theme::with_colors(theme, colors)),
+                    None => (name, theme),
+                }
+            }
+        };
         AppConfig {
+            profile: Profile::default(),
+            present: false,
+            minimal: false,
+            pick: None,
+            present_interval: None,
+            preview: false,
+            compare: None,
+            accessible: false,
+            overrides: Overrides::default(),
+            keymap: None,
+            warnings: config.warnings,
+            user_config_path: config.path,
+            theme_name,
+            close_on_focus_loss: config.close_on_focus_loss.unwrap_or(false),
+            // an invalid hotkey is reported by the config linting
+            hotkey: config
+                .toggle_hotkey
+                .and_then(|keys| Hotkey::parse(&keys).ok()),
+            tray: config.tray.unwrap_or_default(),
+            http_port: config.http_port,
+            renderer: config.renderer.unwrap_or_default(),
+            window_mode: window.mode.unwrap_or_default(),
+            sources: config.sources.unwrap_or_default(),
             config_path: config.xmonad_config,
-            theme: config
-                .theme
-                .map(|t| match t {
-                    user_config::Theme::Dark => Theme::Dark,
-                    user_config::Theme::Light => Theme::Light,
-                    user_config::Theme::Tars => Theme::custom(theme::Palette {
-                        background: Color::from_rgb8(33, 33, 33), // #212121
-                        text: Color::from_rgb8(203, 205, 212),    // #CBCDD4
-                        primary: Color::from_rgb8(253, 153, 53),  // #FD9935
-                        success: Color::from_rgb8(188, 190, 196), // #212121
-                        danger: Color::from_rgb8(248, 113, 113),  // #f87171
-                    }),
-                })
-                .unwrap_or_else(|| Theme::Dark),
+            focus_search: config
+                .startup
+                .as_ref()
+                .and_then(|s| s.focus_search)
+                .unwrap_or(true),
+            collapsed_sections: config
+                .startup
+                .as_ref()
+                .and_then(|s| s.collapsed_sections.clone())
+                .unwrap_or_default(),
+            initial_section: config
+                .startup
+                .as_ref()
+                .and_then(|s| s.initial_section.clone()),
+            query: config.startup.and_then(|s| s.initial_query),
+            confirm_exec: config.confirm_exec.unwrap_or(true),
+            search_actions: config.search_actions.unwrap_or(false),
+            search_weights: config.search_weights.unwrap_or_default(),
+            search_mode: config.search_mode.unwrap_or_default(),
+            search_matcher: config.search_matcher.unwrap_or_default(),
+            usage_log: config.usage_log,
+            haddock: config.haddock.unwrap_or_default(),
+            fallback_description: config.fallback_description.unwrap_or_default(),
+            show_ignored: config.show_ignored.unwrap_or_default(),
+            layout: match config.keyboard_layout {
+                Some(true) => desktop.layout.clone(),
+                _ => None,
+            },
+            key_style: match config.keys {
+                Some(keys) => KeyStyle {
+                    order: keys.order.unwrap_or_default(),
+                    separator: keys
+                        .separator
+                        .unwrap_or_else(|| KeyStyle::default().separator),
+                    names: keys.names.unwrap_or_default(),
+                },
+                None => KeyStyle::default(),
+            },
+            // an invalid template is reported by the config linting
+            row_template: config
+                .row_template
+                .and_then(|template| RowTemplate::parse(&template).ok()),
+            clipboard_format: config.clipboard_format.unwrap_or_default(),
+            section_sort: config.section_sort.unwrap_or_default(),
+            theme,
+            day_night,
+            desktop,
             ui: Ui {
-                title_size: font_config.title_size.unwrap_or(TITLE_FONT_SIZE),
-                section_size: font_config.section_size.unwrap_or(FONT_SIZE),
-                keybind_size: font_config.keybind_size.unwrap_or(FONT_SIZE),
-                text_size: font_config.text_size.unwrap_or(FONT_SIZE),
-                error_size: font_config.error_size.unwrap_or(FONT_SIZE),
+                title_size: font_config.title_size.unwrap_or(title_size),
+                section_size: font_config.section_size.unwrap_or(font_size),
+                keybind_size: font_config.keybind_size.unwrap_or(font_size),
+                text_size: font_config.text_size.unwrap_or(font_size),
+                error_size: font_config.error_size.unwrap_or(font_size),
+                opacity: window
+                    .opacity
+                    .map_or(1.0, |opacity| opacity.clamp(0.0, 1.0)),
+                key_column: window.key_column.unwrap_or(KEY_COLUMN).max(1),
+                metrics: Metrics {
+                    row_spacing: layout.row_spacing.unwrap_or(density.row_spacing),
+                    section_spacing: layout.section_spacing.unwrap_or(density.section_spacing),
+                    section_padding: layout
+                        .section_padding
+                        .map_or(density.section_padding, Padding::from),
+                    keymap_padding: layout
+                        .keymap_padding
+                        .map_or(density.keymap_padding, Padding::from),
+                    header_padding: layout.header_padding.unwrap_or(density.header_padding),
+                    header_spacing: layout.header_spacing.unwrap_or(density.header_spacing),
+                    rule_thickness: layout.rule_thickness.unwrap_or(density.rule_thickness),
+                    search_width: layout.search_width.map_or(density.search_width, f32::from),
+                    sub_columns_from: layout.sub_columns_from.unwrap_or(density.sub_columns_from),
+                },
+                window_width: WINDOW_WIDTH,
             },
         }
     }
 }
 
+/// What apekey lists
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Keybinds annotated in the xmonad config
+    #[default]
+    Xmonad,
+    /// Compose sequences of an XCompose file
+    Xcompose,
+    /// Keybindings listed by xmonad, e.g. the NamedActions `showKm` dump
+    Dump,
+}
+
+impl Profile {
+    /// What the profile reads, e.g. for the header
+    pub fn format(self) -> &'static str {
+        match self {
+            Profile::Xmonad => "xmonad.hs annotations",
+            Profile::Xcompose => "XCompose",
+            Profile::Dump => "xmonad keybindings dump",
+        }
+    }
+}
+
+/// Restricts the rendered keybinds to a given kind of action
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ActionFilter {
+    #[default]
+    All,
+    Kind(ActionKind),
+}
+
+impl ActionFilter {
+    pub const ALL: [ActionFilter; 8] = [
+        ActionFilter::All,
+        ActionFilter::Kind(ActionKind::Spawn),
+        ActionFilter::Kind(ActionKind::Window),
+        ActionFilter::Kind(ActionKind::Layout),
+        ActionFilter::Kind(ActionKind::Workspace),
+        ActionFilter::Kind(ActionKind::Screen),
+        ActionFilter::Kind(ActionKind::Xmonad),
+        ActionFilter::Kind(ActionKind::Other),
+    ];
+
+    pub fn matches(&self, kind: ActionKind) -> bool {
+        match self {
+            ActionFilter::All => true,
+            ActionFilter::Kind(k) => *k == kind,
+        }
+    }
+}
+
+impl fmt::Display for ActionFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionFilter::All => write!(f, "all"),
+            ActionFilter::Kind(kind) => write!(f, "{kind}"),
+        }
+    }
+}
+
+/// Restricts the rendered keybinds to the ones active in a layout, set
+/// by `-- ##layout` annotations
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LayoutFilter {
+    #[default]
+    All,
+    /// Only the keybinds active in every layout
+    Global,
+    /// The global keybinds and the ones of the layout
+    Layout(String),
+}
+
+impl LayoutFilter {
+    /// Whether a keybind active in these layouts, every one if empty, is
+    /// kept
+    pub fn matches(&self, layouts: &[String]) -> bool {
+        match self {
+            LayoutFilter::All => true,
+            LayoutFilter::Global => layouts.is_empty(),
+            LayoutFilter::Layout(layout) => layouts.is_empty() || layouts.contains(layout),
+        }
+    }
+}
+
+impl fmt::Display for LayoutFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutFilter::All => write!(f, "all layouts"),
+            LayoutFilter::Global => write!(f, "global"),
+            LayoutFilter::Layout(layout) => write!(f, "{layout}"),
+        }
+    }
+}
+
 #[derive(Debug)]
 enum State {
     ReadingConfig,
     ParsingConfig,
     RenderKeybinds,
-    Error(String),
+    // one section at a time, with large fonts
+    Presenting { section: usize },
+    // the line to fix, for the editor
+    Error { err: String, line: Option<usize> },
 }
 
 #[instrument]
 pub async fn read_config(config_path: String) -> Result<String> {
-    fs::read_to_string(&config_path)
-        .map_err(|e| eyre!("Failed to read the config file {config_path}\n{e}"))
-        .await
+    if config_path == STDIN_PATH {
+        return read_stdin().map_err(stdin_error);
+    }
+    match time::timeout(READ_TIMEOUT, fs::read_to_string(&config_path)).await {
+        Ok(result) => result.map_err(|e| read_error(&config_path, e)),
+        Err(_) => Err(Failure::report(
+            Exit::Unreadable,
+            format!(
+                "Timed out reading the config file {config_path} after {}s\n\
+                 If it is on a network filesystem, check that it is reachable",
+                READ_TIMEOUT.as_secs()
+            ),
+        )),
+    }
+}
+
+/// Reads the keymap of the xmonad config a line at a time, outside of the
+/// GUI, e.g. to export or check a huge generated config
+pub fn read_keymap(config: &AppConfig) -> Result<Parser> {
+    let (haddock, fallback) = (config.haddock, config.fallback_description);
+    let parser = if config.config_path == STDIN_PATH {
+        Parser::read(io::stdin().lock(), haddock, fallback).map_err(stdin_error)?
+    } else {
+        let file = std::fs::File::open(&config.config_path)
+            .map_err(|e| read_error(&config.config_path, e))?;
+        Parser::read(io::BufReader::new(file), haddock, fallback)
+            .map_err(|e| read_error(&config.config_path, e))?
+    };
+    Ok(parser)
+}
+
+/// Reads the standard input once, a reload reads the same content again
+pub fn read_stdin() -> io::Result<String> {
+    static STDIN: OnceCell<String> = OnceCell::new();
+    STDIN
+        .get_or_try_init(|| io::read_to_string(io::stdin()))
+        .cloned()
+}
+
+/// Reads and parses the keymap like the window does, before opening it,
+/// e.g. for `--pick` to exit with the code of the failure right away
+pub fn load_keymap(config: &AppConfig) -> Result<Tokens> {
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let content = read_config(config.config_path.clone()).await?;
+        parse(content, config.clone(), ParseCache::default()).await
+    })
+}
+
+fn stdin_error(e: io::Error) -> eyre::Report {
+    Failure::report(
+        Exit::Unreadable,
+        format!("Failed to read the standard input\n{e}"),
+    )
+}
+
+/// Turns an IO error into an actionable message, exiting with
+/// `Exit::Unreadable`
+pub fn read_error(config_path: &str, e: io::Error) -> eyre::Report {
+    let message = match e.kind() {
+        io::ErrorKind::NotFound => {
+            let found: Vec<String> = probe_xmonad_configs()
+                .into_iter()
+                .filter(|p| p != config_path)
+                .collect();
+            let hint = if found.is_empty() {
+                "Set `xmonad_config` in apekey.toml or pass the path as argument".to_owned()
+            } else {
+                format!("Found xmonad configs at:\n{}", found.join("\n"))
+            };
+            format!("Config file {config_path} not found\n{hint}")
+        }
+        io::ErrorKind::PermissionDenied => format!(
+            "Permission denied reading the config file {config_path}\n\
+             Check that it is readable by the current user"
+        ),
+        io::ErrorKind::IsADirectory => {
+            format!("{config_path} is a directory\nThe path must point to your xmonad.hs file")
+        }
+        // not UTF-8, e.g. the compiled xmonad executable
+        io::ErrorKind::InvalidData => format!(
+            "{config_path} is not a text file, is it the compiled xmonad?\n\
+             The path must point to your xmonad.hs file"
+        ),
+        _ => format!("Failed to read the config file {config_path}\n{e}"),
+    };
+    Failure::report(Exit::Unreadable, message)
+}
+
+// usual locations of xmonad.hs, only the existing ones are returned
+fn probe_xmonad_configs() -> Vec<String> {
+    let Some(home) = dirs::home_dir() else {
+        return vec![];
+    };
+    dirs::config_dir()
+        .map(|dir| dir.join("xmonad/xmonad.hs"))
+        .into_iter()
+        .chain([
+            home.join(".config/xmonad/xmonad.hs"),
+            home.join(".xmonad/xmonad.hs"),
+        ])
+        .filter(|p| p.is_file())
+        .map(|p| p.display().to_string())
+        .fold(vec![], |mut acc, p| {
+            if !acc.contains(&p) {
+                acc.push(p);
+            }
+            acc
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::{assert_snapshot, assert_widgets_snapshot};
+
+    #[test]
+    fn overrides_reapplied() {
+        let user_config = || {
+            let mut config = UserConfig::default();
+            config.xmonad_config = "xmonad.hs".to_owned();
+            config
+        };
+        let overrides = Overrides {
+            profile: Some(Profile::Xcompose),
+            config_path: Some("keys.compose".to_owned()),
+            query: Some("vol".to_owned()),
+            minimal: true,
+            accessible: true,
+            ..Default::default()
+        };
+        let config = overrides.config(user_config(), Desktop::default());
+        assert_eq!(config.profile, Profile::Xcompose);
+        assert_eq!(config.config_path, "keys.compose");
+        assert_eq!(config.query.as_deref(), Some("vol"));
+        assert!(config.minimal && config.accessible);
+        // a reload of apekey.toml keeps them
+        let reloaded = config.overrides.config(user_config(), Desktop::default());
+        assert_eq!(reloaded.config_path, "keys.compose");
+        assert_eq!(reloaded.theme_name, ACCESSIBLE_THEME);
+        let config = Overrides::default().config(user_config(), Desktop::default());
+        assert_eq!(config.config_path, "xmonad.hs");
+        assert_eq!(config.profile, Profile::Xmonad);
+    }
+
+    #[test]
+    fn enter_picking() {
+        let pick = Pick::new(RowTemplate::parse_line("{description}").unwrap());
+        let tokens = Parser::new(
+            include_str!("../fixtures/basic.hs"),
+            Default::default(),
+            false,
+        )
+        .tokens()
+        .unwrap();
+        let config = AppConfig {
+            pick: Some(pick.clone()),
+            keymap: Some(tokens),
+            ..AppConfig::from(UserConfig::default())
+        };
+        // the keymap loaded by main is shown right away
+        let (mut app, _) = Apekey::new(config);
+        assert!(matches!(app.state, State::RenderKeybinds));
+        assert!(app.config.keymap.is_none());
+        // Enter with an empty query picks nothing, the window stays open
+        let _ = app.update(Message::SearchSubmitted);
+        assert_eq!(pick.line(), None);
+        // losing the focus quits instead of hiding the picker
+        app.config.close_on_focus_loss = true;
+        let _ = app.update(Message::FocusLost);
+        assert!(app.visible);
+
+        let _ = app.update(Message::InputChanged("workspace".to_owned()));
+        let results = iced::futures::executor::block_on(filter_tokens(
+            app.view.keymap.keybinds(),
+            app.view.input_value.clone(),
+            app.view.matcher,
+            app.config.search_actions,
+            app.config.search_weights,
+        ));
+        let best = results.0[0].description.clone();
+        let _ = app.update(Message::TokensFiltered(results));
+        let _ = app.update(Message::SearchSubmitted);
+        assert_eq!(pick.line(), Some(best.as_str()));
+    }
+
+    // drives the app through its messages, the commands are not run but
+    // the futures the tests need, in a runtime of their own
+    #[test]
+    fn screens_snapshotting() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let config = AppConfig {
+            config_path: "fixtures/basic.hs".to_owned(),
+            ..AppConfig::from(UserConfig::default())
+        };
+        let (mut app, _) = Apekey::new(config.clone());
+        let _ = app.view();
+        assert_snapshot("loading", &app.screen());
+
+        let _ = app.update(Message::ConfigError(
+            app.generation,
+            "No such file or directory (os error 2)".to_owned(),
+        ));
+        let _ = app.view();
+        assert_snapshot("error", &app.screen());
+        assert_widgets_snapshot("error", app.view(), &app.theme());
+
+        let (mut app, _) = Apekey::new(config.clone());
+        let content = include_str!("../fixtures/basic.hs").to_owned();
+        let _ = app.update(Message::ConfigRead(app.generation, content.clone()));
+        assert_snapshot("parsing", &app.screen());
+        let tokens = runtime
+            .block_on(parse(content, config.clone(), app.parsed.clone()))
+            .unwrap();
+        let _ = app.update(Message::ParsingDone(app.generation, tokens));
+        let _ = app.view();
+        assert_snapshot("list", &app.screen());
+        assert_widgets_snapshot("list", app.view(), &app.theme());
+
+        let _ = app.update(Message::InputChanged("workspace".to_owned()));
+        let (matches, _) = runtime.block_on(filter_tokens(
+            app.view.keymap.keybinds(),
+            app.view.input_value.clone(),
+            app.view.matcher,
+            config.search_actions,
+            config.search_weights,
+        ));
+        // the search time shown varies from a run to the next
+        let _ = app.update(Message::TokensFiltered((matches, Duration::ZERO)));
+        let _ = app.view();
+        assert_snapshot("filtered_list", &app.screen());
+        assert_widgets_snapshot("filtered_list", app.view(), &app.theme());
+
+        let _ = app.update(Message::InputChanged(String::new()));
+        let _ = app.update(Message::SectionToggled("Window navigation".to_owned()));
+        let _ = app.view();
+        assert_snapshot("collapsed_sections", &app.screen());
+    }
 }
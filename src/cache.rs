@@ -0,0 +1,99 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Keymaps parsed from each file the keymap is read from, the entry config
+// and the Haskell files of the `xmonad` sources, so that a reload only
+// parses the files whose content changed. Held by the app and shared with
+// the tasks parsing the sources.
+
+use eyre::Result;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+
+use crate::token::Tokens;
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseCache(Arc<Mutex<HashMap<String, (u64, Tokens)>>>);
+
+impl ParseCache {
+    /// The keymap of the file at `path`, the one parsed the last time
+    /// unless its content or the `options` changing how it is parsed
+    /// changed
+    pub fn parse(
+        &self,
+        path: &str,
+        content: &str,
+        options: impl Hash,
+        parse: impl FnOnce(&str) -> Result<Tokens>,
+    ) -> Result<Tokens> {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        options.hash(&mut hasher);
+        let hash = hasher.finish();
+        // a lock poisoned by a panicking task is a miss
+        let cached = self.0.lock().ok().and_then(|parsed| {
+            parsed
+                .get(path)
+                .filter(|(parsed_hash, _)| *parsed_hash == hash)
+                .map(|(_, tokens)| tokens.clone())
+        });
+        if let Some(tokens) = cached {
+            debug!("{path} unchanged, parsing skipped");
+            return Ok(tokens);
+        }
+        let tokens = parse(content)?;
+        if let Ok(mut parsed) = self.0.lock() {
+            parsed.insert(path.to_owned(), (hash, tokens.clone()));
+        }
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn parse_caching() {
+        let cache = ParseCache::default();
+        let parsed = Cell::new(0);
+        let parse = |path: &str, content: &str, options: bool| {
+            cache
+                .parse(path, content, options, |content| {
+                    parsed.set(parsed.get() + 1);
+                    Ok(Tokens {
+                        title: Some(content.to_owned()),
+                        ..Default::default()
+                    })
+                })
+                .unwrap()
+                .title
+        };
+        assert_eq!(parse("xmonad.hs", "a", false).as_deref(), Some("a"));
+        assert_eq!(parse("lib/Keys.hs", "b", false).as_deref(), Some("b"));
+        assert_eq!(parsed.get(), 2);
+        // hits, each file has its own entry
+        parse("xmonad.hs", "a", false);
+        parse("lib/Keys.hs", "b", false);
+        assert_eq!(parsed.get(), 2);
+        // a module changing leaves the entry cached
+        assert_eq!(parse("lib/Keys.hs", "c", false).as_deref(), Some("c"));
+        parse("xmonad.hs", "a", false);
+        assert_eq!(parsed.get(), 3);
+        // the options change the keymap of unchanged files
+        parse("xmonad.hs", "a", true);
+        assert_eq!(parsed.get(), 4);
+        // a failure is not cached
+        assert!(cache
+            .parse("xmonad.hs", "d", false, |_| Err(eyre::eyre!("bad")))
+            .is_err());
+        parse("xmonad.hs", "d", false);
+        assert_eq!(parsed.get(), 5);
+    }
+}
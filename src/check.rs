@@ -0,0 +1,439 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::ValueEnum;
+use eyre::Result;
+use std::{collections::HashMap, fmt};
+use tracing::{debug, instrument};
+
+use crate::{
+    app::{read_keymap, AppConfig},
+    parser::Parser,
+    token::{canonical_keys, Tokens},
+};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum CheckFormat {
+    /// One `file:line:column: severity[rule]: message` line per finding
+    #[default]
+    Text,
+    /// A JSON object listing the findings
+    Json,
+    /// SARIF 2.1.0 log, e.g. for GitHub code scanning
+    Sarif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    ParseError,
+    DuplicateKeys,
+    InvalidKeys,
+    PrefixConflict,
+    UnclosedKeymap,
+}
+
+impl Rule {
+    const ALL: [Rule; 5] = [
+        Rule::ParseError,
+        Rule::DuplicateKeys,
+        Rule::InvalidKeys,
+        Rule::PrefixConflict,
+        Rule::UnclosedKeymap,
+    ];
+
+    fn id(&self) -> &'static str {
+        match self {
+            Rule::ParseError => "parse-error",
+            Rule::DuplicateKeys => "duplicate-keys",
+            Rule::InvalidKeys => "invalid-keys",
+            Rule::PrefixConflict => "prefix-conflict",
+            Rule::UnclosedKeymap => "unclosed-keymap",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Rule::ParseError => "The keymap annotations could not be parsed",
+            Rule::DuplicateKeys => "The same keys are bound more than once",
+            Rule::InvalidKeys => "The keys do not follow the EZConfig notation",
+            Rule::PrefixConflict => {
+                "A binding is the prefix of a key sequence, one shadows the other"
+            }
+            Rule::UnclosedKeymap => {
+                "The keymap has no closing `-- #` boundary, it goes on to the end of the file"
+            }
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self {
+            // xmonad keeps the last binding, nothing breaks
+            Rule::DuplicateKeys | Rule::PrefixConflict | Rule::UnclosedKeymap => Severity::Warning,
+            Rule::ParseError | Rule::InvalidKeys => Severity::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Outcome of a check, rendered in the requested format
+#[derive(Debug)]
+pub struct Report {
+    pub output: String,
+    pub has_errors: bool,
+}
+
+/// Reads and checks the xmonad config annotations
+#[instrument(skip(config))]
+pub fn run(config: &AppConfig, format: CheckFormat) -> Result<Report> {
+    let findings = check(&read_keymap(config)?);
+    debug!("{} findings", findings.len());
+    let path = &config.config_path;
+    Ok(Report {
+        output: match format {
+            CheckFormat::Text => render_text(path, &findings),
+            CheckFormat::Json => render_json(path, &findings),
+            CheckFormat::Sarif => render_sarif(path, &findings),
+        },
+        has_errors: findings
+            .iter()
+            .any(|f| f.rule.severity() == Severity::Error),
+    })
+}
+
+fn check(parser: &Parser) -> Vec<Finding> {
+    if let Err(e) = parser.check_format() {
+        return vec![Finding {
+            rule: Rule::ParseError,
+            message: e.to_string(),
+            line: 1,
+            column: 1,
+        }];
+    }
+    let sections = match parser.sections() {
+        Ok((_, sections)) => sections,
+        Err(e) => {
+            let (line, column) = parser.position(e.input);
+            return vec![Finding {
+                rule: Rule::ParseError,
+                message: format!("failed to parse the keymap: {:?}", e.code),
+                line,
+                column,
+            }];
+        }
+    };
+    let mut findings = vec![];
+    if let Some(line) = parser.unclosed_keymap() {
+        findings.push(Finding {
+            rule: Rule::UnclosedKeymap,
+            message: "no closing `-- #` boundary, the keymap is parsed up to the end of the file"
+                .into(),
+            line,
+            column: 1,
+        });
+    }
+    let mut positions = vec![];
+    // by canonical keys, `S-M-a` is bound by `M-S-a`
+    let mut seen: HashMap<String, (&str, usize)> = HashMap::new();
+    for keybind in sections.iter().flat_map(|s| &s.keybinds) {
+        // generated keys are not part of the content, point to the description
+        let (line, column) = parser.keybind_position(keybind);
+        let keys = keybind.0.as_ref();
+        positions.push((keys, line, column));
+        if !valid_keys(keys) {
+            findings.push(Finding {
+                rule: Rule::InvalidKeys,
+                message: format!("invalid keys `{keys}`"),
+                line,
+                column,
+            });
+        }
+        match seen.get(&canonical_keys(keys)) {
+            Some((first_keys, first)) => findings.push(Finding {
+                rule: Rule::DuplicateKeys,
+                message: if *first_keys == keys {
+                    format!("`{keys}` already bound at line {first}")
+                } else {
+                    format!("`{keys}` already bound as `{first_keys}` at line {first}")
+                },
+                line,
+                column,
+            }),
+            None => {
+                seen.insert(canonical_keys(keys), (keys, line));
+            }
+        }
+    }
+    for (prefix, sequence) in prefix_conflicts(positions.iter().map(|(keys, ..)| *keys)) {
+        let (prefix_keys, prefix_line, _) = positions[prefix];
+        let (keys, line, column) = positions[sequence];
+        findings.push(Finding {
+            rule: Rule::PrefixConflict,
+            message: format!("`{keys}` is shadowed by `{prefix_keys}` bound at line {prefix_line}"),
+            line,
+            column,
+        });
+    }
+    findings
+}
+
+/// Parsing problems and conflicts of the loaded keymap, shown in the
+/// diagnostics panel
+pub fn keymap_warnings(tokens: &Tokens) -> Vec<String> {
+    let keys: Vec<&str> = tokens
+        .sections
+        .iter()
+        .flat_map(|s| &s.keybinds)
+        // the keybind opening a submap is meant to prefix its keybinds
+        .filter(|k| !k.submap)
+        .map(|k| k.keys.as_str())
+        .collect();
+    let conflicts = prefix_conflicts(keys.iter().copied())
+        .into_iter()
+        .map(|(prefix, sequence)| {
+            format!("`{}` is shadowed by `{}`", keys[sequence], keys[prefix])
+        });
+    tokens.warnings.iter().cloned().chain(conflicts).collect()
+}
+
+/// Returns the indexes of the keys bound directly that are the start of
+/// a bound key sequence, e.g. `M-p` and `M-p c`, with the index of the
+/// sequence
+pub fn prefix_conflicts<'a>(keys: impl Iterator<Item = &'a str>) -> Vec<(usize, usize)> {
+    let keys: Vec<String> = keys.map(canonical_keys).collect();
+    let mut conflicts = vec![];
+    for (sequence, sequence_keys) in keys.iter().enumerate() {
+        let chords: Vec<&str> = sequence_keys.split(' ').collect();
+        for len in 1..chords.len() {
+            let start = chords[..len].join(" ");
+            if let Some(prefix) = keys.iter().position(|k| *k == start) {
+                conflicts.push((prefix, sequence));
+                break;
+            }
+        }
+    }
+    conflicts
+}
+
+/// Checks the keys against the EZConfig notation, space separated chords
+/// of modifiers followed by a single char, a `<special>` key or a
+/// comprehension range such as `[1..9]`
+fn valid_keys(keys: &str) -> bool {
+    const MODIFIERS: [&str; 8] = ["M-", "M1-", "M2-", "M3-", "M4-", "M5-", "C-", "S-"];
+    !keys.trim().is_empty()
+        && keys.split_whitespace().all(|chord| {
+            let mut key = chord;
+            while let Some(rest) = MODIFIERS.iter().find_map(|m| key.strip_prefix(m)) {
+                if rest.is_empty() {
+                    // e.g. `M--`, the `-` key
+                    break;
+                }
+                key = rest;
+            }
+            key.chars().count() == 1
+                || (key.len() > 2 && key.starts_with('<') && key.ends_with('>'))
+                || (key.len() > 2 && key.starts_with('[') && key.ends_with(']'))
+        })
+}
+
+fn render_text(path: &str, findings: &[Finding]) -> String {
+    findings
+        .iter()
+        .map(|f| {
+            format!(
+                "{path}:{}:{}: {}[{}]: {}",
+                f.line,
+                f.column,
+                f.rule.severity(),
+                f.rule.id(),
+                f.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_json(path: &str, findings: &[Finding]) -> String {
+    let findings: Vec<String> = findings
+        .iter()
+        .map(|f| {
+            format!(
+                r#"{{"rule":"{}","severity":"{}","message":"{}","line":{},"column":{}}}"#,
+                f.rule.id(),
+                f.rule.severity(),
+                json_escape(&f.message),
+                f.line,
+                f.column
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"file":"{}","findings":[{}]}}"#,
+        json_escape(path),
+        findings.join(",")
+    )
+}
+
+fn render_sarif(path: &str, findings: &[Finding]) -> String {
+    let rules: Vec<String> = Rule::ALL
+        .iter()
+        .map(|r| {
+            format!(
+                r#"{{"id":"{}","shortDescription":{{"text":"{}"}},"defaultConfiguration":{{"level":"{}"}}}}"#,
+                r.id(),
+                r.description(),
+                r.severity()
+            )
+        })
+        .collect();
+    let results: Vec<String> = findings
+        .iter()
+        .map(|f| {
+            format!(
+                r#"{{"ruleId":"{}","level":"{}","message":{{"text":"{}"}},"locations":[{{"physicalLocation":{{"artifactLocation":{{"uri":"{}"}},"region":{{"startLine":{},"startColumn":{}}}}}}}]}}"#,
+                f.rule.id(),
+                f.rule.severity(),
+                json_escape(&f.message),
+                json_escape(path),
+                f.line,
+                f.column
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"$schema":"{SARIF_SCHEMA}","version":"2.1.0","runs":[{{"tool":{{"driver":{{"name":"apekey","version":"{}","rules":[{}]}}}},"results":[{}]}}]}}"#,
+        env!("CARGO_PKG_VERSION"),
+        rules.join(","),
+        results.join(",")
+    )
+}
+
+pub fn json_escape(s: &str) -> String {
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut escaped, c| {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                c => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Haddock;
+
+    #[test]
+    fn keymap_checking() {
+        let parser = Parser::new(
+            r#"-- # Keymap
+-- ## Apps
+-- "M-S-<Return>" terminal
+-- "M-f" browser
+-- "M-S-<Return>" another terminal
+-- "M-<Return" broken
+-- "S-M-<Return>" swapped modifiers
+-- "M-p" prompt
+-- "M-p c" calculator prompt
+-- # end
+"#,
+            Haddock::default(),
+            false,
+        );
+        assert_eq!(
+            check(&parser),
+            vec![
+                Finding {
+                    rule: Rule::DuplicateKeys,
+                    message: "`M-S-<Return>` already bound at line 3".into(),
+                    line: 5,
+                    column: 5,
+                },
+                Finding {
+                    rule: Rule::InvalidKeys,
+                    message: "invalid keys `M-<Return`".into(),
+                    line: 6,
+                    column: 5,
+                },
+                Finding {
+                    rule: Rule::DuplicateKeys,
+                    message: "`S-M-<Return>` already bound as `M-S-<Return>` at line 3".into(),
+                    line: 7,
+                    column: 5,
+                },
+                Finding {
+                    rule: Rule::PrefixConflict,
+                    message: "`M-p c` is shadowed by `M-p` bound at line 8".into(),
+                    line: 9,
+                    column: 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_keymap_checking() {
+        let parser = Parser::new(
+            "main = xmonad\n-- # Keymap\n-- ## Apps\n-- \"M-f\" browser\n",
+            Haddock::default(),
+            false,
+        );
+        let findings = check(&parser);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, Rule::UnclosedKeymap);
+        assert_eq!(findings[0].line, 2);
+        assert_eq!(findings[0].rule.severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn keys_validation() {
+        assert!(valid_keys("M-S-<Return>"));
+        assert!(valid_keys("M-x M-y"));
+        assert!(valid_keys("M1-C-l"));
+        assert!(valid_keys("M--"));
+        assert!(valid_keys("M-[1..9]"));
+        assert!(valid_keys("<XF86AudioMute>"));
+        assert!(valid_keys("M-é"));
+        assert!(!valid_keys("M-ab"));
+        assert!(!valid_keys("X-a"));
+        assert!(!valid_keys("M-"));
+        assert!(!valid_keys("<>"));
+        assert!(!valid_keys(""));
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_escape(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+        assert_eq!(json_escape("a\nb\u{1}"), "a\\nb\\u0001");
+    }
+}
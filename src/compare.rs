@@ -0,0 +1,112 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Two keymaps side by side, e.g. two people pairing on one machine
+// looking for the differences of their muscle memory
+
+use std::collections::HashMap;
+
+use crate::token::{canonical_keys, Keybind, Tokens};
+
+/// Keys bound in one keymap at least, with the description of each side
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparedKeys {
+    pub keys: String,
+    pub mine: Option<String>,
+    pub theirs: Option<String>,
+}
+
+impl ComparedKeys {
+    /// Bound on both sides, to the same description
+    pub fn is_same(&self) -> bool {
+        self.mine.is_some() && self.mine == self.theirs
+    }
+}
+
+/// Aligns the keybinds of both keymaps by canonical keys, `S-M-a` being
+/// `M-S-a`. The rows follow my keymap, the keys only bound in theirs
+/// come last in their order.
+pub fn align(mine: &Tokens, theirs: &Tokens) -> Vec<ComparedKeys> {
+    let keybinds = |tokens: &Tokens| -> Vec<Keybind> {
+        tokens
+            .sections
+            .iter()
+            .flat_map(|s| s.keybinds.iter().cloned())
+            .collect()
+    };
+    let mut theirs: Vec<Option<Keybind>> = keybinds(theirs).into_iter().map(Some).collect();
+    // a duplicated binding is aligned with its first declaration
+    let mut by_keys: HashMap<String, usize> = HashMap::new();
+    for (i, keybind) in theirs.iter().enumerate().rev() {
+        if let Some(keybind) = keybind {
+            by_keys.insert(canonical_keys(&keybind.keys), i);
+        }
+    }
+    let mut rows: Vec<ComparedKeys> = keybinds(mine)
+        .into_iter()
+        .map(|keybind| {
+            let theirs = by_keys
+                .remove(&canonical_keys(&keybind.keys))
+                .and_then(|i| theirs[i].take());
+            ComparedKeys {
+                keys: keybind.keys,
+                mine: Some(keybind.description),
+                theirs: theirs.map(|k| k.description),
+            }
+        })
+        .collect();
+    rows.extend(theirs.into_iter().flatten().map(|keybind| ComparedKeys {
+        keys: keybind.keys,
+        mine: None,
+        theirs: Some(keybind.description),
+    }));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Section;
+
+    fn tokens(keybinds: &[(&str, &str)]) -> Tokens {
+        Tokens {
+            sections: vec![Section {
+                title: None,
+                description: None,
+                keybinds: keybinds
+                    .iter()
+                    .map(|(keys, desc)| Keybind::new(keys, desc, None))
+                    .collect(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keymaps_alignment() {
+        let mine = tokens(&[
+            ("M-<Return>", "Terminal"),
+            ("M-S-c", "Close"),
+            ("M-p", "Launcher"),
+        ]);
+        let theirs = tokens(&[("M-p", "dmenu"), ("M-q", "Restart"), ("S-M-c", "Close")]);
+        let row = |keys: &str, mine: Option<&str>, theirs: Option<&str>| ComparedKeys {
+            keys: keys.into(),
+            mine: mine.map(Into::into),
+            theirs: theirs.map(Into::into),
+        };
+        let rows = align(&mine, &theirs);
+        assert_eq!(
+            rows,
+            vec![
+                row("M-<Return>", Some("Terminal"), None),
+                row("M-S-c", Some("Close"), Some("Close")),
+                row("M-p", Some("Launcher"), Some("dmenu")),
+                row("M-q", None, Some("Restart")),
+            ]
+        );
+        assert!(rows[1].is_same());
+        assert!(!rows[2].is_same());
+    }
+}
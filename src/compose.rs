@@ -0,0 +1,151 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Parser of XCompose files, each compose sequence becomes a keybind,
+// e.g. `<Multi_key> <a> <e> : "æ" ae # LATIN SMALL LETTER AE`
+
+use std::env;
+use tracing::{instrument, trace};
+
+use crate::token::{Keybind, Section, Tokens};
+
+const TITLE: &str = "Compose sequences";
+
+/// Path of the user XCompose file, `$XCOMPOSEFILE` or `~/.XCompose`
+pub fn default_path() -> String {
+    env::var("XCOMPOSEFILE").unwrap_or_else(|_| {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".XCompose")
+            .display()
+            .to_string()
+    })
+}
+
+/// Parses an XCompose file. A comment line following a blank line starts
+/// a new section titled by the comment.
+#[instrument(skip_all)]
+pub fn parse(content: &str) -> Tokens {
+    let mut sections = vec![Section {
+        title: None,
+        description: None,
+        keybinds: vec![],
+    }];
+    let mut after_blank = true;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() {
+            after_blank = true;
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            if after_blank {
+                sections.push(Section {
+                    title: Some(comment.trim().to_owned()),
+                    description: None,
+                    keybinds: vec![],
+                });
+            }
+        } else if let Some(keybind) = parse_sequence(line) {
+            trace!("sequence {}", keybind.keys);
+            sections.last_mut().unwrap().keybinds.push(keybind);
+        }
+        after_blank = false;
+    }
+    Tokens {
+        title: Some(TITLE.to_owned()),
+        sections: sections
+            .into_iter()
+            .filter(|s| !s.keybinds.is_empty())
+            .collect(),
+        ignored: vec![],
+        warnings: vec![],
+    }
+}
+
+// `<Multi_key> <a> <e> : "æ" ae # LATIN SMALL LETTER AE`, `include` lines
+// and malformed ones are skipped
+fn parse_sequence(line: &str) -> Option<Keybind> {
+    let (events, result) = line.split_once(':')?;
+    let events: Vec<&str> = events
+        .split_whitespace()
+        .filter(|e| e.starts_with('<') && e.ends_with('>'))
+        .collect();
+    if events.is_empty() {
+        return None;
+    }
+    let (string, rest) = parse_string(result.trim_start())?;
+    let (keysym, comment) = match rest.split_once('#') {
+        Some((keysym, comment)) => (keysym.trim(), comment.trim()),
+        None => (rest.trim(), ""),
+    };
+    let name = if comment.is_empty() { keysym } else { comment };
+    let description = if name.is_empty() {
+        format!("`{string}`")
+    } else {
+        format!("`{string}` {name}")
+    };
+    Some(Keybind::new(&events.join(" "), &description, None))
+}
+
+// parses a double quoted string with `\"` and `\\` escapes, returns its
+// content and what follows
+fn parse_string(input: &str) -> Option<(String, &str)> {
+    let mut chars = input.strip_prefix('"')?.char_indices();
+    let mut string = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((string, &input[i + 2..])),
+            '\\' => string.push(chars.next()?.1),
+            c => string.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_parsing() {
+        let keybind = parse_sequence(r#"<Multi_key> <a> <e> : "æ" ae # LATIN SMALL LETTER AE"#);
+        assert_eq!(
+            keybind.map(|k| (k.keys, k.description)),
+            Some((
+                "<Multi_key> <a> <e>".to_owned(),
+                "`æ` LATIN SMALL LETTER AE".to_owned()
+            ))
+        );
+        let keybind = parse_sequence(r#"<dead_acute> <e> : "é" eacute"#).unwrap();
+        assert_eq!(keybind.description, "`é` eacute");
+        let keybind = parse_sequence(r#"<Multi_key> <q> <q> : "\"" "#).unwrap();
+        assert_eq!(keybind.description, "`\"`");
+        assert!(parse_sequence(r#"include "%L""#).is_none());
+        assert!(parse_sequence(r#"<Multi_key> <a> : "unterminated"#).is_none());
+    }
+
+    #[test]
+    fn xcompose_parsing() {
+        let tokens = parse(
+            r#"# my compose file
+include "%L"
+
+# Greek
+<Multi_key> <g> <a> : "α" alpha
+# beta
+<Multi_key> <g> <b> : "β" beta
+
+# Arrows
+<Multi_key> <minus> <greater> : "→" rightarrow
+"#,
+        );
+        assert_eq!(tokens.title.as_deref(), Some(TITLE));
+        let titles: Vec<_> = tokens.sections.iter().map(|s| s.title.clone()).collect();
+        assert_eq!(
+            titles,
+            vec![Some("Greek".to_owned()), Some("Arrows".to_owned())]
+        );
+        assert_eq!(tokens.keybind_count(), 3);
+    }
+}
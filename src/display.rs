@@ -0,0 +1,231 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{env, fs, process::Command};
+use tracing::{debug, info, instrument};
+
+use crate::layout::{self, Layout};
+
+// the resolution and DPI the default font sizes were chosen for
+const REFERENCE_HEIGHT: f32 = 1080.0;
+const REFERENCE_DPI: f32 = 96.0;
+// backends iced tries in order, see `select_renderer`
+const BACKEND_VAR: &str = "ICED_BACKEND";
+
+/// How the window is rendered
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    /// The GPU if it has a driver, the software renderer otherwise or
+    /// when the GPU fails to start
+    #[default]
+    Auto,
+    /// The GPU only, with wgpu
+    Gpu,
+    /// The CPU, with tiny-skia, e.g. in a VM without GPU driver
+    Software,
+}
+
+/// How the window is shown at startup and when toggled
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    Maximized,
+    /// The whole screen, e.g. for presentations or a kiosk
+    Fullscreen,
+}
+
+/// What the GUI detects of the desktop once at startup, kept across the
+/// config reloads, the CLI commands use the defaults but for `wm`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Desktop {
+    // the running window manager, whose `[wm.<name>]` table applies,
+    // none when the keymap is given on the command line
+    pub wm: Option<String>,
+    // factor of the default font sizes, see `Monitor::font_scale`
+    pub font_scale: f32,
+    // the keyboard layout, used when `keyboard_layout` is set
+    pub layout: Option<Layout>,
+}
+
+impl Default for Desktop {
+    fn default() -> Self {
+        Desktop {
+            wm: None,
+            font_scale: 1.0,
+            layout: None,
+        }
+    }
+}
+
+impl Desktop {
+    pub fn detect() -> Self {
+        Desktop {
+            wm: None,
+            font_scale: primary_monitor().map_or(1.0, |m| m.font_scale()),
+            layout: layout::current(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Monitor {
+    pub width: u32,
+    pub height: u32,
+    pub width_mm: u32,
+    pub height_mm: u32,
+}
+
+impl Monitor {
+    /// Factor to apply to the default font sizes so that they look the
+    /// same on this monitor as on a 1080p, 96 DPI one. Small resolutions
+    /// get smaller fonts to fit, dense screens get bigger fonts to stay
+    /// readable.
+    pub fn font_scale(&self) -> f32 {
+        let by_height = self.height as f32 / REFERENCE_HEIGHT;
+        let scale = if self.height_mm == 0 {
+            by_height
+        } else {
+            let dpi = self.height as f32 / (self.height_mm as f32 / 25.4);
+            by_height.min(dpi / REFERENCE_DPI)
+        };
+        ((scale * 20.0).round() / 20.0).clamp(0.75, 3.0)
+    }
+}
+
+/// Queries xrandr for the primary monitor, or the first connected one
+#[instrument]
+pub fn primary_monitor() -> Option<Monitor> {
+    let output = Command::new("xrandr").arg("--query").output().ok()?;
+    let monitor = parse_xrandr(&String::from_utf8_lossy(&output.stdout));
+    debug!("monitor {:?}", monitor);
+    monitor
+}
+
+fn parse_xrandr(output: &str) -> Option<Monitor> {
+    let connected: Vec<&str> = output
+        .lines()
+        .filter(|l| l.contains(" connected"))
+        .collect();
+    connected
+        .iter()
+        .find(|l| l.contains(" primary "))
+        .into_iter()
+        .chain(connected.iter())
+        .find_map(|l| parse_monitor_line(l))
+}
+
+// e.g. `eDP-1 connected primary 1366x768+0+0 (normal left) 344mm x 193mm`
+fn parse_monitor_line(line: &str) -> Option<Monitor> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (width, height) = words.iter().find_map(|w| {
+        let (size, _) = w.split_once('+')?;
+        let (width, height) = size.split_once('x')?;
+        Some((width.parse().ok()?, height.parse().ok()?))
+    })?;
+    let mm: Vec<u32> = words
+        .iter()
+        .filter_map(|w| w.strip_suffix("mm")?.parse().ok())
+        .collect();
+    Some(Monitor {
+        width,
+        height,
+        width_mm: mm.first().copied().unwrap_or_default(),
+        height_mm: mm.get(1).copied().unwrap_or_default(),
+    })
+}
+
+/// Sets the backends iced tries for the renderer, unless already set
+/// in the environment
+#[instrument]
+pub fn select_renderer(renderer: Renderer) {
+    if let Ok(backends) = env::var(BACKEND_VAR) {
+        debug!("{BACKEND_VAR}={backends} set in the environment");
+        return;
+    }
+    let backends = backends(renderer, has_gpu_driver());
+    info!("rendering with {backends}");
+    env::set_var(BACKEND_VAR, backends);
+}
+
+fn backends(renderer: Renderer, gpu_driver: bool) -> &'static str {
+    match renderer {
+        Renderer::Auto if gpu_driver => "wgpu,tiny-skia",
+        Renderer::Auto | Renderer::Software => "tiny-skia",
+        Renderer::Gpu => "wgpu",
+    }
+}
+
+// a DRM render node exists when a GPU driver is loaded, Mesa is told
+// to render in software on machines where the driver is broken
+fn has_gpu_driver() -> bool {
+    let render_node = fs::read_dir("/dev/dri").is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| entry.file_name().to_string_lossy().starts_with("renderD"))
+    });
+    let forced_software =
+        env::var("LIBGL_ALWAYS_SOFTWARE").is_ok_and(|value| matches!(value.as_str(), "1" | "true"));
+    debug!("render node {render_node}, software forced {forced_software}");
+    render_node && !forced_software
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xrandr_parsing() {
+        let output = r#"Screen 0: minimum 8 x 8, current 5206x2160, maximum 32767 x 32767
+eDP-1 connected 1366x768+3840+0 (normal left inverted right x axis y axis) 344mm x 193mm
+   1366x768      60.00*+
+HDMI-1 connected primary 3840x2160+0+0 (normal left inverted right x axis y axis) 597mm x 336mm
+DP-1 disconnected (normal left inverted right x axis y axis)"#;
+        assert_eq!(
+            parse_xrandr(output),
+            Some(Monitor {
+                width: 3840,
+                height: 2160,
+                width_mm: 597,
+                height_mm: 336
+            })
+        );
+        assert_eq!(
+            parse_xrandr("eDP-1 connected 1366x768+0+0 (normal) 344mm x 193mm"),
+            Some(Monitor {
+                width: 1366,
+                height: 768,
+                width_mm: 344,
+                height_mm: 193
+            })
+        );
+        assert_eq!(parse_xrandr("DP-1 disconnected (normal)"), None);
+    }
+
+    #[test]
+    fn font_scaling() {
+        let monitor = |height, height_mm| Monitor {
+            width: 0,
+            height,
+            width_mm: 0,
+            height_mm,
+        };
+        assert_eq!(monitor(1080, 300).font_scale(), 0.95);
+        assert_eq!(monitor(768, 193).font_scale(), 0.75);
+        assert_eq!(monitor(2160, 336).font_scale(), 1.7);
+        assert_eq!(monitor(2160, 0).font_scale(), 2.0);
+    }
+
+    #[test]
+    fn renderer_backends() {
+        assert_eq!(backends(Renderer::Auto, true), "wgpu,tiny-skia");
+        assert_eq!(backends(Renderer::Auto, false), "tiny-skia");
+        assert_eq!(backends(Renderer::Gpu, false), "wgpu");
+        assert_eq!(backends(Renderer::Software, true), "tiny-skia");
+    }
+}
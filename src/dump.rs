@@ -0,0 +1,127 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Parser of the keybindings listed by xmonad itself, e.g. the
+// `showKm` dump of XMonad.Util.NamedActions or the help message of the
+// default config. They are already described, the xmonad.hs is not read.
+//
+// Applications:
+// M-S-Return        Launch a terminal
+// M-p               Launch dmenu
+
+use tracing::{instrument, trace};
+
+use crate::{
+    terminal,
+    token::{Keybind, Section, Tokens},
+};
+
+/// Parses the dump. The keys and their description are separated by a
+/// tab or two spaces at least, the other lines are section titles, e.g.
+/// `Applications:` or `-- launching and killing programs`.
+#[instrument(skip_all)]
+pub fn parse(content: &str) -> Tokens {
+    let mut sections = vec![Section {
+        title: None,
+        description: None,
+        keybinds: vec![],
+    }];
+    for line in content.lines().map(str::trim) {
+        // blank lines and the underlines of the titles
+        if line.chars().all(|c| c == '-' || c == '=') {
+            continue;
+        }
+        match split_line(line)
+            .and_then(|(keys, description)| Some((parse_keys(keys)?, description)))
+        {
+            Some((keys, description)) => {
+                trace!("dumped keybind {keys}");
+                sections
+                    .last_mut()
+                    .unwrap()
+                    .keybinds
+                    .push(Keybind::new(&keys, description, None));
+            }
+            None => {
+                let title = line.trim_start_matches("--").trim().trim_end_matches(':');
+                sections.push(Section {
+                    title: Some(title.to_owned()),
+                    description: None,
+                    keybinds: vec![],
+                });
+            }
+        }
+    }
+    Tokens {
+        title: None,
+        sections: sections
+            .into_iter()
+            .filter(|s| !s.keybinds.is_empty())
+            .collect(),
+        ignored: vec![],
+        warnings: vec![],
+    }
+}
+
+// splits at the first tab or run of two spaces
+fn split_line(line: &str) -> Option<(&str, &str)> {
+    let at = match (line.find('\t'), line.find("  ")) {
+        (Some(tab), Some(spaces)) => tab.min(spaces),
+        (tab, spaces) => tab.or(spaces)?,
+    };
+    Some((line[..at].trim(), line[at..].trim()))
+}
+
+/// Converts the keys to the EZConfig notation, e.g. `mod-Shift-Enter`
+/// gives `M-S-<Return>` and `M-S-Return` gives `M-S-<Return>`
+fn parse_keys(keys: &str) -> Option<String> {
+    keys.split_whitespace()
+        .map(|chord| {
+            // `M--` is the `-` key
+            let (modifiers, key) = match chord.strip_suffix("--") {
+                Some(modifiers) => (modifiers, "-"),
+                None => chord.rsplit_once('-').unwrap_or(("", chord)),
+            };
+            terminal::chord(modifiers.split('-').chain([key])).ok()
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|chords| chords.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_parsing() {
+        let content = "\
+Applications:
+M-S-Return        Launch a terminal
+M-p\tLaunch dmenu
+
+-- quit, or restart
+mod-Shift-q       Quit xmonad
+M-x M--           Shrink
+Unnamed line
+";
+        let tokens = parse(content);
+        let titles: Vec<_> = tokens.sections.iter().map(|s| s.title.as_deref()).collect();
+        assert_eq!(titles, vec![Some("Applications"), Some("quit, or restart")]);
+        let keybinds: Vec<_> = tokens
+            .sections
+            .iter()
+            .flat_map(|s| &s.keybinds)
+            .map(|k| (k.keys.as_str(), k.description.as_str()))
+            .collect();
+        assert_eq!(
+            keybinds,
+            vec![
+                ("M-S-<Return>", "Launch a terminal"),
+                ("M-p", "Launch dmenu"),
+                ("M-S-q", "Quit xmonad"),
+                ("M-x M--", "Shrink"),
+            ]
+        );
+    }
+}
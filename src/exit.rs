@@ -0,0 +1,88 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Exit codes of apekey, stable so that the scripts running it can branch
+// on the outcome, e.g. `apekey --pick --query vol || notify-send …`,
+// 0 when it succeeds
+
+use std::fmt;
+
+use crate::parser::ParseFailure;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    /// Any other failure, e.g. `check` finding errors or the picker closed
+    /// without picking
+    Failure = 1,
+    /// The config could not be read, e.g. not found, a directory or the
+    /// compiled xmonad
+    Unreadable = 2,
+    /// The config is not annotated or its keymap fails to be parsed
+    Invalid = 3,
+    /// `--query` matches no keybind in pick mode
+    NoMatch = 4,
+}
+
+impl Exit {
+    /// The exit code of an error, `Failure` unless it is a `Failure` error
+    /// or a parse failure
+    pub fn of(e: &eyre::Report) -> Self {
+        if let Some(failure) = e.downcast_ref::<Failure>() {
+            failure.exit
+        } else if e.downcast_ref::<ParseFailure>().is_some() {
+            Exit::Invalid
+        } else {
+            Exit::Failure
+        }
+    }
+
+    pub fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
+
+/// An error ending apekey with a given exit code
+#[derive(Debug)]
+pub struct Failure {
+    pub exit: Exit,
+    message: String,
+}
+
+impl Failure {
+    pub fn report(exit: Exit, message: String) -> eyre::Report {
+        eyre::Report::new(Failure { exit, message })
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app::read_error, parser::Parser};
+    use std::io;
+
+    #[test]
+    fn errors_exit_codes() {
+        let not_found = read_error("xmonad.hs", io::ErrorKind::NotFound.into());
+        assert_eq!(Exit::of(&not_found), Exit::Unreadable);
+        assert!(not_found
+            .to_string()
+            .starts_with("Config file xmonad.hs not found"));
+        let wrapped = not_found.wrap_err("compared keymap");
+        assert_eq!(Exit::of(&wrapped), Exit::Unreadable);
+
+        let not_annotated = Parser::new("main = xmonad def\n", Default::default(), false)
+            .tokens()
+            .unwrap_err();
+        assert_eq!(Exit::of(&not_annotated), Exit::Invalid);
+        assert_eq!(Exit::of(&eyre::eyre!("failed to write")), Exit::Failure);
+    }
+}
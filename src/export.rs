@@ -0,0 +1,804 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::ValueEnum;
+use eyre::{eyre, Result};
+use iced::Color;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, instrument, warn};
+
+use crate::{
+    app::{read_error, read_keymap, read_stdin, AppConfig, Profile, STDIN_PATH},
+    cache::ParseCache,
+    compose, dump,
+    markup::{self, Span},
+    source,
+    template::{Align, Field, RowTemplate, Segment},
+    token::{render_keys, ActionKind, ScoredKeybind, Tokens},
+    user_config::UserConfig,
+};
+
+const DEFAULT_TITLE: &str = "Keymap";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// One-line output for a polybar `custom/script` module
+    Polybar,
+    /// One-line output for an xmobar `Com` command
+    Xmobar,
+    /// Scalable vector cheat sheet
+    Svg,
+    /// Anki flashcards, one per keybind, to import as a deck
+    Anki,
+    /// LaTeX multi-column reference card, to typeset with pdflatex
+    Latex,
+    /// Plain text, one line per keybind laid out by `row_template`
+    Text,
+    /// A Markdown table per section, e.g. for the dotfiles docs
+    Markdown,
+}
+
+impl ExportFormat {
+    /// Extension of the files written by `--all`
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Polybar
+            | ExportFormat::Xmobar
+            | ExportFormat::Anki
+            | ExportFormat::Text => "txt",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Latex => "tex",
+            ExportFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Format of the search results copied with `Ctrl+Shift+C`
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardFormat {
+    /// A table per section, under its title
+    #[default]
+    Markdown,
+    /// Tab separated keys, description and section
+    Tsv,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Wrap the output in bar markup using the theme colors
+    pub markup: bool,
+    /// Show a different keybind every `cycle` seconds instead of a summary
+    pub cycle: Option<u64>,
+    /// Only export the keybinds of this kind of action
+    pub kind: Option<ActionKind>,
+}
+
+/// Reads and parses the xmonad config, then renders it in the given format
+#[instrument(skip(config))]
+pub fn run(config: &AppConfig, format: ExportFormat, options: &ExportOptions) -> Result<String> {
+    Ok(render(read_tokens(config)?, config, format, options))
+}
+
+/// Exports the keymap of every profile to a file of `out_dir`, the
+/// default one and the one of each `[wm.<name>]` table, named after it,
+/// with the sections of its sources like in the window. A profile
+/// reading the standard input is skipped.
+#[instrument(skip(user_config))]
+pub fn run_all(
+    user_config: &UserConfig,
+    format: ExportFormat,
+    options: &ExportOptions,
+    out_dir: &Path,
+) -> Result<String> {
+    fs::create_dir_all(out_dir).map_err(|e| eyre!("{}: {e}", out_dir.display()))?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let mut written = vec![];
+    for (name, profile_config) in user_config.profiles() {
+        let config = AppConfig {
+            profile: profile_config.profile.unwrap_or_default(),
+            ..AppConfig::from(profile_config)
+        };
+        if config.config_path == STDIN_PATH {
+            warn!("{name} profile skipped, it reads the standard input");
+            continue;
+        }
+        let mut tokens = match read_tokens(&config) {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                warn!("{name} profile skipped: {e}");
+                continue;
+            }
+        };
+        tokens.sections.extend(runtime.block_on(source::sections(
+            &config.sources,
+            config.haddock,
+            config.fallback_description,
+            &ParseCache::default(),
+        )));
+        let path = out_dir.join(format!("{name}.{}", format.extension()));
+        fs::write(&path, render(tokens, &config, format, options) + "\n")
+            .map_err(|e| eyre!("{}: {e}", path.display()))?;
+        debug!("{name} profile exported to {}", path.display());
+        written.push(path.display().to_string());
+    }
+    if written.is_empty() {
+        return Err(eyre!("no profile exported"));
+    }
+    Ok(written.join("\n"))
+}
+
+fn render(
+    mut tokens: Tokens,
+    config: &AppConfig,
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> String {
+    if let Some(layout) = &config.layout {
+        tokens.translate_keys(layout);
+    }
+    if let Some(kind) = options.kind {
+        tokens = tokens.with_kind(kind);
+    }
+    tokens.sort_sections(config.section_sort);
+    debug!("exporting {} keybinds", tokens.keybind_count());
+    match format {
+        ExportFormat::Polybar | ExportFormat::Xmobar => {
+            render_bar(&tokens, config, format, options, unix_time())
+        }
+        ExportFormat::Svg => render_svg(&tokens, config),
+        ExportFormat::Anki => render_anki(&tokens, config),
+        ExportFormat::Latex => render_latex(&tokens, config),
+        ExportFormat::Text => render_text(&tokens, config),
+        ExportFormat::Markdown => format!(
+            "# {}\n\n{}",
+            tokens.title.as_deref().unwrap_or(DEFAULT_TITLE),
+            render_markdown(&tokens, config)
+        ),
+    }
+}
+
+/// Reads and parses the config of the profile, without running apekey
+pub fn read_tokens(config: &AppConfig) -> Result<Tokens> {
+    Ok(match config.profile {
+        // a line at a time, a generated config may be huge
+        Profile::Xmonad => read_keymap(config)?.tokens()?,
+        Profile::Xcompose => compose::parse(&read_content(config)?),
+        Profile::Dump => dump::parse(&read_content(config)?),
+    })
+}
+
+fn read_content(config: &AppConfig) -> Result<String> {
+    if config.config_path == STDIN_PATH {
+        Ok(read_stdin()?)
+    } else {
+        fs::read_to_string(&config.config_path).map_err(|e| read_error(&config.config_path, e))
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn render_bar(
+    tokens: &Tokens,
+    config: &AppConfig,
+    format: ExportFormat,
+    options: &ExportOptions,
+    now: u64,
+) -> String {
+    let palette = config.theme.palette();
+    let xmobar = format == ExportFormat::Xmobar;
+    let colorize = |text: &str, color: Color| -> String {
+        match (options.markup, xmobar) {
+            (false, _) => text.to_owned(),
+            (true, true) => format!("<fc={}>{}</fc>", hex_color(color), xmobar_escape(text)),
+            (true, false) => format!("%{{F{}}}{text}%{{F-}}", hex_color(color)),
+        }
+    };
+    let plain = |text: &str| -> String {
+        if options.markup && xmobar {
+            xmobar_escape(text)
+        } else {
+            text.to_owned()
+        }
+    };
+
+    let keybinds = tokens.keybinds();
+    match options.cycle {
+        // bar modules call us periodically, derive the keybind to show
+        // from the current time so that each slot shows the next one
+        Some(interval) if !keybinds.is_empty() => {
+            let index = (now / interval.max(1)) as usize % keybinds.len();
+            let template = config.row_template.clone().unwrap_or_else(|| {
+                RowTemplate(vec![
+                    field(Field::Keys, None),
+                    Segment::Literal(" ".to_owned()),
+                    field(Field::Description, None),
+                ])
+            });
+            template.render(
+                row_value(&keybinds[index], config),
+                |field, text| match field {
+                    Some(Field::Keys) => colorize(&text, palette.primary),
+                    _ => plain(&text),
+                },
+            )
+        }
+        _ => {
+            let title = tokens.title.as_deref().unwrap_or(DEFAULT_TITLE);
+            format!(
+                "{} {}",
+                colorize(title, palette.primary),
+                plain(&format!(
+                    "{} keybinds, {} sections",
+                    tokens.keybind_count(),
+                    tokens.section_count()
+                ))
+            )
+        }
+    }
+}
+
+fn field(field: Field, width: Option<(Align, usize)>) -> Segment {
+    Segment::Field { field, width }
+}
+
+/// Text of each field of the row template
+pub fn row_value<'a>(
+    keybind: &'a ScoredKeybind,
+    config: &'a AppConfig,
+) -> impl Fn(Field) -> String + 'a {
+    move |field| match field {
+        Field::Keys => render_keys(&keybind.keys, &config.key_style),
+        // one line per keybind
+        Field::Description => markup::strip(&keybind.description).replace('\n', " "),
+        Field::Usage => keybind
+            .usage
+            .map(|count| format!("×{count}"))
+            .unwrap_or_default(),
+        Field::Action => keybind.action.clone().unwrap_or_default(),
+    }
+}
+
+/// Renders the title then each section, its title followed by its
+/// keybinds composed by the row template, by default the keys column
+/// of the window then the description
+fn render_text(tokens: &Tokens, config: &AppConfig) -> String {
+    let template = config.row_template.clone().unwrap_or_else(|| {
+        RowTemplate(vec![
+            field(
+                Field::Keys,
+                Some((Align::Left, usize::from(config.ui.key_column))),
+            ),
+            Segment::Literal("  ".to_owned()),
+            field(Field::Description, None),
+        ])
+    });
+    let mut lines = vec![tokens.title.as_deref().unwrap_or(DEFAULT_TITLE).to_owned()];
+    for section in &tokens.sections {
+        lines.push(String::new());
+        if let Some(title) = &section.title {
+            lines.push(title.clone());
+        }
+        for keybind in &section.keybinds {
+            let row = template.render(row_value(&keybind.into(), config), |_, text| text);
+            lines.push(row.trim_end().to_owned());
+        }
+    }
+    lines.join("\n")
+}
+
+/// Renders the keybinds to paste in a chat or a document
+pub fn render_clipboard(tokens: &Tokens, config: &AppConfig, format: ClipboardFormat) -> String {
+    match format {
+        ClipboardFormat::Markdown => render_markdown(tokens, config),
+        ClipboardFormat::Tsv => render_tsv(tokens, config),
+    }
+}
+
+fn render_markdown(tokens: &Tokens, config: &AppConfig) -> String {
+    let mut blocks = vec![];
+    for section in tokens.sections.iter().filter(|s| !s.keybinds.is_empty()) {
+        let mut lines = vec![];
+        if let Some(title) = &section.title {
+            lines.push(format!("### {title}\n"));
+        }
+        lines.push("| Keys | Description |".to_owned());
+        lines.push("| --- | --- |".to_owned());
+        for keybind in &section.keybinds {
+            let description: String = markup::parse(&keybind.description)
+                .into_iter()
+                .map(|span| match span {
+                    Span::Text(text) => markdown_escape(text),
+                    Span::Bold(text) => format!("**{}**", markdown_escape(text)),
+                    Span::Code(text) => format!("`{}`", text.replace('|', "\\|")),
+                })
+                .collect::<String>()
+                // a table row is a single line
+                .replace('\n', "<br>");
+            lines.push(format!(
+                "| `{}` | {description} |",
+                render_keys(&keybind.keys, &config.key_style).replace('|', "\\|")
+            ));
+        }
+        blocks.push(lines.join("\n"));
+    }
+    blocks.join("\n\n")
+}
+
+// the characters Markdown would take as markup or as a cell separator
+fn markdown_escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '|' | '*' | '_' | '`' | '<' | '[' | ']' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+fn render_tsv(tokens: &Tokens, config: &AppConfig) -> String {
+    let field = |text: &str| text.replace(['\t', '\n'], " ");
+    tokens
+        .sections
+        .iter()
+        .flat_map(|section| {
+            section.keybinds.iter().map(|keybind| {
+                format!(
+                    "{}\t{}\t{}",
+                    field(&render_keys(&keybind.keys, &config.key_style)),
+                    field(&markup::strip(&keybind.description)),
+                    field(section.title.as_deref().unwrap_or_default())
+                )
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// SVG layout, in pixels
+const SVG_COLUMNS: usize = 3;
+const SVG_MARGIN: f32 = 40.0;
+const SVG_GUTTER: f32 = 40.0;
+const SVG_LINE_HEIGHT: f32 = 1.6;
+// average advance of a monospace glyph relative to the font size
+const SVG_GLYPH_WIDTH: f32 = 0.6;
+
+/// Renders the sections on a grid, each section goes in the shortest column
+fn render_svg(tokens: &Tokens, config: &AppConfig) -> String {
+    let palette = config.theme.palette();
+    let ui = &config.ui;
+    let (title_size, section_size, keybind_size, text_size) = (
+        f32::from(ui.title_size),
+        f32::from(ui.section_size),
+        f32::from(ui.keybind_size),
+        f32::from(ui.text_size),
+    );
+    let line = keybind_size.max(text_size) * SVG_LINE_HEIGHT;
+
+    let keys_width = tokens
+        .sections
+        .iter()
+        .flat_map(|s| &s.keybinds)
+        .map(|k| render_keys(&k.keys, &config.key_style).chars().count())
+        .max()
+        .unwrap_or_default() as f32
+        * keybind_size
+        * SVG_GLYPH_WIDTH;
+    let column_width = tokens
+        .sections
+        .iter()
+        .flat_map(|s| &s.keybinds)
+        .map(|k| k.description.chars().count())
+        .max()
+        .unwrap_or_default() as f32
+        * text_size
+        * SVG_GLYPH_WIDTH
+        + keys_width
+        + text_size;
+
+    let top = SVG_MARGIN + title_size * SVG_LINE_HEIGHT * 1.5;
+    let mut heights = [top; SVG_COLUMNS];
+    let mut body = String::new();
+    for section in &tokens.sections {
+        let (column, y) = heights
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, top));
+        let x = SVG_MARGIN + column as f32 * (column_width + SVG_GUTTER);
+        let mut y = y;
+        if let Some(title) = &section.title {
+            y += section_size * SVG_LINE_HEIGHT;
+            body.push_str(&format!(
+                "  <text x=\"{x:.1}\" y=\"{y:.1}\" font-size=\"{section_size}\" font-weight=\"bold\">{}</text>\n",
+                xml_escape(title)
+            ));
+        }
+        if let Some(description) = &section.description {
+            y += text_size * SVG_LINE_HEIGHT;
+            body.push_str(&format!(
+                "  <text x=\"{x:.1}\" y=\"{y:.1}\" font-size=\"{text_size}\" fill-opacity=\"0.6\">{}</text>\n",
+                xml_escape(description)
+            ));
+        }
+        for keybind in &section.keybinds {
+            y += line;
+            body.push_str(&format!(
+                "  <text x=\"{x:.1}\" y=\"{y:.1}\" font-size=\"{keybind_size}\" fill=\"{}\">{}</text>\n",
+                hex_color(palette.primary),
+                xml_escape(&render_keys(&keybind.keys, &config.key_style))
+            ));
+            body.push_str(&format!(
+                "  <text x=\"{:.1}\" y=\"{y:.1}\" font-size=\"{text_size}\">{}</text>\n",
+                x + keys_width + text_size,
+                svg_description(&keybind.description, palette.primary)
+            ));
+        }
+        heights[column] = y + SVG_GUTTER;
+    }
+
+    let width = 2.0 * SVG_MARGIN
+        + SVG_COLUMNS as f32 * column_width
+        + (SVG_COLUMNS - 1) as f32 * SVG_GUTTER;
+    let height = heights.iter().copied().fold(top, f32::max) + SVG_MARGIN - SVG_GUTTER;
+    let title = tokens.title.as_deref().unwrap_or(DEFAULT_TITLE);
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.1}\" height=\"{height:.1}\" viewBox=\"0 0 {width:.1} {height:.1}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n\
+         <g font-family=\"JetBrains Mono, monospace\" fill=\"{}\">\n\
+         \x20 <text x=\"{SVG_MARGIN}\" y=\"{:.1}\" font-size=\"{title_size}\">{}</text>\n\
+         {body}</g>\n\
+         </svg>",
+        hex_color(palette.background),
+        hex_color(palette.text),
+        SVG_MARGIN + title_size,
+        xml_escape(title),
+    )
+}
+
+/// Renders a tab separated file Anki imports as notes, the description
+/// on the front, the keys on the back and the section as a tag
+fn render_anki(tokens: &Tokens, config: &AppConfig) -> String {
+    let mut lines = vec![
+        "#separator:tab".to_owned(),
+        "#html:false".to_owned(),
+        "#tags column:3".to_owned(),
+    ];
+    for section in &tokens.sections {
+        // a tag can't hold spaces
+        let tag = section
+            .title
+            .as_deref()
+            .unwrap_or(DEFAULT_TITLE)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("_");
+        for keybind in &section.keybinds {
+            let description: String = markup::parse(&keybind.description)
+                .iter()
+                .map(Span::content)
+                .collect();
+            lines.push(format!(
+                "{}\t{}\t{}",
+                anki_field(&description),
+                anki_field(&render_keys(&keybind.keys, &config.key_style)),
+                anki_field(&tag)
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+// fields holding a separator or a quote are quoted, quotes doubled
+fn anki_field(text: &str) -> String {
+    if text.contains(['\t', '\n', '"']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+const LATEX_COLUMNS: usize = 3;
+const LATEX_PREAMBLE: &str = r"\documentclass[10pt,landscape]{article}
+\usepackage[margin=1cm]{geometry}
+\usepackage[T1]{fontenc}
+\usepackage[utf8]{inputenc}
+\usepackage{multicol}
+\usepackage{tabularx}
+\setlength{\parindent}{0pt}
+\setlength{\columnsep}{1cm}
+\pagestyle{empty}
+";
+
+/// Renders a reference card, the sections flowing through the columns,
+/// each one kept in a single column
+fn render_latex(tokens: &Tokens, config: &AppConfig) -> String {
+    let title = tokens.title.as_deref().unwrap_or(DEFAULT_TITLE);
+    let mut tex = format!(
+        "{LATEX_PREAMBLE}\n\\begin{{document}}\n{{\\Large\\bfseries {}}}\\par\\medskip\n\\begin{{multicols}}{{{LATEX_COLUMNS}}}\n",
+        latex_escape(title)
+    );
+    for section in &tokens.sections {
+        tex.push_str("\\begin{minipage}{\\linewidth}\n");
+        if let Some(title) = &section.title {
+            tex.push_str(&format!("\\textbf{{{}}}\\par\n", latex_escape(title)));
+        }
+        if let Some(description) = &section.description {
+            tex.push_str(&format!(
+                "{{\\small\\itshape {}}}\\par\n",
+                latex_escape(description)
+            ));
+        }
+        tex.push_str("\\smallskip\n\\begin{tabularx}{\\linewidth}{@{}l X@{}}\n");
+        for keybind in &section.keybinds {
+            tex.push_str(&format!(
+                "\\texttt{{{}}} & {} \\\\\n",
+                latex_escape(&render_keys(&keybind.keys, &config.key_style)),
+                latex_description(&keybind.description)
+            ));
+        }
+        tex.push_str("\\end{tabularx}\n\\end{minipage}\\par\\medskip\n");
+    }
+    tex.push_str("\\end{multicols}\n\\end{document}");
+    tex
+}
+
+// converts the description markup to bold and typewriter text
+fn latex_description(desc: &str) -> String {
+    markup::parse(desc)
+        .into_iter()
+        .map(|span| match span {
+            Span::Text(t) => latex_escape(t),
+            Span::Bold(t) => format!("\\textbf{{{}}}", latex_escape(t)),
+            Span::Code(t) => format!("\\texttt{{{}}}", latex_escape(t)),
+        })
+        .collect()
+}
+
+fn latex_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' => r"\textbackslash{}".to_owned(),
+            '~' => r"\textasciitilde{}".to_owned(),
+            '^' => r"\textasciicircum{}".to_owned(),
+            // `<` and `>` are other glyphs in the T1 text fonts
+            '<' => r"\textless{}".to_owned(),
+            '>' => r"\textgreater{}".to_owned(),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => format!("\\{c}"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+// converts the description markup to styled `tspan`s
+fn svg_description(desc: &str, code_color: Color) -> String {
+    markup::parse(desc)
+        .into_iter()
+        .map(|span| match span {
+            Span::Text(t) => xml_escape(t),
+            Span::Bold(t) => format!("<tspan font-weight=\"bold\">{}</tspan>", xml_escape(t)),
+            Span::Code(t) => format!(
+                "<tspan fill=\"{}\">{}</tspan>",
+                hex_color(code_color),
+                xml_escape(t)
+            ),
+        })
+        .collect()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats a color as `#rrggbb`
+pub fn hex_color(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// xmobar parses its markup from the text, `<` has to be escaped
+fn xmobar_escape(text: &str) -> String {
+    text.replace('<', "<raw=1:</>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        token::{Keybind, Section},
+        user_config::UserConfig,
+    };
+
+    #[test]
+    fn latex_rendering() {
+        let tokens = Tokens {
+            title: Some("My keymap".into()),
+            sections: vec![Section {
+                title: Some("Apps & tools".into()),
+                description: None,
+                keybinds: vec![Keybind::new("M-S-<Return>", "Open `~/notes` *now*", None)],
+            }],
+            ignored: vec![],
+            warnings: vec![],
+        };
+        let tex = render_latex(&tokens, &AppConfig::from(UserConfig::default()));
+        assert!(tex.starts_with(LATEX_PREAMBLE));
+        assert!(tex.contains("{\\Large\\bfseries My keymap}"));
+        assert!(tex.contains("\\begin{multicols}{3}"));
+        assert!(tex.contains("\\textbf{Apps \\& tools}\\par"));
+        assert!(tex.contains(
+            "\\texttt{M-S-\\textless{}Return\\textgreater{}} & \
+             Open \\texttt{\\textasciitilde{}/notes} \\textbf{now} \\\\"
+        ));
+        assert!(tex.ends_with("\\end{multicols}\n\\end{document}"));
+    }
+
+    #[test]
+    fn text_rendering() {
+        let tokens = Tokens {
+            title: None,
+            sections: vec![Section {
+                title: Some("Apps".into()),
+                description: None,
+                keybinds: vec![
+                    Keybind::new("M-p", "Launch *dmenu*", None),
+                    Keybind::new("M-S-<Return>", "Terminal", None),
+                ],
+            }],
+            ignored: vec![],
+            warnings: vec![],
+        };
+        let mut config = AppConfig::from(UserConfig::default());
+        config.ui.key_column = 14;
+        assert_eq!(
+            render_text(&tokens, &config),
+            "Keymap\n\nApps\nM-p             Launch dmenu\nM-S-<Return>    Terminal"
+        );
+        config.row_template = RowTemplate::parse("{description:>14} │ {keys}").ok();
+        assert_eq!(
+            render_text(&tokens, &config),
+            "Keymap\n\nApps\n  Launch dmenu │ M-p\n      Terminal │ M-S-<Return>"
+        );
+    }
+
+    #[test]
+    fn clipboard_rendering() {
+        let tokens = Tokens {
+            title: None,
+            sections: vec![Section {
+                title: Some("Media".into()),
+                description: None,
+                keybinds: vec![
+                    Keybind::new("<XF86AudioMute>", "Mute *all* | `pactl`", None),
+                    Keybind::new("M-m", "Play\tpause", None),
+                ],
+            }],
+            ignored: vec![],
+            warnings: vec![],
+        };
+        let config = AppConfig::from(UserConfig::default());
+        assert_eq!(
+            render_clipboard(&tokens, &config, ClipboardFormat::Markdown),
+            "### Media\n\n| Keys | Description |\n| --- | --- |\n\
+             | `<XF86AudioMute>` | Mute **all** \\| `pactl` |\n\
+             | `M-m` | Play\tpause |"
+        );
+        assert_eq!(
+            render_clipboard(&tokens, &config, ClipboardFormat::Tsv),
+            "<XF86AudioMute>\tMute all | pactl\tMedia\nM-m\tPlay pause\tMedia"
+        );
+    }
+
+    #[test]
+    fn bar_rendering() {
+        let tokens = Tokens {
+            title: Some("Keys <xmonad>".into()),
+            sections: vec![Section {
+                title: Some("Apps".into()),
+                description: None,
+                keybinds: vec![
+                    Keybind::new("M-p", "Launch <dmenu>", None),
+                    Keybind::new("M-S-<Return>", "Terminal", None),
+                    Keybind::new("M-q", "Quit", None),
+                ],
+            }],
+            ignored: vec![],
+            warnings: vec![],
+        };
+        let config = AppConfig::from(UserConfig::default());
+        let primary = hex_color(config.theme.palette().primary);
+        let options = |markup, cycle| ExportOptions {
+            markup,
+            cycle,
+            kind: None,
+        };
+        let bar = |format, options: &ExportOptions, now| {
+            render_bar(&tokens, &config, format, options, now)
+        };
+
+        let summary = options(false, None);
+        assert_eq!(
+            bar(ExportFormat::Polybar, &summary, 0),
+            "Keys <xmonad> 3 keybinds, 1 sections"
+        );
+        assert_eq!(
+            bar(ExportFormat::Polybar, &options(true, None), 0),
+            format!("%{{F{primary}}}Keys <xmonad>%{{F-}} 3 keybinds, 1 sections")
+        );
+        assert_eq!(
+            bar(ExportFormat::Xmobar, &options(true, None), 0),
+            format!("<fc={primary}>Keys <raw=1:</>xmonad></fc> 3 keybinds, 1 sections")
+        );
+
+        // the keybind of the slot of `now`, wrapping around
+        let cycle = options(false, Some(10));
+        assert_eq!(bar(ExportFormat::Polybar, &cycle, 9), "M-p Launch <dmenu>");
+        assert_eq!(
+            bar(ExportFormat::Polybar, &cycle, 10),
+            "M-S-<Return> Terminal"
+        );
+        assert_eq!(bar(ExportFormat::Polybar, &cycle, 25), "M-q Quit");
+        assert_eq!(bar(ExportFormat::Polybar, &cycle, 30), "M-p Launch <dmenu>");
+        // a zero interval is one second
+        assert_eq!(
+            bar(ExportFormat::Polybar, &options(false, Some(0)), 4),
+            "M-S-<Return> Terminal"
+        );
+        assert_eq!(
+            bar(ExportFormat::Xmobar, &options(true, Some(10)), 15),
+            format!("<fc={primary}>M-S-<raw=1:</>Return></fc> Terminal")
+        );
+        assert_eq!(
+            bar(ExportFormat::Xmobar, &options(true, Some(10)), 0),
+            format!("<fc={primary}>M-p</fc> Launch <raw=1:</>dmenu>")
+        );
+    }
+
+    #[test]
+    fn xmobar_escaping() {
+        assert_eq!(xmobar_escape("no markup"), "no markup");
+        assert_eq!(
+            xmobar_escape("<fc=red>M-<Tab></fc>"),
+            "<raw=1:</>fc=red>M-<raw=1:</>Tab><raw=1:</>/fc>"
+        );
+    }
+
+    #[test]
+    fn anki_rendering() {
+        let tokens = Tokens {
+            title: None,
+            sections: vec![Section {
+                title: Some("Window focus".into()),
+                description: None,
+                keybinds: vec![
+                    Keybind::new("M-j", "Focus *next* window", None),
+                    Keybind::new("M-S-<Return>", r#"Spawn "term""#, None),
+                ],
+            }],
+            ignored: vec![],
+            warnings: vec![],
+        };
+        assert_eq!(
+            render_anki(&tokens, &AppConfig::from(UserConfig::default())),
+            "#separator:tab\n#html:false\n#tags column:3\n\
+             Focus next window\tM-j\tWindow_focus\n\
+             \"Spawn \"\"term\"\"\"\tM-S-<Return>\tWindow_focus"
+        );
+    }
+}
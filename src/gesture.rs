@@ -0,0 +1,90 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Touch gestures, iced only reports the fingers so the pinch is
+// recognized from the distance between two of them
+
+use iced::{
+    touch::{self, Finger},
+    Point,
+};
+use std::{collections::HashMap, ops::RangeInclusive};
+
+/// Bounds of the zoom factor applied to the font sizes
+const ZOOM_RANGE: RangeInclusive<f32> = 0.5..=3.0;
+
+/// Tracks the fingers on the screen to turn a pinch into a zoom factor
+#[derive(Debug, Default)]
+pub struct Pinch {
+    fingers: HashMap<Finger, Point>,
+    // distance between the two fingers and zoom when the pinch started
+    start: Option<(f32, f32)>,
+}
+
+impl Pinch {
+    /// Returns the new zoom factor when the event moves a pinch
+    pub fn update(&mut self, event: touch::Event, zoom: f32) -> Option<f32> {
+        match event {
+            touch::Event::FingerPressed { id, position } => {
+                self.fingers.insert(id, position);
+                self.start = self.distance().map(|distance| (distance, zoom));
+                None
+            }
+            touch::Event::FingerMoved { id, position } => {
+                self.fingers.insert(id, position);
+                let (start, start_zoom) = self.start?;
+                let distance = self.distance()?;
+                (start > 0.0).then(|| {
+                    (start_zoom * distance / start).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end())
+                })
+            }
+            touch::Event::FingerLifted { id, .. } | touch::Event::FingerLost { id, .. } => {
+                self.fingers.remove(&id);
+                self.start = None;
+                None
+            }
+        }
+    }
+
+    // only a two fingers gesture is a pinch
+    fn distance(&self) -> Option<f32> {
+        let mut fingers = self.fingers.values();
+        match (fingers.next(), fingers.next(), fingers.next()) {
+            (Some(a), Some(b), None) => Some(a.distance(*b)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinch_zoom() {
+        let mut pinch = Pinch::default();
+        let press = |id, x| touch::Event::FingerPressed {
+            id: Finger(id),
+            position: Point::new(x, 0.0),
+        };
+        let moved = |id, x| touch::Event::FingerMoved {
+            id: Finger(id),
+            position: Point::new(x, 0.0),
+        };
+        assert_eq!(pinch.update(press(1, 100.0), 1.0), None);
+        // a single finger does not zoom
+        assert_eq!(pinch.update(moved(1, 120.0), 1.0), None);
+        assert_eq!(pinch.update(press(2, 220.0), 1.0), None);
+        assert_eq!(pinch.update(moved(2, 320.0), 1.0), Some(2.0));
+        assert_eq!(pinch.update(moved(2, 1000.0), 2.0), Some(3.0));
+        pinch.update(
+            touch::Event::FingerLifted {
+                id: Finger(2),
+                position: Point::ORIGIN,
+            },
+            3.0,
+        );
+        assert_eq!(pinch.update(moved(1, 0.0), 3.0), None);
+    }
+}
@@ -0,0 +1,279 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Global hotkey toggling the window, grabbed on the X11 root window so
+// that it does not have to be bound in the window manager. Wayland
+// compositors do not allow grabs, bind `apekey` there instead.
+
+use eyre::{eyre, Result};
+use iced::{
+    futures::{channel::mpsc, SinkExt, StreamExt},
+    subscription, Subscription,
+};
+use std::{collections::HashSet, fmt};
+use tracing::{error, info, instrument, trace};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{ConnectionExt, GrabMode, ModMask},
+        ErrorKind, Event,
+    },
+    x11_utils::X11Error,
+};
+
+use crate::{app::Message, token::split_chord};
+
+// X modifier of each entry of `token::MODIFIERS`, `M-` being the usual
+// XMonad mod key, Super
+const MOD_MASKS: [ModMask; 8] = [
+    ModMask::M4,
+    ModMask::M4,
+    ModMask::M1,
+    ModMask::M2,
+    ModMask::M3,
+    ModMask::M5,
+    ModMask::CONTROL,
+    ModMask::SHIFT,
+];
+
+// keysyms of the special keys, see X11/keysymdef.h
+const KEYSYMS: [(&str, u32); 13] = [
+    ("<Space>", 0x0020),
+    ("<Return>", 0xff0d),
+    ("<Tab>", 0xff09),
+    ("<Esc>", 0xff1b),
+    ("<Escape>", 0xff1b),
+    ("<Backspace>", 0xff08),
+    ("<Delete>", 0xffff),
+    ("<Home>", 0xff50),
+    ("<End>", 0xff57),
+    ("<Insert>", 0xff63),
+    ("<Print>", 0xff61),
+    ("<Pause>", 0xff13),
+    ("<Menu>", 0xff67),
+];
+
+// modifiers of the keys probed whether they are grabbed, all their
+// combinations are probed
+const PROBED_MASKS: [ModMask; 4] = [ModMask::M4, ModMask::M1, ModMask::CONTROL, ModMask::SHIFT];
+
+/// Hotkey in the EZConfig syntax, e.g. `M-S-/` or `M-<F1>`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    modifiers: ModMask,
+    keysym: u32,
+}
+
+impl Hotkey {
+    pub fn parse(keys: &str) -> Result<Self> {
+        let (modifiers, key) = split_chord(keys.trim());
+        let keysym = keysym(key).ok_or_else(|| eyre!("unsupported hotkey key `{key}`"))?;
+        let modifiers = modifiers
+            .into_iter()
+            .fold(ModMask::from(0u16), |mask, i| mask | MOD_MASKS[i]);
+        Ok(Hotkey { modifiers, keysym })
+    }
+}
+
+// renders `M-` for Super, the usual XMonad mod key, as `MOD_MASKS` reads it
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mask = u16::from(self.modifiers);
+        for (prefix, modifier) in [
+            ("M-", ModMask::M4),
+            ("M1-", ModMask::M1),
+            ("M2-", ModMask::M2),
+            ("M3-", ModMask::M3),
+            ("M5-", ModMask::M5),
+            ("C-", ModMask::CONTROL),
+            ("S-", ModMask::SHIFT),
+        ] {
+            if mask & u16::from(modifier) != 0 {
+                write!(f, "{prefix}")?;
+            }
+        }
+        write!(f, "{}", key_name(self.keysym))
+    }
+}
+
+// printable ASCII chars are their own keysym, `<F1>` to `<F35>` follow
+// each other
+fn keysym(key: &str) -> Option<u32> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return (' '..='~').contains(&c).then_some(u32::from(c));
+    }
+    if let Some(n) = key
+        .strip_prefix("<F")
+        .and_then(|n| n.strip_suffix('>'))
+        .and_then(|n| n.parse::<u32>().ok())
+        .filter(|n| (1..=35).contains(n))
+    {
+        return Some(0xffbe + n - 1);
+    }
+    KEYSYMS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, keysym)| *keysym)
+}
+
+// EZConfig name of a keysym, the reverse of `keysym`, its hexadecimal
+// value when apekey does not know it, e.g. the XF86 media keys
+fn key_name(keysym: u32) -> String {
+    if let Some(c) = char::from_u32(keysym).filter(|c| ('!'..='~').contains(c)) {
+        return c.to_string();
+    }
+    if (0xffbe..0xffbe + 35).contains(&keysym) {
+        return format!("<F{}>", keysym - 0xffbe + 1);
+    }
+    KEYSYMS
+        .iter()
+        .find(|(_, k)| *k == keysym)
+        .map_or_else(|| format!("<{keysym:#x}>"), |(name, _)| (*name).to_owned())
+}
+
+/// Returns the keys grabbed on the root window by the other clients, e.g.
+/// the window manager, among the combinations of Super, Alt, Ctrl and
+/// Shift, and the given hotkeys. X11 does not list the grabs, each key is
+/// grabbed in turn, the ones already grabbed fail with `BadAccess`.
+#[instrument(skip_all)]
+pub fn grabbed_keys(hotkeys: &[Hotkey]) -> Result<HashSet<Hotkey>> {
+    let (conn, screen) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen].root;
+    let min = conn.setup().min_keycode;
+    let max = conn.setup().max_keycode;
+    let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+    let per_keycode = usize::from(mapping.keysyms_per_keycode);
+    let mut masks: HashSet<ModMask> = (0..1 << PROBED_MASKS.len())
+        .map(|bits: usize| {
+            PROBED_MASKS
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| bits & 1 << i != 0)
+                .fold(ModMask::from(0u16), |mask, (_, modifier)| mask | *modifier)
+        })
+        .collect();
+    masks.extend(hotkeys.iter().map(|hotkey| hotkey.modifiers));
+    let mut grabbed = HashSet::new();
+    // the requests are sent at once, their replies checked afterwards
+    let mut probes = vec![];
+    for (i, keysyms) in mapping.keysyms.chunks(per_keycode).enumerate() {
+        let keysym = keysyms[0];
+        if keysym == 0 {
+            continue;
+        }
+        let keycode = min + i as u8;
+        for modifiers in &masks {
+            let cookie = conn.grab_key(
+                false,
+                root,
+                *modifiers,
+                keycode,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?;
+            probes.push((cookie, keycode, *modifiers, keysym));
+        }
+    }
+    trace!("{} keys probed", probes.len());
+    for (cookie, keycode, modifiers, keysym) in probes {
+        match cookie.check() {
+            Ok(()) => {
+                conn.ungrab_key(keycode, root, modifiers)?;
+            }
+            Err(x11rb::errors::ReplyError::X11Error(X11Error {
+                error_kind: ErrorKind::Access,
+                ..
+            })) => {
+                grabbed.insert(Hotkey { modifiers, keysym });
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    conn.flush()?;
+    Ok(grabbed)
+}
+
+// grabs the hotkey and blocks, sending a message on each press
+#[instrument(skip(output))]
+fn grab(hotkey: &Hotkey, mut output: mpsc::Sender<Message>) -> Result<()> {
+    let (conn, screen) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen].root;
+    let min = conn.setup().min_keycode;
+    let max = conn.setup().max_keycode;
+    let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+    let per_keycode = usize::from(mapping.keysyms_per_keycode);
+    let keycode = mapping
+        .keysyms
+        .chunks(per_keycode)
+        .position(|keysyms| keysyms.contains(&hotkey.keysym))
+        .ok_or_else(|| eyre!("no key produces the hotkey keysym"))?;
+    let keycode = min + keycode as u8;
+    // the grab must not depend on Caps Lock and Num Lock
+    for locks in [
+        ModMask::from(0u16),
+        ModMask::LOCK,
+        ModMask::M2,
+        ModMask::LOCK | ModMask::M2,
+    ] {
+        conn.grab_key(
+            true,
+            root,
+            hotkey.modifiers | locks,
+            keycode,
+            GrabMode::ASYNC,
+            GrabMode::ASYNC,
+        )?
+        .check()?;
+    }
+    info!("hotkey grabbed, keycode {keycode}");
+    loop {
+        if let Event::KeyPress(event) = conn.wait_for_event()? {
+            trace!("hotkey pressed {:?}", event.detail);
+            iced::futures::executor::block_on(output.send(Message::ToggleWindow))?;
+        }
+    }
+}
+
+/// Toggles the window when the hotkey is pressed
+pub fn subscription(hotkey: Hotkey) -> Subscription<Message> {
+    struct GlobalHotkey;
+
+    subscription::channel(
+        std::any::TypeId::of::<GlobalHotkey>(),
+        10,
+        |mut output| async move {
+            let (sender, mut receiver) = mpsc::channel(10);
+            // the X connection blocks, it gets its own thread
+            std::thread::spawn(move || {
+                if let Err(e) = grab(&hotkey, sender) {
+                    error!("failed to grab the hotkey: {e}");
+                }
+            });
+            while let Some(message) = receiver.next().await {
+                output.send(message).await.ok();
+            }
+            iced::futures::future::pending().await
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkey_parsing() {
+        assert_eq!(
+            Hotkey::parse("M-S-/").unwrap(),
+            Hotkey {
+                modifiers: ModMask::M4 | ModMask::SHIFT,
+                keysym: u32::from('/')
+            }
+        );
+        assert_eq!(Hotkey::parse("M1-<F12>").unwrap().keysym, 0xffc9);
+        assert_eq!(Hotkey::parse("C-<Space>").unwrap().keysym, 0x20);
+        assert!(Hotkey::parse("M-<XF86AudioPlay>").is_err());
+    }
+}
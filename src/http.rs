@@ -0,0 +1,318 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Local HTTP endpoint of the running instance, opt-in with `http_port`,
+// for launchers and browser extensions: `GET /keymap` returns the keymap
+// as JSON, `GET /search?q=` the matching keybinds, best first. It is
+// bound to 127.0.0.1, never reachable from the network, and a request
+// whose `Host` is not 127.0.0.1 or localhost is refused so that a web page
+// cannot read it through DNS rebinding. No CORS header is sent, a browser
+// extension reads it with the host permission of `http://127.0.0.1/*`.
+
+use iced::{subscription, Subscription};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time,
+};
+use tracing::{debug, error, info, instrument};
+
+use crate::{
+    app::Message,
+    check::json_escape,
+    search::{filter_tokens, Matcher, SearchWeights},
+    token::{Keybind, ScoredKeybind, Tokens},
+};
+
+// a GET request has no body, its headers fit in a few lines
+const MAX_REQUEST: usize = 8 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The loaded keymap, updated by the app and read by the endpoint
+pub type SharedKeymap = Arc<RwLock<Tokens>>;
+
+/// Serves the keymap on `127.0.0.1:port`, bound again when the port
+/// changes
+pub fn subscription(
+    port: u16,
+    keymap: SharedKeymap,
+    matcher: Matcher,
+    search_actions: bool,
+    weights: SearchWeights,
+) -> Subscription<Message> {
+    struct Endpoint;
+
+    subscription::channel(
+        (std::any::TypeId::of::<Endpoint>(), port),
+        1,
+        move |_output| async move {
+            let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+            let listener = match TcpListener::bind(address).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    error!("failed to bind the HTTP endpoint {address}: {e}");
+                    return iced::futures::future::pending().await;
+                }
+            };
+            info!("serving the keymap on http://{address}");
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let keymap = keymap.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        handle(stream, port, keymap, matcher, search_actions, weights).await
+                    {
+                        debug!("HTTP request failed: {e}");
+                    }
+                });
+            }
+        },
+    )
+}
+
+#[instrument(skip_all)]
+async fn handle(
+    mut stream: TcpStream,
+    port: u16,
+    keymap: SharedKeymap,
+    matcher: Matcher,
+    search_actions: bool,
+    weights: SearchWeights,
+) -> std::io::Result<()> {
+    let mut request = vec![];
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST {
+        match time::timeout(READ_TIMEOUT, stream.read(&mut buffer)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(read)) => request.extend_from_slice(&buffer[..read]),
+            Ok(Err(e)) => return Err(e),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let request_line = request.lines().next().unwrap_or_default();
+    debug!("HTTP request {request_line:?}");
+    let (status, body) = if host_allowed(&request, port) {
+        let tokens = keymap.read().map(|k| k.clone()).unwrap_or_default();
+        respond(request_line, &tokens, matcher, search_actions, weights).await
+    } else {
+        debug!("HTTP request refused, unexpected host");
+        ("403 Forbidden", error_body("unexpected `Host`"))
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: application/json; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Whether the `Host` header of the request is the endpoint, by address
+/// or by `localhost`
+fn host_allowed(request: &str, port: u16) -> bool {
+    let host = request.lines().skip(1).find_map(|header| {
+        let (name, value) = header.split_once(':')?;
+        name.eq_ignore_ascii_case("host").then(|| value.trim())
+    });
+    host.is_some_and(|host| {
+        [format!("127.0.0.1:{port}"), format!("localhost:{port}")]
+            .iter()
+            .any(|allowed| host.eq_ignore_ascii_case(allowed))
+    })
+}
+
+/// Status line and JSON body answering a request line, e.g. `GET
+/// /search?q=term HTTP/1.1`
+async fn respond(
+    request_line: &str,
+    tokens: &Tokens,
+    matcher: Matcher,
+    search_actions: bool,
+    weights: SearchWeights,
+) -> (&'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next(), parts.next().unwrap_or_default());
+    if method != Some("GET") {
+        return (
+            "405 Method Not Allowed",
+            error_body("only GET is supported"),
+        );
+    }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    match path {
+        "/keymap" => ("200 OK", keymap_json(tokens)),
+        "/search" => {
+            let pattern = query
+                .split('&')
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| *name == "q")
+                .map(|(_, value)| percent_decode(value))
+                .unwrap_or_default();
+            if pattern.is_empty() {
+                return ("400 Bad Request", error_body("missing `q` parameter"));
+            }
+            let (matches, _) = filter_tokens(
+                tokens.keybinds(),
+                pattern.clone(),
+                matcher,
+                search_actions,
+                weights,
+            )
+            .await;
+            let matches: Vec<String> = matches.iter().map(scored_json).collect();
+            (
+                "200 OK",
+                format!(
+                    r#"{{"query":"{}","matches":[{}]}}"#,
+                    json_escape(&pattern),
+                    matches.join(",")
+                ),
+            )
+        }
+        _ => ("404 Not Found", error_body("one of /keymap, /search?q=")),
+    }
+}
+
+fn error_body(message: &str) -> String {
+    format!(r#"{{"error":"{}"}}"#, json_escape(message))
+}
+
+fn keymap_json(tokens: &Tokens) -> String {
+    let sections: Vec<String> = tokens
+        .sections
+        .iter()
+        .map(|section| {
+            let keybinds: Vec<String> = section.keybinds.iter().map(keybind_json).collect();
+            format!(
+                r#"{{"title":{},"description":{},"keybinds":[{}]}}"#,
+                json_string(section.title.as_deref()),
+                json_string(section.description.as_deref()),
+                keybinds.join(",")
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"title":{},"sections":[{}]}}"#,
+        json_string(tokens.title.as_deref()),
+        sections.join(",")
+    )
+}
+
+fn keybind_json(keybind: &Keybind) -> String {
+    scored_json(&keybind.into())
+}
+
+fn scored_json(keybind: &ScoredKeybind) -> String {
+    let layouts: Vec<String> = keybind
+        .layouts
+        .iter()
+        .map(|layout| json_string(Some(layout)))
+        .collect();
+    format!(
+        r#"{{"keys":"{}","description":"{}","kind":"{}","command":{},"layouts":[{}]}}"#,
+        json_escape(&keybind.keys),
+        json_escape(&keybind.description),
+        keybind.kind,
+        json_string(keybind.command.as_deref()),
+        layouts.join(",")
+    )
+}
+
+fn json_string(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("\"{}\"", json_escape(value)),
+        None => "null".to_owned(),
+    }
+}
+
+/// Decodes a query string value, `+` and `%20` are spaces
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Section;
+    use iced::futures::executor::block_on;
+
+    #[test]
+    fn endpoint_responses() {
+        let tokens = Tokens {
+            title: Some("Keymap".to_owned()),
+            sections: vec![Section {
+                title: Some("Apps".to_owned()),
+                description: None,
+                keybinds: vec![
+                    Keybind::new("M-S-<Return>", "Terminal", Some("spawn \"alacritty\"")),
+                    Keybind::new("M-\\", "Say \"hi\"", None),
+                ],
+            }],
+            ignored: vec![],
+            warnings: vec![],
+        };
+        let get = |line: &str| {
+            block_on(respond(
+                line,
+                &tokens,
+                Matcher::Fuzzy,
+                false,
+                SearchWeights::default(),
+            ))
+        };
+
+        let (status, body) = get("GET /keymap HTTP/1.1");
+        assert_eq!(status, "200 OK");
+        assert_eq!(
+            body,
+            r#"{"title":"Keymap","sections":[{"title":"Apps","description":null,"keybinds":[{"keys":"M-S-<Return>","description":"Terminal","kind":"spawn","command":"alacritty","layouts":[]},{"keys":"M-\\","description":"Say \"hi\"","kind":"other","command":null,"layouts":[]}]}]}"#
+        );
+
+        let (status, body) = get("GET /search?q=term HTTP/1.1");
+        assert_eq!(status, "200 OK");
+        assert!(body.starts_with(r#"{"query":"term","matches":[{"keys":"M-S-<Return>""#));
+        assert_eq!(get("GET /search?q= HTTP/1.1").0, "400 Bad Request");
+        assert_eq!(get("GET / HTTP/1.1").0, "404 Not Found");
+        assert_eq!(get("POST /keymap HTTP/1.1").0, "405 Method Not Allowed");
+
+        let request = |host: &str| format!("GET /keymap HTTP/1.1\r\n{host}\r\n\r\n");
+        assert!(host_allowed(&request("Host: 127.0.0.1:7777"), 7777));
+        assert!(host_allowed(&request("host: LocalHost:7777"), 7777));
+        assert!(!host_allowed(&request("Host: 127.0.0.1:7778"), 7777));
+        assert!(!host_allowed(&request("Host: evil.example:7777"), 7777));
+        assert!(!host_allowed(&request("Accept: */*"), 7777));
+
+        assert_eq!(percent_decode("say+%22hi%22%2"), "say \"hi\"%2");
+        assert_eq!(percent_decode("%C3%A9cran"), "écran");
+    }
+}
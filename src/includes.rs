@@ -0,0 +1,272 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Include graph of a config split in modules, the xmonad.hs entry
+// importing the files of the `xmonad` sources, e.g. `lib/Keys.hs`. The
+// annotations xmonad never reads are reported, the ones of a file no
+// import reaches from the entry and the keymaps held by a declaration
+// nothing refers to, stale annotations listing keybinds not bound.
+
+use std::{collections::HashSet, path::Path};
+use tokio::fs;
+use tracing::{debug, instrument, warn};
+
+use crate::{
+    parser::parse_boundary,
+    source::{SourceConfig, SourceType},
+};
+
+// words starting a top-level line which do not declare a value
+const KEYWORDS: [&str; 12] = [
+    "module", "import", "data", "type", "newtype", "class", "instance", "deriving", "infix",
+    "infixl", "infixr", "foreign",
+];
+// characters of the Haskell operators, `-->` is not a comment
+const SYMBOLS: &str = "!#$%&*+./<=>?@\\^|-~:";
+
+/// Warnings about the annotations of the entry config and of the
+/// `xmonad` sources which are never bound, none without such source
+#[instrument(skip_all)]
+pub async fn diagnostics(entry: &str, content: &str, sources: &[SourceConfig]) -> Vec<String> {
+    let mut files = vec![(entry.to_owned(), content.to_owned())];
+    for path in sources
+        .iter()
+        .filter(|source| source.kind == SourceType::Xmonad)
+        .filter_map(|source| source.path.clone())
+    {
+        // a file failing to be read is reported by its source
+        if let Ok(content) = fs::read_to_string(&path).await {
+            files.push((path, content));
+        }
+    }
+    if files.len() == 1 {
+        return vec![];
+    }
+    let files: Vec<(&str, &str)> = files
+        .iter()
+        .map(|(path, content)| (path.as_str(), content.as_str()))
+        .collect();
+    let warnings = check(&files);
+    debug!("{} include warnings", warnings.len());
+    for warning in &warnings {
+        warn!("{warning}");
+    }
+    warnings
+}
+
+// the first file is the entry
+fn check(files: &[(&str, &str)]) -> Vec<String> {
+    let reached = reachable(files);
+    let mut warnings = vec![];
+    for (index, (path, content)) in files.iter().enumerate() {
+        let Some(line) = keymap_line(content) else {
+            continue;
+        };
+        if !reached.contains(&index) {
+            warnings.push(format!(
+                "{path} is not imported by {}, its keybinds are never bound",
+                files[0].0
+            ));
+            continue;
+        }
+        let Some(name) = declaration(content, line) else {
+            continue;
+        };
+        if name != "main" && !reached.iter().any(|&i| refers_to(files[i].1, name)) {
+            warnings.push(format!(
+                "`{name}` holding the keymap of {path}:{line} is never used, \
+                 its keybinds are never bound"
+            ));
+        }
+    }
+    warnings
+}
+
+// indexes of the files imported from the entry, directly or not, by
+// module name, the file name for a file without `module` header
+fn reachable(files: &[(&str, &str)]) -> HashSet<usize> {
+    let names: Vec<String> = files
+        .iter()
+        .map(|(path, content)| match module_name(content) {
+            Some(name) => name.to_owned(),
+            None => Path::new(path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        })
+        .collect();
+    let mut reached = HashSet::from([0]);
+    let mut queue = vec![0];
+    while let Some(index) = queue.pop() {
+        for import in imports(files[index].1) {
+            for (other, name) in names.iter().enumerate() {
+                if name == import && reached.insert(other) {
+                    queue.push(other);
+                }
+            }
+        }
+    }
+    reached
+}
+
+fn module_name(content: &str) -> Option<&str> {
+    content.lines().find_map(|line| {
+        line.strip_prefix("module ")?
+            .split(|c: char| c.is_whitespace() || c == '(')
+            .find(|word| !word.is_empty())
+    })
+}
+
+// `import qualified Keys as K (myKeys)` imports `Keys`
+fn imports(content: &str) -> impl Iterator<Item = &str> {
+    content.lines().filter_map(|line| {
+        line.strip_prefix("import ")?
+            .split(|c: char| c.is_whitespace() || c == '(')
+            .find(|word| word.starts_with(char::is_uppercase))
+    })
+}
+
+// line of the `-- #` boundary opening the keymap
+fn keymap_line(content: &str) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| parse_boundary(&format!("{line}\n")).is_ok())
+        .map(|index| index + 1)
+}
+
+// name of the top-level declaration the keymap opened at `line` belongs
+// to, the one of its first line of code or the last one declared before
+fn declaration(content: &str, line: usize) -> Option<&str> {
+    let lines: Vec<&str> = content.lines().collect();
+    let is_code = |line: &str| {
+        let code = strip_comment(line).trim();
+        !code.is_empty() && !code.starts_with("{-")
+    };
+    let first = line + lines[line..].iter().position(|line| is_code(line))?;
+    lines[..=first]
+        .iter()
+        .rev()
+        .filter(|line| is_code(line))
+        .find(|line| !line.starts_with(char::is_whitespace))
+        .and_then(|line| leading_name(line))
+        .filter(|name| !KEYWORDS.contains(name))
+}
+
+// a value starts with a lowercase letter or `_`
+fn leading_name(line: &str) -> Option<&str> {
+    let end = line.find(|c: char| !is_name_char(c)).unwrap_or(line.len());
+    let name = &line[..end];
+    name.starts_with(|c: char| c.is_lowercase() || c == '_')
+        .then_some(name)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+// whether `name` is used in `content`, its own declaration, signature
+// and the export list of the module header aside
+fn refers_to(content: &str, name: &str) -> bool {
+    let mut header = false;
+    content.lines().any(|line| {
+        if line.starts_with("module ") {
+            header = true;
+        }
+        if header {
+            header = !strip_comment(line).split_whitespace().any(|w| w == "where");
+            return false;
+        }
+        if leading_name(line) == Some(name) {
+            return false;
+        }
+        let code = strip_comment(line);
+        code.match_indices(name).any(|(start, _)| {
+            let end = start + name.len();
+            !code[..start].ends_with(is_name_char) && !code[end..].starts_with(is_name_char)
+        })
+    })
+}
+
+// the line without its `--` comment
+fn strip_comment(line: &str) -> &str {
+    line.match_indices("--")
+        .find(|(start, _)| {
+            let dashes = line[*start..].trim_start_matches('-');
+            !line[..*start].ends_with(|c| SYMBOLS.contains(c))
+                && !dashes.starts_with(|c| SYMBOLS.contains(c))
+        })
+        .map_or(line, |(start, _)| &line[..start])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_graph_checking() {
+        let entry = r#"
+import XMonad
+import qualified Keys as K (myKeys)
+main = xmonad $ def `additionalKeysP` K.myKeys
+  where hook = className =? "mpv" --> doFloat -- not oldKeys
+"#;
+        let keys = r#"
+module Keys
+  ( myKeys
+  , oldKeys
+  ) where
+import Apps
+myKeys :: [(String, X ())]
+-- # Keys
+myKeys = appKeys ++
+  [ ("M-q", kill)
+  ]
+-- #
+"#;
+        let apps = r#"
+module Apps where
+appKeys =
+  -- # Apps
+  [ ("M-b", spawn "firefox")
+  ]
+  -- #
+oldKeys = []
+"#;
+        let stale = r#"
+module Stale where
+-- # Old
+oldKeys = [("M-o", spawn "old")]
+-- #
+"#;
+        let mut files = vec![
+            ("xmonad.hs", entry),
+            ("lib/Keys.hs", keys),
+            ("lib/Apps.hs", apps),
+            ("lib/Stale.hs", stale),
+        ];
+        assert_eq!(
+            check(&files),
+            vec!["lib/Stale.hs is not imported by xmonad.hs, its keybinds are never bound"]
+        );
+
+        files[2].1 = r#"
+module Apps where
+appKeys = []
+oldKeys =
+  -- # Apps
+  [ ("M-b", spawn "firefox")
+  ]
+  -- #
+"#;
+        files.pop();
+        assert_eq!(
+            check(&files),
+            vec![
+                "`oldKeys` holding the keymap of lib/Apps.hs:5 is never used, \
+                 its keybinds are never bound"
+            ]
+        );
+        assert_eq!(strip_comment("a --> b -- c"), "a --> b ");
+    }
+}
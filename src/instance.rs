@@ -0,0 +1,66 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use iced::{futures::SinkExt, subscription, Subscription};
+use std::{env, fs, io::Write, os::unix::net::UnixStream, path::PathBuf};
+use tokio::{io::AsyncReadExt, net::UnixListener};
+use tracing::{debug, error, info, instrument};
+
+use crate::app::Message;
+
+const TOGGLE_REQUEST: &[u8] = b"toggle";
+
+/// Path of the unix socket the running instance listens on
+pub fn socket_path() -> PathBuf {
+    let dir = env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| env::temp_dir().display().to_string());
+    let user = env::var("USER").unwrap_or_default();
+    PathBuf::from(dir).join(format!("apekey-{user}.sock"))
+}
+
+/// Asks an already running instance, if any, to toggle its window.
+/// Returns `true` when an instance answered.
+#[instrument]
+pub fn notify_running() -> bool {
+    let path = socket_path();
+    match UnixStream::connect(&path) {
+        Ok(mut stream) => {
+            debug!("found a running instance at {}", path.display());
+            stream.write_all(TOGGLE_REQUEST).is_ok()
+        }
+        Err(_) => false,
+    }
+}
+
+/// Listens for requests sent by other apekey launches
+pub fn subscription() -> Subscription<Message> {
+    struct Instance;
+
+    subscription::channel(
+        std::any::TypeId::of::<Instance>(),
+        10,
+        |mut output| async move {
+            let path = socket_path();
+            // a socket file left by a previous instance that did not
+            // exit cleanly would prevent binding
+            fs::remove_file(&path).ok();
+            let listener = match UnixListener::bind(&path) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    error!("failed to bind socket {}: {e}", path.display());
+                    return iced::futures::future::pending().await;
+                }
+            };
+            info!("listening on {}", path.display());
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let mut request = vec![];
+                if stream.read_to_end(&mut request).await.is_ok() && request == TOGGLE_REQUEST {
+                    output.send(Message::ToggleWindow).await.ok();
+                }
+            }
+        },
+    )
+}
@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use eyre::Result;
+use std::collections::HashMap;
+use tracing::{debug, instrument};
+use x11rb::protocol::{
+    xkb::{self, ConnectionExt},
+    xproto::Keysym,
+};
+
+use crate::token::split_chord;
+
+// keysyms of the Latin-2 chars found on the first level of the digit keys
+// of the usual layouts, e.g. Czech, the Latin-1 keysyms are their code
+// point
+const LATIN2_KEYSYMS: [(Keysym, char); 5] = [
+    (0x1b9, 'š'),
+    (0x1be, 'ž'),
+    (0x1e8, 'č'),
+    (0x1ec, 'ě'),
+    (0x1f8, 'ř'),
+];
+// Unicode keysyms are their code point plus this offset
+const UNICODE_OFFSET: Keysym = 0x100_0000;
+
+/// Keys of the current XKB layout whose digit is not on the first level,
+/// e.g. `2` is on the `é` key with AZERTY
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout(HashMap<char, char>);
+
+impl Layout {
+    /// Replaces the digits of the keys by the char printed on their key,
+    /// e.g. `M-2` becomes `M-é` with AZERTY. EZConfig keys name keysyms,
+    /// letters and symbols are the ones printed on the keyboard already.
+    pub fn translate(&self, keys: &str) -> String {
+        keys.split(' ')
+            .map(|chord| {
+                let (_, key) = split_chord(chord);
+                let mut chars = key.chars();
+                match (chars.next().and_then(|c| self.0.get(&c)), chars.next()) {
+                    (Some(printed), None) => {
+                        format!("{}{printed}", &chord[..chord.len() - key.len()])
+                    }
+                    _ => chord.to_owned(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Reads the current layout through the XKB extension
+#[instrument]
+pub fn current() -> Option<Layout> {
+    let layout = key_syms()
+        .map_err(|e| debug!("no XKB keymap: {e}"))
+        .ok()
+        .map(|keys| from_key_syms(&keys))?;
+    debug!("layout {:?}", layout);
+    Some(layout)
+}
+
+// the keysyms of the levels of the first group of each key
+fn key_syms() -> Result<Vec<Vec<Keysym>>> {
+    let (conn, _) = x11rb::connect(None)?;
+    conn.xkb_use_extension(1, 0)?.reply()?;
+    let reply = conn
+        .xkb_get_map(
+            xkb::ID::USE_CORE_KBD.into(),
+            xkb::MapPart::KEY_SYMS,
+            0u16.into(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0u16.into(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        )?
+        .reply()?;
+    Ok(reply
+        .map
+        .syms_rtrn
+        .unwrap_or_default()
+        .into_iter()
+        .map(|key| key.syms.into_iter().take(key.width.into()).collect())
+        .collect())
+}
+
+fn from_key_syms(keys: &[Vec<Keysym>]) -> Layout {
+    Layout(
+        keys.iter()
+            .filter_map(|levels| {
+                let first = keysym_char(*levels.first()?)?;
+                let digit = levels[1..]
+                    .iter()
+                    .filter_map(|&keysym| keysym_char(keysym))
+                    .find(char::is_ascii_digit)?;
+                (!first.is_ascii_digit()).then_some((digit, first))
+            })
+            .collect(),
+    )
+}
+
+fn keysym_char(keysym: Keysym) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        0x100_0100..=0x110_ffff => char::from_u32(keysym - UNICODE_OFFSET),
+        _ => LATIN2_KEYSYMS
+            .iter()
+            .find(|(k, _)| *k == keysym)
+            .map(|(_, c)| *c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the first group of the digit keys of AZERTY, and of a letter
+    fn azerty() -> Vec<Vec<Keysym>> {
+        vec![
+            // ampersand, 1, onesuperior, exclamdown
+            vec![0x26, 0x31, 0xb9, 0xa1],
+            // eacute, 2, asciitilde, oneeighth
+            vec![0xe9, 0x32, 0x7e, 0xac3],
+            vec![0x28, 0x35, 0x5b, 0xac4],
+            vec![0xe8, 0x37, 0x60, 0xac5],
+            // q, Q, at, Greek_OMEGA
+            vec![0x71, 0x51, 0x40, 0x7d9],
+            // no keysym
+            vec![],
+        ]
+    }
+
+    #[test]
+    fn key_syms_layout() {
+        assert_eq!(
+            from_key_syms(&azerty()),
+            Layout(HashMap::from([
+                ('1', '&'),
+                ('2', 'é'),
+                ('5', '('),
+                ('7', 'è')
+            ]))
+        );
+        // digits are on the first level with QWERTY
+        assert_eq!(from_key_syms(&[vec![0x32, 0x40]]), Layout::default());
+        // Latin-2 and Unicode keysyms, no char for a function key
+        assert_eq!(keysym_char(0x1be), Some('ž'));
+        assert_eq!(keysym_char(0x100_0153), Some('œ'));
+        assert_eq!(keysym_char(0xff0d), None);
+    }
+
+    #[test]
+    fn keys_translation() {
+        let layout = from_key_syms(&azerty());
+        assert_eq!(layout.translate("M-2"), "M-é");
+        assert_eq!(layout.translate("M-S-7 M-1"), "M-S-è M-&");
+        assert_eq!(layout.translate("M-3"), "M-3");
+        assert_eq!(layout.translate("M-<Return>"), "M-<Return>");
+        assert_eq!(layout.translate("M-[1..9]"), "M-[1..9]");
+        assert_eq!(Layout::default().translate("M-2"), "M-2");
+    }
+}
@@ -2,20 +2,56 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+mod annotate;
 mod app;
+mod cache;
+mod check;
+mod compare;
+mod compose;
+mod display;
+mod dump;
+mod exit;
+mod export;
+mod gesture;
+mod hotkey;
+mod http;
+mod includes;
+mod instance;
+mod layout;
+mod markup;
 mod parser;
+mod probe;
+mod screen;
+mod search;
+mod source;
+mod template;
+mod terminal;
+mod theme;
 mod token;
+mod tray;
+mod ui;
+mod usage;
 mod user_config;
+mod view_model;
+mod watch;
+mod wm;
 
 use crate::{
-    app::{Apekey, AppConfig, FONT_MONO},
-    user_config::{UserConfig, FONT_SIZE},
+    app::{Apekey, Overrides, Pick, Profile, FONT_MONO, STDIN_PATH},
+    check::CheckFormat,
+    display::{Desktop, Renderer},
+    exit::Exit,
+    export::{ExportFormat, ExportOptions},
+    search::filter_tokens,
+    template::RowTemplate,
+    token::ActionKind,
+    user_config::{ThemeOverride, UserConfig},
 };
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use dotenv::dotenv;
-use iced::{Application, Settings};
-use std::env;
-use tracing::{info, trace, warn, Level};
+use iced::{window, Application, Settings};
+use std::{env, path::PathBuf};
+use tracing::{error, info, trace, warn, Level};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 /// apekey, lists your XMonad keymap
@@ -26,13 +62,171 @@ struct Cli {
     #[clap(value_parser)]
     path: Option<String>,
 
+    /// Path of the apekey.toml config, defaults to `$APEKEY_CONFIG` or
+    /// `apekey/apekey.toml` in the config directory, e.g.
+    /// `$XDG_CONFIG_HOME`
+    #[clap(short, long, value_name = "PATH")]
+    config: Option<String>,
+
     /// Log level, one of trace, debug, info, warn, error
     #[clap(short, long)]
     log: Option<tracing::Level>,
 
+    /// No logs, for scripts branching on the exit code
+    #[clap(long, conflicts_with = "log")]
+    quiet: bool,
+
     /// Font size
     #[clap(short, long)]
     font_size: Option<f32>,
+
+    /// Start with the search field filled with this query
+    #[clap(short, long)]
+    query: Option<String>,
+
+    /// What to list, the XCompose profile reads `$XCOMPOSEFILE` or
+    /// `~/.XCompose` and the dump profile reads the standard input unless
+    /// a path is given. Defaults to the `profile` of the `[wm.<name>]`
+    /// table of the running window manager, if any, or to xmonad
+    #[clap(short, long, value_enum)]
+    profile: Option<Profile>,
+
+    /// Start in presentation mode, one section at a time with large fonts
+    #[clap(long)]
+    present: bool,
+
+    /// Pick a keybind, with `Enter` or a click, print it and exit, with 1
+    /// if the window is closed without picking one and with 4 if `--query`
+    /// matches nothing
+    #[clap(long)]
+    pick: bool,
+
+    /// Line printed by `--pick`, placeholders are `{keys}`,
+    /// `{description}`, `{usage}` and `{action}`
+    #[clap(
+        long,
+        value_name = "TEMPLATE",
+        requires = "pick",
+        default_value = "{keys}\t{description}",
+        value_parser = pick_template
+    )]
+    pick_format: RowTemplate,
+
+    /// Only a filter line over a dense list of the keybinds, without
+    /// title, section headers nor search controls
+    #[clap(long)]
+    minimal: bool,
+
+    /// High contrast theme, larger fonts and labeled buttons
+    #[clap(long)]
+    accessible: bool,
+
+    /// Show the next section every N seconds, implies --present
+    #[clap(long, value_name = "SECONDS")]
+    present_interval: Option<u64>,
+
+    /// Built-in theme, e.g. dark, light or solarized-light, or
+    /// auto-time, instead of the one of the config and its colors
+    #[clap(long, value_name = "NAME", value_parser = theme_name)]
+    theme: Option<String>,
+
+    /// Accent color, as `#rrggbb`, instead of the primary color of the
+    /// config
+    #[clap(long, value_name = "HEX", value_parser = hex_color)]
+    accent: Option<String>,
+
+    /// Render with the GPU or the CPU, the software renderer works
+    /// without GPU driver. Defaults to the `renderer` of the config
+    #[clap(long, value_enum)]
+    renderer: Option<Renderer>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the keymap for a status bar module or as a document
+    Export {
+        #[clap(value_enum)]
+        format: ExportFormat,
+
+        /// Colorize the output using the bar markup
+        #[clap(short, long)]
+        markup: bool,
+
+        /// Show a different keybind every N seconds instead of a summary
+        #[clap(short, long, value_name = "SECONDS")]
+        cycle: Option<u64>,
+
+        /// Only export the keybinds triggering this kind of action
+        #[clap(short, long, value_enum)]
+        kind: Option<ActionKind>,
+
+        /// Export every profile, the default one and the one of each
+        /// `[wm.<name>]` table, to a file each, named after it
+        #[clap(short, long)]
+        all: bool,
+
+        /// Directory of the files written by `--all`
+        #[clap(long, value_name = "DIR", requires = "all", default_value = ".")]
+        out_dir: PathBuf,
+    },
+    /// Check the keymap annotations, exits with 1 on errors and with 2
+    /// when the config could not be read
+    Check {
+        /// Output format of the findings
+        #[clap(short, long, value_enum, default_value_t)]
+        format: CheckFormat,
+    },
+    /// Add a description comment above the keybinds without one, asking
+    /// for each of them, and the `-- #` boundaries if missing
+    Annotate {
+        /// Path of the xmonad.hs, defaults to the configured one
+        path: Option<String>,
+
+        /// Write the suggested descriptions without asking
+        #[clap(short, long)]
+        write: bool,
+    },
+    /// Render two keymaps side by side, the shared keys aligned
+    Compare {
+        /// Path of my xmonad.hs
+        mine: String,
+        /// Path of the xmonad.hs to compare it with
+        theirs: String,
+    },
+    /// Compare the keys grabbed on the X11 root window, e.g. by the window
+    /// manager, with the keymap, `M-` standing for Super
+    Probe,
+    /// Render a fixture file and reload it on change, alongside a running
+    /// apekey, to iterate on the parser and the UI
+    Preview {
+        /// Path of the fixture, e.g. fixtures/basic.hs
+        fixture: String,
+    },
+}
+
+fn theme_name(name: &str) -> Result<String, String> {
+    if theme::builtin(name).is_some() || name == theme::AUTO_TIME {
+        Ok(name.to_owned())
+    } else {
+        Err(format!(
+            "one of {}, {}",
+            theme::THEMES.join(", "),
+            theme::AUTO_TIME
+        ))
+    }
+}
+
+fn hex_color(hex: &str) -> Result<String, String> {
+    theme::parse_hex_color(hex)
+        .map(|_| hex.to_owned())
+        .ok_or_else(|| "expected a #rrggbb color".to_owned())
+}
+
+fn pick_template(template: &str) -> Result<RowTemplate, String> {
+    RowTemplate::parse_line(template).map_err(|e| e.to_string())
 }
 
 fn main() -> iced::Result {
@@ -40,15 +234,18 @@ fn main() -> iced::Result {
     let cli = Cli::parse();
 
     // Tracing init
+    let filter = if cli.quiet {
+        "off".to_owned()
+    } else {
+        env::var("RUST_LOG")
+            .unwrap_or_else(|_| format!("apekey={}", cli.log.unwrap_or(Level::INFO)))
+    };
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            env::var("RUST_LOG")
-                .unwrap_or_else(|_| format!("apekey={}", cli.log.unwrap_or(Level::INFO))),
-        ))
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::EnvFilter::new(filter))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
-    let mut user_config = UserConfig::try_read().unwrap_or_else(|e| {
+    let user_config = UserConfig::try_read(cli.config).unwrap_or_else(|e| {
         warn!("Failed to read user config: {}", e);
         warn!("Fallback to default config");
         UserConfig::default()
@@ -56,21 +253,180 @@ fn main() -> iced::Result {
     trace!("User config: {:#?}", &user_config);
 
     // Override xmonad.hs path if provided as CLI argument
-    if let Some(p) = cli.path {
-        user_config.xmonad_config = p;
+    let profile_path = match cli.profile {
+        Some(Profile::Xcompose) => Some(compose::default_path()),
+        Some(Profile::Dump) => Some(STDIN_PATH.to_owned()),
+        Some(Profile::Xmonad) | None => None,
+    };
+    let mut overrides = Overrides {
+        profile: cli.profile,
+        config_path: cli.path.or(profile_path),
+        query: cli.query,
+        present: cli.present || cli.present_interval.is_some(),
+        present_interval: cli.present_interval,
+        minimal: cli.minimal,
+        pick: cli.pick.then(|| Pick::new(cli.pick_format)),
+        accessible: cli.accessible,
+        theme: ThemeOverride {
+            theme: cli.theme,
+            accent: cli.accent,
+        },
+        ..Default::default()
+    };
+    match &cli.command {
+        Some(Command::Compare { mine, theirs }) => {
+            overrides.config_path = Some(mine.clone());
+            overrides.compare = Some(theirs.clone());
+        }
+        Some(Command::Preview { fixture }) => {
+            overrides.config_path = Some(fixture.clone());
+            overrides.preview = true;
+        }
+        _ => {}
     }
-    info!("Path to XMonad config file: {}", &user_config.xmonad_config);
+    let profiles = matches!(cli.command, Some(Command::Export { all: true, .. })).then(|| {
+        let mut user_config = user_config.clone();
+        overrides.apply(&mut user_config);
+        user_config
+    });
+    // the monitor is only looked up for the window
+    let desktop = match cli.command {
+        None | Some(Command::Compare { .. }) | Some(Command::Preview { .. }) => Desktop::detect(),
+        Some(_) => Desktop::default(),
+    };
+    // the window manager only picks the keymap when none is given
+    let wm = match (&overrides.config_path, overrides.profile) {
+        (None, None) => wm::detect(),
+        _ => None,
+    };
+    let desktop = Desktop { wm, ..desktop };
+    let mut config = overrides.config(user_config, desktop);
+    info!("Path to the config file: {}", &config.config_path);
 
+    match cli.command {
+        Some(Command::Export {
+            format,
+            markup,
+            cycle,
+            kind,
+            out_dir,
+            ..
+        }) => {
+            let options = ExportOptions {
+                markup,
+                cycle,
+                kind,
+            };
+            let output = match profiles {
+                Some(user_config) => export::run_all(&user_config, format, &options, &out_dir),
+                None => export::run(&config, format, &options),
+            };
+            match output {
+                Ok(output) => println!("{output}"),
+                Err(e) => {
+                    error!("{e}");
+                    Exit::of(&e).exit();
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Check { format }) => {
+            match check::run(&config, format) {
+                Ok(report) => {
+                    if !report.output.is_empty() {
+                        println!("{}", report.output);
+                    }
+                    if report.has_errors {
+                        Exit::Failure.exit();
+                    }
+                }
+                Err(e) => {
+                    error!("{e}");
+                    Exit::of(&e).exit();
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Annotate { path, write }) => {
+            let path = path.unwrap_or_else(|| config.config_path.clone());
+            match annotate::run(&path, write) {
+                Ok(report) => println!("{report}"),
+                Err(e) => {
+                    error!("{e}");
+                    Exit::of(&e).exit();
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Probe) => {
+            match probe::run(&config) {
+                Ok(output) => println!("{output}"),
+                Err(e) => {
+                    error!("{e}");
+                    Exit::of(&e).exit();
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Compare { .. }) | Some(Command::Preview { .. }) | None => {}
+    }
+
+    // a second launch toggles the window of the running instance
+    if !config.preview && config.compare.is_none() && !cli.pick && instance::notify_running() {
+        info!("apekey is already running");
+        return Ok(());
+    }
+
+    // a script picking a keybind branches on the exit code, the window is
+    // not opened when the keymap fails to load or nothing matches
+    if config.pick.is_some() {
+        let tokens = app::load_keymap(&config).unwrap_or_else(|e| {
+            error!("{e}");
+            Exit::of(&e).exit()
+        });
+        if let Some(query) = config.query.as_ref().filter(|q| !q.is_empty()) {
+            let (matches, _) = iced::futures::executor::block_on(filter_tokens(
+                tokens.keybinds(),
+                query.clone(),
+                config.search_matcher,
+                config.search_actions,
+                config.search_weights,
+            ));
+            if matches.is_empty() {
+                error!("no keybind matches {query}");
+                Exit::NoMatch.exit();
+            }
+        }
+        config.keymap = Some(tokens);
+    }
+    display::select_renderer(cli.renderer.unwrap_or(config.renderer));
+    let transparent = config.ui.opacity < 1.0;
     let mut settings = Settings {
         id: Some("apekey".into()),
-        default_text_size: FONT_SIZE as f32,
+        window: window::Settings {
+            transparent,
+            ..Default::default()
+        },
+        default_text_size: f32::from(config.ui.text_size),
         default_font: FONT_MONO,
-        ..Settings::with_flags(AppConfig::from(user_config))
+        ..Settings::with_flags(config)
     };
     if let Some(size) = cli.font_size {
         settings.default_text_size = size;
     }
 
     info!("Starting apekey");
-    Apekey::run(settings)
+    let pick = settings.flags.pick.clone();
+    Apekey::run(settings).map_err(|e| {
+        error!("{e}, `--renderer software` renders without GPU");
+        e
+    })?;
+    // the window closes once a keybind is picked, see `Message::Picked`
+    if let Some(pick) = pick {
+        match pick.line() {
+            Some(line) => println!("{line}"),
+            None => Exit::Failure.exit(),
+        }
+    }
+    Ok(())
 }
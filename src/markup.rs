@@ -0,0 +1,98 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Minimal inline markup supported in keybind descriptions:
+// `*bold*` and `` `code` `` for literal keys or commands
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Span<'a> {
+    Text(&'a str),
+    Bold(&'a str),
+    Code(&'a str),
+}
+
+impl<'a> Span<'a> {
+    pub fn content(&self) -> &'a str {
+        match self {
+            Span::Text(t) | Span::Bold(t) | Span::Code(t) => t,
+        }
+    }
+}
+
+/// Splits a description into styled spans. A marker without its closing
+/// counterpart, or enclosing nothing, is kept as plain text.
+pub fn parse(input: &str) -> Vec<Span<'_>> {
+    let mut spans = vec![];
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < input.len() {
+        let marker = input.as_bytes()[i];
+        if marker == b'*' || marker == b'`' {
+            let closing = input[i + 1..].find(marker as char).map(|j| i + 1 + j);
+            if let Some(end) = closing.filter(|end| *end > i + 1) {
+                if text_start < i {
+                    spans.push(Span::Text(&input[text_start..i]));
+                }
+                let content = &input[i + 1..end];
+                spans.push(if marker == b'*' {
+                    Span::Bold(content)
+                } else {
+                    Span::Code(content)
+                });
+                i = end + 1;
+                text_start = i;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    if text_start < input.len() {
+        spans.push(Span::Text(&input[text_start..]));
+    }
+    spans
+}
+
+/// Returns the description without its markup
+pub fn strip(input: &str) -> String {
+    parse(input).iter().map(Span::content).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_parsing() {
+        assert_eq!(parse("plain text"), vec![Span::Text("plain text")]);
+        assert_eq!(
+            parse("Open *firefox* with `M-f`"),
+            vec![
+                Span::Text("Open "),
+                Span::Bold("firefox"),
+                Span::Text(" with "),
+                Span::Code("M-f"),
+            ]
+        );
+        assert_eq!(parse("*all bold*"), vec![Span::Bold("all bold")]);
+        assert_eq!(parse("a * b"), vec![Span::Text("a * b")]);
+        assert_eq!(
+            parse("empty ** marker"),
+            vec![Span::Text("empty ** marker")]
+        );
+        assert_eq!(
+            parse("`a*b` *c`d*"),
+            vec![Span::Code("a*b"), Span::Text(" "), Span::Bold("c`d")]
+        );
+        assert_eq!(
+            parse("écran *é*"),
+            vec![Span::Text("écran "), Span::Bold("é")]
+        );
+    }
+
+    #[test]
+    fn markup_stripping() {
+        assert_eq!(strip("Open *firefox* with `M-f`"), "Open firefox with M-f");
+        assert_eq!(strip("a * b"), "a * b");
+    }
+}
@@ -2,47 +2,464 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use eyre::{eyre, Result};
+use eyre::Result;
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_until},
-    character::complete::{multispace0, newline, not_line_ending, space0},
-    combinator::{eof, map, not, opt, peek},
+    character::complete::{line_ending, multispace0, not_line_ending, one_of, space0},
+    combinator::{eof, map, not, opt, peek, recognize, verify},
     error::ParseError,
-    multi::many_till,
-    sequence::{delimited, preceded, terminated, tuple},
+    multi::{many0, many_till},
+    sequence::{delimited, pair, preceded, terminated, tuple},
     Finish, IResult,
 };
-use tracing::{info, instrument, trace};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{self, BufRead},
+};
+use tracing::{info, instrument, trace, warn};
 
-use crate::token::Tokens;
+use crate::exit::{Exit, Failure};
+use crate::token::{Keybind, Tokens};
 
 const BOUNDARY_TOKEN: &str = "#";
 const SECTION_TOKEN: &str = "##";
 const HS_COMMENT_SEQ: &str = "--";
+// a block comment on a single line, e.g. `{- ## Apps -}`
+const HS_BLOCK_COMMENT_START: &str = "{-";
+const HS_BLOCK_COMMENT_END: &str = "-}";
 const IGNORE_TOKEN: &str = "!";
+const SECTION_DESCRIPTION_TOKEN: &str = ":";
+// `-- ##layout Tall, Full` marks the keybinds that follow in the section
+// as active in these layouts only
+const LAYOUT_TOKEN: &str = "layout";
+// names of the ASCII control codes escaped in strings, e.g. `\ESC`, by code
+const ASCII_CONTROLS: [&str; 32] = [
+    "NUL", "SOH", "STX", "ETX", "EOT", "ENQ", "ACK", "BEL", "BS", "HT", "LF", "VT", "FF", "CR",
+    "SO", "SI", "DLE", "DC1", "DC2", "DC3", "DC4", "NAK", "SYN", "ETB", "CAN", "EM", "SUB", "ESC",
+    "FS", "GS", "RS", "US",
+];
+// lines of the file shown when it does not look like an annotated config
+const FORMAT_HINT_LINES: usize = 5;
+
+/// Failure to parse the keymap, located at the line the parser stopped
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub line: usize,
+    message: String,
+}
+
+impl fmt::Display for ParseFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fail to parse xmonad config at line {}: {}",
+            self.line, self.message
+        )
+    }
+}
 
+impl std::error::Error for ParseFailure {}
+
+/// A keybind: keys, description and, when declared inline, the Haskell action
 #[derive(Debug, Clone, Default, PartialEq)]
-pub struct KeybindToken<'input>(pub &'input str, pub &'input str);
+pub struct KeybindToken<'input>(
+    pub Cow<'input, str>,
+    pub &'input str,
+    pub Option<&'input str>,
+);
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Section<'input> {
     pub title: Option<&'input str>,
+    pub description: Option<&'input str>,
     pub keybinds: Vec<KeybindToken<'input>>,
+    // keybinds annotated with `-- !`, not rendered by default
+    pub ignored: Vec<KeybindToken<'input>>,
+}
+
+/// How haddock comments (`-- |`, `-- ^`) preceding a keybind are handled
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Haddock {
+    /// Strip the marker and use the rest of the comment as description
+    #[default]
+    Strip,
+    /// Haddock comments are code documentation, never keybind descriptions
+    Skip,
 }
 
+/// Parser of the keymap of a config. The config is read a line at a time
+/// and only the lines from the `-- #` boundary opening the keymap to the
+/// one closing it are kept, the rest of a generated config may be huge.
+/// Their count gives the line numbers.
 #[derive(Debug, Default)]
-pub struct Parser(pub String);
+pub struct Parser {
+    // the keymap lines
+    content: String,
+    // line of the file the keymap starts at, none without boundary
+    first_line: Option<usize>,
+    closed: bool,
+    // first lines of the file, to tell what it is when it has no keymap
+    head: Vec<String>,
+    binary: bool,
+    haddock: Haddock,
+    // the keybinds without description are kept, described by their action
+    fallback_description: bool,
+}
 
 impl Parser {
+    /// Reads the keymap of a config, the lines after it are not read
+    pub fn read(
+        mut reader: impl BufRead,
+        haddock: Haddock,
+        fallback_description: bool,
+    ) -> io::Result<Self> {
+        let mut parser = Parser {
+            haddock,
+            fallback_description,
+            ..Parser::default()
+        };
+        let mut bytes = vec![];
+        for number in 1.. {
+            bytes.clear();
+            if reader.read_until(b'\n', &mut bytes)? == 0 {
+                break;
+            }
+            if bytes.contains(&0) {
+                parser.binary = true;
+                break;
+            }
+            let line = std::str::from_utf8(&bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let line_end = format!("{}\n", line.trim_end_matches(['\n', '\r']));
+            let boundary = parse_boundary(&line_end).is_ok();
+            match parser.first_line {
+                None if boundary => {
+                    parser.first_line = Some(number);
+                    parser.content.push_str(line);
+                }
+                None => {
+                    if !line.trim().is_empty() && parser.head.len() < FORMAT_HINT_LINES {
+                        parser.head.push(line_end.trim_end_matches('\n').to_owned());
+                    }
+                }
+                Some(_) => {
+                    parser.content.push_str(line);
+                    if boundary {
+                        parser.closed = true;
+                        break;
+                    }
+                }
+            }
+        }
+        trace!(
+            "keymap of {} bytes from line {:?}",
+            parser.content.len(),
+            parser.first_line
+        );
+        Ok(parser)
+    }
+
+    /// Reads the keymap of a config already in memory, e.g. by the GUI
+    pub fn new(content: &str, haddock: Haddock, fallback_description: bool) -> Self {
+        Parser::read(content.as_bytes(), haddock, fallback_description)
+            .expect("the lines of a string are valid UTF-8")
+    }
+
+    /// The keymap of the config, parsed in the GUI tasks and outside of
+    /// the GUI (e.g. exports)
     #[instrument(skip_all)]
-    pub async fn parse(&self) -> Result<Tokens> {
+    pub fn tokens(&self) -> Result<Tokens> {
         info!("start parsing xmonad configuration");
-        parse_entry(&self.0)
+        self.check_format()?;
+        let (title, sections) = self.sections().map_err(|e| ParseFailure {
+            line: self.position(e.input).0,
+            message: e.to_string(),
+        })?;
+        let sources: Vec<Vec<(Option<&str>, usize)>> = sections
+            .iter()
+            .map(|s| {
+                s.keybinds
+                    .iter()
+                    .map(|k| (self.source_lines(k), self.keybind_position(k).0))
+                    .collect()
+            })
+            .collect();
+        let layout_marks = self.layout_marks();
+        let layouts: Vec<Vec<Vec<String>>> = sections
+            .iter()
+            .map(|s| {
+                s.keybinds
+                    .iter()
+                    .map(|k| self.layouts(k, &layout_marks))
+                    .collect()
+            })
+            .collect();
+        let mut tokens = Tokens::from((title, sections));
+        if let Some(line) = self.unclosed_keymap() {
+            let warning = format!(
+                "the keymap opened at line {line} has no closing `-- #` boundary, \
+                 parsed up to the end of the file"
+            );
+            warn!("{warning}");
+            tokens.warnings.push(warning);
+        }
+        for ((section, sources), layouts) in tokens.sections.iter_mut().zip(sources).zip(layouts) {
+            for ((keybind, (source, line)), layouts) in
+                section.keybinds.iter_mut().zip(sources).zip(layouts)
+            {
+                keybind.source = source.map(str::to_owned);
+                keybind.line = Some(line);
+                keybind.layouts = layouts;
+            }
+            section.keybinds = section
+                .keybinds
+                .drain(..)
+                .flat_map(|keybind| with_submap(keybind, self.haddock, self.fallback_description))
+                .collect();
+            for keybind in &mut section.keybinds {
+                if let (true, Some(action)) = (keybind.description.is_empty(), &keybind.action) {
+                    keybind.description = action_description(action);
+                }
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Parses the config into sections borrowing from it
+    pub fn sections(&self) -> Result<(Option<&str>, Vec<Section<'_>>), nom::error::Error<&str>> {
+        parse_entry(&self.content)
             .finish()
-            .map(|(_, (title, sections))| Tokens::from((title, sections)))
-            .map_err(|e| eyre!("fail to parse xmonad config: {e}"))
+            .map(|(_, (title, sections))| {
+                let sections = sections
+                    .into_iter()
+                    .map(|s| handle_haddock(s, self.haddock, self.fallback_description))
+                    .collect();
+                (title, sections)
+            })
+    }
+
+    /// Returns the line of the `-- #` boundary opening the keymap when
+    /// there is no closing one
+    pub fn unclosed_keymap(&self) -> Option<usize> {
+        self.first_line.filter(|_| !self.closed)
+    }
+
+    /// Fails early with a helpful message when the content is obviously
+    /// not an annotated xmonad config, the parser error would not tell why
+    pub fn check_format(&self) -> Result<()> {
+        if self.binary {
+            return Err(Failure::report(
+                Exit::Unreadable,
+                "The config looks like a binary file, is it the compiled xmonad?\n\
+                 The path must point to your xmonad.hs file"
+                    .to_owned(),
+            ));
+        }
+        if self.first_line.is_none() {
+            return Err(Failure::report(
+                Exit::Invalid,
+                format!(
+                    "No `-- #` boundary found, the keymap is not annotated\n\
+                     Surround it with `-- # Title` and `-- #` comments, sections start \
+                     with `-- ## Section` and each keybind is preceded by a \
+                     `-- description` comment\n\
+                     First lines of the file:\n{}",
+                    self.head.join("\n")
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the full lines declaring a keybind, from its keys to the
+    /// end of its action. Keybinds only declared in a comment have none.
+    pub fn source_lines(&self, keybind: &KeybindToken) -> Option<&str> {
+        let action = keybind.2?;
+        let start = match &keybind.0 {
+            Cow::Borrowed(keys) => self.offset(keys).min(self.offset(action)),
+            Cow::Owned(_) => self.offset(action),
+        };
+        let end = self.offset(action) + action.len();
+        let start = self.content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.content[end..]
+            .find('\n')
+            .map_or(self.content.len(), |i| end + i);
+        Some(self.content[start..end].trim_end())
+    }
+
+    // offsets of the layout lines and of the section tags, which end
+    // the layouts, with the layouts of the keybinds that follow
+    fn layout_marks(&self) -> Vec<(usize, Vec<String>)> {
+        self.content
+            .lines()
+            .filter_map(|line| {
+                let line_end = format!("{line}\n");
+                let layouts = match parse_layout_tag(&line_end) {
+                    Ok((_, layouts)) => layouts.into_iter().map(str::to_owned).collect(),
+                    Err(_) if parse_section_tag(&line_end).is_ok() => vec![],
+                    Err(_) if parse_ignored_section_tag(&line_end).is_ok() => vec![],
+                    Err(_) => return None,
+                };
+                Some((self.offset(line), layouts))
+            })
+            .collect()
+    }
+
+    // layouts of the last layout line above the keybind, in its section
+    fn layouts(&self, keybind: &KeybindToken, marks: &[(usize, Vec<String>)]) -> Vec<String> {
+        // the slices of the keybind borrowed from the content
+        let keys = match &keybind.0 {
+            Cow::Borrowed(keys) => Some(*keys),
+            Cow::Owned(_) => None,
+        };
+        let Some(offset) = [Some(keybind.1).filter(|d| !d.is_empty()), keybind.2, keys]
+            .into_iter()
+            .flatten()
+            .map(|slice| self.offset(slice))
+            .min()
+        else {
+            return vec![];
+        };
+        marks
+            .iter()
+            .rev()
+            .find(|(mark, _)| *mark < offset)
+            .map(|(_, layouts)| layouts.clone())
+            .unwrap_or_default()
+    }
+
+    // offset of a slice of the parsed content
+    fn offset(&self, slice: &str) -> usize {
+        (slice.as_ptr() as usize)
+            .saturating_sub(self.content.as_ptr() as usize)
+            .min(self.content.len())
+    }
+
+    /// Line and column of the keys of a keybind, of its description when
+    /// the keys are generated
+    pub fn keybind_position(&self, keybind: &KeybindToken) -> (usize, usize) {
+        match &keybind.0 {
+            Cow::Borrowed(keys) => self.position(keys),
+            Cow::Owned(_) => self.position(keybind.1),
+        }
+    }
+
+    /// Returns the 1-based line and column where the given slice of the
+    /// parsed content starts
+    pub fn position(&self, slice: &str) -> (usize, usize) {
+        let before = &self.content[..self.offset(slice)];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (
+            self.first_line.unwrap_or(1) + before.matches('\n').count(),
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+/// Returns the description without its haddock marker, if any, e.g.
+/// `| Launch a terminal` or `^ Launch a terminal`
+fn strip_haddock_marker(description: &str) -> Option<&str> {
+    let rest = description.strip_prefix(['|', '^'])?;
+    if rest.is_empty() || rest.starts_with([' ', '\t']) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+/// The description of the keybinds declared without any comment, unlike
+/// an empty `--` one it is never rendered
+const UNDESCRIBED: &str = "\0";
+
+// `fallback` keeps the keybinds without description but with an action
+fn handle_haddock(section: Section<'_>, haddock: Haddock, fallback: bool) -> Section<'_> {
+    let keybinds = section
+        .keybinds
+        .into_iter()
+        .filter_map(|KeybindToken(keys, description, action)| {
+            match (strip_haddock_marker(description), haddock) {
+                (None, _) if description == UNDESCRIBED => {
+                    (fallback && action.is_some()).then_some(KeybindToken(keys, "", action))
+                }
+                (None, _) => Some(KeybindToken(keys, description, action)),
+                (Some(_), Haddock::Skip) => None,
+                // a bare marker leaves nothing to describe the keybind
+                (Some(""), Haddock::Strip) => {
+                    (fallback && action.is_some()).then_some(KeybindToken(keys, "", action))
+                }
+                (Some(stripped), Haddock::Strip) => Some(KeybindToken(keys, stripped, action)),
+            }
+        })
+        .collect();
+    Section {
+        keybinds,
+        ..section
+    }
+}
+
+/// Returns the annotated keybinds of the keymap of a
+/// `submap . mkKeymap conf [...]` action, declared like the top level ones
+pub fn submap_keybinds(action: &str) -> Vec<KeybindToken<'_>> {
+    if !action.contains("submap") {
+        return vec![];
     }
+    // the keymap starts on the line after `mkKeymap`
+    let Some(body) = action
+        .find("mkKeymap")
+        .and_then(|i| action[i..].find('\n').map(|j| &action[i + j + 1..]))
+    else {
+        return vec![];
+    };
+    many0(parse_section_inner)(body)
+        .map(|(_, lines)| lines.into_iter().flat_map(|(kept, _)| kept).collect())
+        .unwrap_or_default()
+}
+
+// the keybind followed by the ones of its submap, recursively, their
+// keys prefixed by its keys, e.g. `M-o f`
+fn with_submap(keybind: Keybind, haddock: Haddock, fallback: bool) -> Vec<Keybind> {
+    let children: Vec<Keybind> = keybind
+        .action
+        .as_deref()
+        .map(|action| {
+            let section = handle_haddock(
+                Section {
+                    title: None,
+                    description: None,
+                    keybinds: submap_keybinds(action),
+                    ignored: vec![],
+                },
+                haddock,
+                fallback,
+            );
+            section
+                .keybinds
+                .into_iter()
+                .flat_map(|KeybindToken(keys, description, action)| {
+                    let mut child =
+                        Keybind::new(&format!("{} {keys}", keybind.keys), description, action);
+                    child.submap = true;
+                    child.layouts = keybind.layouts.clone();
+                    child.line = keybind.line;
+                    with_submap(child, haddock, fallback)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    std::iter::once(keybind).chain(children).collect()
+}
+
+/// Describes a keybind by its action, the first line of the expression
+/// without the string quotes, e.g. `spawn "firefox"` gives `spawn firefox`
+pub fn action_description(action: &str) -> String {
+    let line = action.lines().next().unwrap_or_default();
+    let description = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    let description = description.replace('"', "");
+    description.trim_end_matches([' ', '$', '.']).to_owned()
 }
 
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
@@ -54,22 +471,48 @@ where
     delimited(multispace0, inner, multispace0)
 }
 
+/// Consumes the rest of the current line, including its line ending (`\n` or `\r\n`),
+/// and returns its content stripped from trailing spaces and tabs.
+fn rest_of_line(input: &str) -> IResult<&str, &str> {
+    map(terminated(not_line_ending, line_ending), |line: &str| {
+        line.trim_end_matches([' ', '\t'])
+    })(input)
+}
+
+/// Consumes the rest of a comment line, without the `-}` closing a block
+/// comment
+fn comment_rest(input: &str) -> IResult<&str, &str> {
+    map(rest_of_line, |line| {
+        line.strip_suffix(HS_BLOCK_COMMENT_END)
+            .unwrap_or(line)
+            .trim_end()
+    })(input)
+}
+
 #[instrument(skip_all)]
-fn parse_inner(input: &str) -> IResult<&str, Option<Section>> {
+fn parse_inner(input: &str) -> IResult<&str, Option<Section<'_>>> {
     trace!("parsing");
     ws(alt((
         map(parse_section, Some),
-        map(terminated(not_line_ending, newline), |_| None),
+        // the last line may have no line ending
+        map(
+            alt((rest_of_line, terminated(not_line_ending, eof))),
+            |_| None,
+        ),
     )))(input)
 }
 
 #[instrument(skip_all)]
-pub fn parse_entry(input: &str) -> IResult<&str, (Option<&str>, Vec<Section>)> {
+pub fn parse_entry(input: &str) -> IResult<&str, (Option<&str>, Vec<Section<'_>>)> {
     trace!("parse entry point");
     map(
         ws(tuple((
-            many_till(terminated(not_line_ending, newline), parse_boundary),
-            many_till(parse_inner, parse_boundary),
+            many_till(rest_of_line, parse_boundary),
+            // without closing boundary, the keymap goes on to the end
+            many_till(
+                parse_inner,
+                alt((map(parse_boundary, |_| ()), map(eof, |_| ()))),
+            ),
         ))),
         |((_, title), (s, _))| (title, s.into_iter().flatten().collect()),
     )(input)
@@ -82,7 +525,11 @@ fn parse_hs_comment_seq(input: &str) -> IResult<&str, ()> {
         tuple((
             multispace0,
             not(preceded(not(space0), tag(HS_COMMENT_SEQ))),
-            tag(HS_COMMENT_SEQ),
+            // `{-#` opens a pragma, e.g. `{-# LANGUAGE ... #-}`
+            alt((
+                tag(HS_COMMENT_SEQ),
+                terminated(tag(HS_BLOCK_COMMENT_START), not(tag(BOUNDARY_TOKEN))),
+            )),
             not(tag(">")),
             space0,
         )),
@@ -91,7 +538,7 @@ fn parse_hs_comment_seq(input: &str) -> IResult<&str, ()> {
 }
 
 #[instrument(skip_all)]
-fn parse_boundary(input: &str) -> IResult<&str, Option<&str>> {
+pub fn parse_boundary(input: &str) -> IResult<&str, Option<&str>> {
     trace!("parse_boundary");
     map(
         ws(tuple((
@@ -99,7 +546,7 @@ fn parse_boundary(input: &str) -> IResult<&str, Option<&str>> {
             not(tag(SECTION_TOKEN)),
             tag(BOUNDARY_TOKEN),
             space0,
-            opt(terminated(not_line_ending, newline)), // main title
+            opt(comment_rest), // main title
         ))),
         |(_, _, _, _, title)| title.and_then(|v| if v.is_empty() { None } else { Some(v) }),
     )(input)
@@ -112,102 +559,510 @@ fn parse_section_tag(input: &str) -> IResult<&str, Option<&str>> {
         ws(tuple((
             parse_hs_comment_seq,
             tag(SECTION_TOKEN),
+            not(tag(LAYOUT_TOKEN)),
+            space0,
+            opt(comment_rest), // section title
+        ))),
+        |(_, _, _, _, title)| title.and_then(|v| if v.is_empty() { None } else { Some(v) }),
+    )(input)
+}
+
+/// Parses the `-- ##layout Tall, Full` line marking the keybinds that
+/// follow as active in these layouts, until the end of the section or
+/// the next layout line. `-- ##layout *` marks the next ones global.
+#[instrument(skip_all)]
+pub fn parse_layout_tag(input: &str) -> IResult<&str, Vec<&str>> {
+    trace!("parse layout tag");
+    map(
+        ws(tuple((
+            parse_hs_comment_seq,
+            tag(SECTION_TOKEN),
+            tag(LAYOUT_TOKEN),
+            space0,
+            opt(comment_rest),
+        ))),
+        |(_, _, _, _, layouts)| {
+            layouts
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|layout| !layout.is_empty() && *layout != "*")
+                .collect()
+        },
+    )(input)
+}
+
+/// Parses the `-- ! ## Section` tag of a section whose keybinds are all
+/// ignored
+#[instrument(skip_all)]
+fn parse_ignored_section_tag(input: &str) -> IResult<&str, Option<&str>> {
+    trace!("parse ignored section tag");
+    map(
+        ws(tuple((
+            parse_hs_comment_seq,
+            tag(IGNORE_TOKEN),
+            space0,
+            tag(SECTION_TOKEN),
             space0,
-            opt(terminated(not_line_ending, newline)), // section title
+            opt(comment_rest),
         ))),
-        |(_, _, _, title)| title.and_then(|v| if v.is_empty() { None } else { Some(v) }),
+        |(_, _, _, _, _, title)| title.filter(|t| !t.is_empty()),
     )(input)
 }
 
+/// Parses the `-- : Controls volume and playback` line following a
+/// section tag
 #[instrument(skip_all)]
-fn parse_section_inner(input: &str) -> IResult<&str, Option<KeybindToken>> {
+fn parse_section_description(input: &str) -> IResult<&str, &str> {
+    trace!("parse section description");
+    map(
+        ws(tuple((
+            parse_hs_comment_seq,
+            tag(SECTION_DESCRIPTION_TOKEN),
+            space0,
+            comment_rest,
+        ))),
+        |(_, _, _, description)| description,
+    )(input)
+}
+
+/// Returns the keybinds of a line, and the ignored ones apart
+#[instrument(skip_all)]
+fn parse_section_inner(
+    input: &str,
+) -> IResult<&str, (Vec<KeybindToken<'_>>, Vec<KeybindToken<'_>>)> {
     trace!("parsing section inner");
     ws(alt((
-        map(parse_keybind_declaration, Some),
-        map(parse_keybind_comment, Some),
-        map(terminated(not_line_ending, newline), |_| None),
+        map(parse_keybind_declaration, |k| (k, vec![])),
+        map(parse_ignored_keybind_comment, |k| (vec![], vec![k])),
+        map(parse_ignored_declaration, |k| (vec![], k)),
+        map(parse_keybind_comment, |k| (vec![k], vec![])),
+        map(parse_undescribed_declaration, |k| (k, vec![])),
+        map(rest_of_line, |_| (vec![], vec![])),
     )))(input)
 }
 
 #[instrument(skip_all)]
-fn parse_section(input: &str) -> IResult<&str, Section> {
+fn parse_section(input: &str) -> IResult<&str, Section<'_>> {
     trace!("parsing section");
     map(
         ws(tuple((
-            parse_section_tag,
+            alt((
+                map(parse_section_tag, |title| (title, false)),
+                map(parse_ignored_section_tag, |title| (title, true)),
+            )),
+            opt(parse_section_description),
             many_till(
                 parse_section_inner,
                 alt((
                     map(peek(parse_boundary), |_| ()),
                     map(peek(parse_section_tag), |_| ()),
+                    map(peek(parse_ignored_section_tag), |_| ()),
                     map(eof, |_| ()),
                 )),
             ),
         ))),
-        |(title, (k, _))| Section {
-            title,
-            keybinds: k.into_iter().flatten().collect(),
+        |((title, ignored_section), description, (k, _))| {
+            let (keybinds, ignored): (Vec<_>, Vec<_>) = k.into_iter().unzip();
+            let (mut keybinds, mut ignored): (Vec<_>, Vec<_>) = (
+                keybinds.into_iter().flatten().collect(),
+                ignored.into_iter().flatten().collect(),
+            );
+            // the section is kept, its keybinds are all ignored
+            if ignored_section {
+                keybinds.append(&mut ignored);
+                std::mem::swap(&mut keybinds, &mut ignored);
+            }
+            Section {
+                title,
+                description: description.filter(|d| !d.is_empty()),
+                keybinds,
+                ignored,
+            }
         },
     )(input)
 }
 
+/// Parses a Haskell string literal, e.g. `"M-\\"`, and returns its
+/// content with the escape sequences left as is, see `unescape`. A gap,
+/// backslashes enclosing white spaces, may span lines.
+pub fn hs_string(input: &str) -> IResult<&str, &str> {
+    let error = || {
+        nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Escaped,
+        ))
+    };
+    let body = input.strip_prefix('"').ok_or_else(error)?;
+    let mut in_gap = false;
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if in_gap => in_gap = false,
+            _ if in_gap => {}
+            '\\' => match chars.next() {
+                Some((_, c)) if c.is_whitespace() => in_gap = true,
+                Some(_) => {}
+                None => break,
+            },
+            '"' => return Ok((&body[i + 1..], &body[..i])),
+            '\n' => break,
+            _ => {}
+        }
+    }
+    Err(error())
+}
+
+/// Resolves the escape sequences of the content of a string literal,
+/// e.g. `M-\\` is `M-\` and `\"` a quote. `\&` and the gaps are dropped,
+/// an unknown escape is kept as is.
+pub fn unescape(literal: &str) -> Cow<'_, str> {
+    if !literal.contains('\\') {
+        return Cow::Borrowed(literal);
+    }
+    let mut unescaped = String::with_capacity(literal.len());
+    let mut rest = literal;
+    while let Some(i) = rest.find('\\') {
+        unescaped.push_str(&rest[..i]);
+        let escape = &rest[i + 1..];
+        let mut chars = escape.chars();
+        let Some(c) = chars.next() else {
+            unescaped.push('\\');
+            rest = escape;
+            break;
+        };
+        let after = chars.as_str();
+        let single = match c {
+            'a' => Some('\x07'),
+            'b' => Some('\x08'),
+            'f' => Some('\x0c'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\x0b'),
+            '\\' | '"' | '\'' => Some(c),
+            _ => None,
+        };
+        rest = if let Some(single) = single {
+            unescaped.push(single);
+            after
+        } else if c == '&' {
+            after
+        } else if c.is_whitespace() {
+            after.split_once('\\').map_or("", |(_, rest)| rest)
+        } else if let ('^', Some(control @ '@'..='_')) = (c, after.chars().next()) {
+            unescaped.push(char::from(control as u8 - b'@'));
+            &after[1..]
+        } else if let Some((code, rest)) = escaped_code(c, after, escape) {
+            unescaped.push(code);
+            rest
+        } else if let Some((name, code)) = ASCII_CONTROLS
+            .iter()
+            .zip(0u8..)
+            .chain([(&"SP", b' '), (&"DEL", 0x7f)])
+            .filter(|(name, _)| escape.starts_with(**name))
+            .max_by_key(|(name, _)| name.len())
+        {
+            unescaped.push(char::from(code));
+            &escape[name.len()..]
+        } else {
+            unescaped.push('\\');
+            escape
+        };
+    }
+    unescaped.push_str(rest);
+    Cow::Owned(unescaped)
+}
+
+// a numeric escape, `\65`, `\x41` or `\o101`, and the rest of the literal
+fn escaped_code<'a>(c: char, after: &'a str, escape: &'a str) -> Option<(char, &'a str)> {
+    let (radix, digits) = match c {
+        'x' => (16, after),
+        'o' => (8, after),
+        '0'..='9' => (10, escape),
+        _ => return None,
+    };
+    let end = digits
+        .find(|d: char| !d.is_digit(radix))
+        .unwrap_or(digits.len());
+    let code = u32::from_str_radix(&digits[..end], radix).ok()?;
+    Some((char::from_u32(code)?, &digits[end..]))
+}
+
+/// Consumes the input up to the parenthesis closing the current
+/// expression, e.g. `sendMessage (IncMasterN 1)` of a keybind tuple.
+/// Parentheses inside string literals are ignored.
+fn balanced(input: &str) -> IResult<&str, &str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '(' | '[' if !in_string => depth += 1,
+            ')' | ']' if !in_string => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return Ok((&input[i..], &input[..i])),
+            },
+            _ => {}
+        }
+    }
+    Err(nom::Err::Error(nom::error::Error::new(
+        input,
+        nom::error::ErrorKind::TakeUntil,
+    )))
+}
+
 #[instrument(skip_all)]
-fn parse_keybind_definition(input: &str) -> IResult<&str, &str> {
+pub fn parse_keybind_tuple(input: &str) -> IResult<&str, (&str, Option<&str>)> {
     trace!("parsing");
     map(
-        tuple((
-            take_until("("),
-            delimited(
-                tag("("),
-                tuple((
-                    space0,
-                    delimited(tag("\""), take_until("\""), tag("\"")),
-                    take_until(")"),
-                )),
-                tag(")"),
-            ),
-        )),
-        |(_, (_, key, _))| key,
+        delimited(tag("("), tuple((space0, hs_string, balanced)), tag(")")),
+        |(_, key, action): (&str, &str, &str)| {
+            let action = action
+                .trim_start_matches([',', ' ', '\t', '\r', '\n'])
+                .trim_end();
+            (key, (!action.is_empty()).then_some(action))
+        },
     )(input)
 }
 
+#[instrument(skip_all)]
+fn parse_keybind_definition(input: &str) -> IResult<&str, (&str, Option<&str>)> {
+    trace!("parsing");
+    preceded(take_until("("), parse_keybind_tuple)(input)
+}
+
+/// Parses a keybind definition followed by the other ones declared on
+/// the same line, e.g. `("M-a", foo), ("M-b", bar)`
+#[instrument(skip_all)]
+fn parse_keybind_definitions(input: &str) -> IResult<&str, Vec<(&str, Option<&str>)>> {
+    trace!("parsing");
+    map(
+        pair(
+            parse_keybind_definition,
+            many0(preceded(
+                tuple((space0, tag(","), space0)),
+                parse_keybind_tuple,
+            )),
+        ),
+        |(first, mut others)| {
+            others.insert(0, first);
+            others
+        },
+    )(input)
+}
+
+/// Parses the consecutive comment lines describing a keybind. The
+/// description of several lines spans them, comment markers included,
+/// see `description_text`.
 #[instrument(skip_all)]
 fn parse_keybind_description(input: &str) -> IResult<&str, &str> {
+    trace!("parsing");
+    let line = || {
+        map(
+            tuple((
+                parse_hs_comment_seq,
+                not(tag(BOUNDARY_TOKEN)),
+                not(tag(IGNORE_TOKEN)),
+                not(tag("\"")),
+                comment_rest,
+            )),
+            |(_, _, _, _, description)| description,
+        )
+    };
+    // a blank line ends the description
+    let blank_line = recognize(pair(space0, line_ending));
+    map(
+        pair(line(), many0(preceded(not(blank_line), line()))),
+        |(first, others)| match others.last() {
+            Some(last) => {
+                let offset = |slice: &str| slice.as_ptr() as usize - input.as_ptr() as usize;
+                &input[offset(first)..offset(last) + last.len()]
+            }
+            None => first,
+        },
+    )(input)
+}
+
+/// The text of a description spanning comment lines, a line each,
+/// without their comment markers, e.g. `Toggle the bar\n  -- and the
+/// gaps` is `Toggle the bar\nand the gaps`
+pub fn description_text(description: &str) -> Cow<'_, str> {
+    if !description.contains('\n') {
+        return Cow::Borrowed(description);
+    }
+    let lines: Vec<&str> = description
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line.trim();
+            let line = match i {
+                0 => line,
+                _ => line
+                    .strip_prefix(HS_COMMENT_SEQ)
+                    .or_else(|| line.strip_prefix(HS_BLOCK_COMMENT_START))
+                    .unwrap_or(line),
+            };
+            line.strip_suffix(HS_BLOCK_COMMENT_END)
+                .unwrap_or(line)
+                .trim()
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+/// Parses the `-- ! Description` of an ignored keybind
+#[instrument(skip_all)]
+fn parse_ignored_description(input: &str) -> IResult<&str, &str> {
     trace!("parsing");
     map(
         tuple((
             parse_hs_comment_seq,
-            not(tag(BOUNDARY_TOKEN)),
-            not(tag(IGNORE_TOKEN)),
-            not(tag("\"")),
-            terminated(not_line_ending, newline),
+            tag(IGNORE_TOKEN),
+            space0,
+            comment_rest,
         )),
-        |(_, _, _, _, description)| description,
+        |(_, _, _, description)| description,
+    )(input)
+}
+
+#[instrument(skip_all)]
+fn parse_keybind_declaration(input: &str) -> IResult<&str, Vec<KeybindToken<'_>>> {
+    trace!("parsing");
+    declaration(parse_keybind_description)(input)
+}
+
+#[instrument(skip_all)]
+fn parse_ignored_declaration(input: &str) -> IResult<&str, Vec<KeybindToken<'_>>> {
+    trace!("parsing");
+    declaration(parse_ignored_description)(input)
+}
+
+/// Parses the definitions of a line not preceded by a description, e.g.
+/// `, ("M-q", kill)`, their description is `UNDESCRIBED`
+#[instrument(skip_all)]
+fn parse_undescribed_declaration(input: &str) -> IResult<&str, Vec<KeybindToken<'_>>> {
+    trace!("parsing");
+    // the keys of a comprehension are not a single string
+    let definition = || {
+        verify(parse_keybind_tuple, |(_, a)| {
+            !a.is_some_and(|a| a.starts_with("++"))
+        })
+    };
+    map(
+        preceded(
+            tuple((opt(one_of("[,")), space0)),
+            pair(
+                definition(),
+                many0(preceded(tuple((space0, tag(","), space0)), definition())),
+            ),
+        ),
+        |(first, others)| {
+            [first]
+                .into_iter()
+                .chain(others)
+                .map(|(k, a)| KeybindToken(unescape(k), UNDESCRIBED, a))
+                .collect()
+        },
     )(input)
 }
 
+/// A description parsed by `description` followed by the definitions
+/// of the keybinds it describes
+fn declaration<'a>(
+    description: impl FnMut(&'a str) -> IResult<&'a str, &'a str>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<KeybindToken<'a>>> {
+    map(
+        tuple((
+            description,
+            alt((
+                map(parse_keybind_comprehension, |(k, a)| vec![(k.into(), a)]),
+                map(parse_keybind_definitions, |definitions| {
+                    definitions
+                        .into_iter()
+                        .map(|(k, a)| (unescape(k), a))
+                        .collect()
+                }),
+            )),
+        )),
+        |(d, definitions)| {
+            definitions
+                .into_iter()
+                .map(|(k, a)| KeybindToken(k, d, a))
+                .collect()
+        },
+    )
+}
+
+/// Parses keybinds generated by a list comprehension, e.g.
+/// `[("M-" ++ show n, windows $ W.greedyView n) | n <- [1..9]]`,
+/// into a single keybind whose keys show the range, `M-[1..9]`
 #[instrument(skip_all)]
-fn parse_keybind_declaration(input: &str) -> IResult<&str, KeybindToken> {
+fn parse_keybind_comprehension(input: &str) -> IResult<&str, (String, Option<&str>)> {
     trace!("parsing");
     map(
-        tuple((parse_keybind_description, parse_keybind_definition)),
-        |(d, k)| KeybindToken(k, d),
+        tuple((
+            preceded(take_until("("), tag("(")),
+            preceded(space0, hs_string),
+            preceded(tuple((space0, tag("++"))), take_until("|")),
+            preceded(
+                tuple((
+                    tag("|"),
+                    take_until("<-"),
+                    tag("<-"),
+                    multispace0,
+                    opt(terminated(tag("zip"), multispace0)),
+                )),
+                recognize(delimited(tag("["), take_until("]"), tag("]"))),
+            ),
+        )),
+        |(_, prefix, tuple_rest, range): (&str, &str, &str, &str)| {
+            // the action is what follows the keys in the tuple
+            let action = tuple_rest
+                .split_once(',')
+                .map(|(_, a)| a.trim_end())
+                .map(|a| a.strip_suffix(')').unwrap_or(a).trim())
+                .filter(|a| !a.is_empty());
+            (format!("{}{range}", unescape(prefix)), action)
+        },
     )(input)
 }
 
 #[instrument(skip_all)]
-fn parse_keybind_comment(input: &str) -> IResult<&str, KeybindToken> {
+fn parse_keybind_comment(input: &str) -> IResult<&str, KeybindToken<'_>> {
     trace!("parsing");
     map(
         tuple((
             parse_hs_comment_seq,
             not(tag(BOUNDARY_TOKEN)),
             not(tag(IGNORE_TOKEN)),
-            delimited(tag("\""), take_until("\""), tag("\"")), // keymap
+            hs_string, // keymap
+            space0,
+            comment_rest, // description
+        )),
+        |(_, (), (), k, _, d)| KeybindToken(unescape(k), d, None),
+    )(input)
+}
+
+/// Parses the `-- ! "<keys>" Description` of an ignored keybind comment
+#[instrument(skip_all)]
+fn parse_ignored_keybind_comment(input: &str) -> IResult<&str, KeybindToken<'_>> {
+    trace!("parsing");
+    map(
+        tuple((
+            parse_hs_comment_seq,
+            tag(IGNORE_TOKEN),
+            space0,
+            hs_string, // keymap
             space0,
-            terminated(not_line_ending, newline), // description
+            comment_rest, // description
         )),
-        |(_, (), (), k, _, d)| KeybindToken(k, d),
+        |(_, _, _, k, _, d)| KeybindToken(unescape(k), d, None),
     )(input)
 }
 
@@ -238,6 +1093,9 @@ mod tests {
         assert!(parse_boundary("-- ##").is_err());
         assert!(parse_boundary("--##").is_err());
         assert!(parse_boundary("-- ##Fool").is_err());
+        assert_eq!(parse_boundary("{- # Fool -}\n"), Ok(("", Some("Fool"))));
+        assert_eq!(parse_boundary("\t {- # -}\n"), Ok(("", None)));
+        assert!(parse_boundary("{-# LANGUAGE Foo #-}\n").is_err());
     }
 
     #[test]
@@ -310,25 +1168,59 @@ mod tests {
 
     #[test]
     fn keybind_definition_parsing() {
-        assert_eq!(parse_keybind_definition(r#"("M-t")"#), Ok(("", "M-t")));
+        assert_eq!(
+            parse_keybind_definition(r#"("M-t")"#),
+            Ok(("", ("M-t", None)))
+        );
         assert_eq!(
             parse_keybind_definition(r#", ("M-t", stuff)"#),
-            Ok(("", "M-t"))
+            Ok(("", ("M-t", Some("stuff"))))
         );
         assert_eq!(
             parse_keybind_definition(r#", ( "M-)", stuff)"#),
-            Ok(("", "M-)"))
+            Ok(("", ("M-)", Some("stuff"))))
         );
         assert_eq!(
             parse_keybind_definition(r#", ( "M-t", stuff)"#),
-            Ok(("", "M-t"))
+            Ok(("", ("M-t", Some("stuff"))))
         );
         assert_eq!(
             parse_keybind_definition(
                 r#", ( "M-<Space>",
                             stuff)"#
             ),
-            Ok(("", "M-<Space>"))
+            Ok(("", ("M-<Space>", Some("stuff"))))
+        );
+    }
+
+    #[test]
+    fn keybind_definitions_parsing() {
+        assert_eq!(
+            parse_keybind_definitions(r#", ("M-a", foo), ("M-b", bar)"#),
+            Ok(("", vec![("M-a", Some("foo")), ("M-b", Some("bar"))]))
+        );
+        assert_eq!(
+            parse_keybind_definitions(r#"("M-a", foo),("M-b", bar) , ( "M-c",baz)"#),
+            Ok((
+                "",
+                vec![
+                    ("M-a", Some("foo")),
+                    ("M-b", Some("bar")),
+                    ("M-c", Some("baz"))
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_keybind_definitions(
+                r#"("M-a",
+                    foo), ("M-b", bar)"#
+            ),
+            Ok(("", vec![("M-a", Some("foo")), ("M-b", Some("bar"))]))
+        );
+        // the next line tuple is not part of the definition
+        assert_eq!(
+            parse_keybind_definitions("(\"M-a\", foo)\n, (\"M-b\", bar)"),
+            Ok(("\n, (\"M-b\", bar)", vec![("M-a", Some("foo"))]))
         );
     }
 
@@ -351,6 +1243,31 @@ mod tests {
         assert!(parse_keybind_description("--! Ignored keybind\n").is_err());
     }
 
+    #[test]
+    fn multiline_description_parsing() {
+        let config = "  -- Toggle the bar\n  --   and the gaps  \n  {- of the workspace -}\n  , (\"M-b\", sendMessage ToggleStruts)";
+        let Ok((_, keybinds)) = parse_keybind_declaration(config) else {
+            panic!("not parsed");
+        };
+        assert_eq!(keybinds[0].0, "M-b");
+        assert_eq!(
+            description_text(keybinds[0].1),
+            "Toggle the bar\nand the gaps\nof the workspace"
+        );
+        // the position of a keybind is the one of its first line
+        assert!(keybinds[0].1.starts_with("Toggle the bar\n"));
+        // a blank line or a section tag ends the description
+        assert_eq!(
+            parse_keybind_description("-- Unrelated\n\n-- Close\n"),
+            Ok(("\n-- Close\n", "Unrelated"))
+        );
+        assert_eq!(
+            parse_keybind_description("-- Close\n-- ## Apps\n"),
+            Ok(("-- ## Apps\n", "Close"))
+        );
+        assert_eq!(description_text("One line"), "One line");
+    }
+
     #[test]
     fn keybind_declaration_parsing() {
         assert_eq!(
@@ -358,7 +1275,14 @@ mod tests {
                 r#"  -- Recompile and restart XMonad
     ("M-C-q",       spawn "xmonad --recompile; xmonad --restart")"#
             ),
-            Ok(("", KeybindToken("M-C-q", "Recompile and restart XMonad")))
+            Ok((
+                "",
+                vec![KeybindToken(
+                    "M-C-q".into(),
+                    "Recompile and restart XMonad",
+                    Some(r#"spawn "xmonad --recompile; xmonad --restart""#)
+                )]
+            ))
         );
         assert_eq!(
             parse_keybind_declaration(
@@ -366,7 +1290,14 @@ mod tests {
     ("M-C-q",
         spawn "xmonad --recompile; xmonad --restart")"#
             ),
-            Ok(("", KeybindToken("M-C-q", "Recompile and restart XMonad")))
+            Ok((
+                "",
+                vec![KeybindToken(
+                    "M-C-q".into(),
+                    "Recompile and restart XMonad",
+                    Some(r#"spawn "xmonad --recompile; xmonad --restart""#)
+                )]
+            ))
         );
         assert!(parse_keybind_declaration(
             r#"  -- Open a terminal
@@ -386,15 +1317,147 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn keybind_declaration_multiple_tuples_parsing() {
+        assert_eq!(
+            parse_keybind_declaration(
+                r#"  -- Focus windows
+    , ("M-j", windows W.focusDown), ("M-k", windows W.focusUp)"#
+            ),
+            Ok((
+                "",
+                vec![
+                    KeybindToken("M-j".into(), "Focus windows", Some("windows W.focusDown")),
+                    KeybindToken("M-k".into(), "Focus windows", Some("windows W.focusUp")),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn keybind_tuple_balanced_parsing() {
+        assert_eq!(
+            parse_keybind_tuple(r#"("M-b", sendMessage (IncMasterN (-1))), next"#),
+            Ok((", next", ("M-b", Some("sendMessage (IncMasterN (-1))"))))
+        );
+        assert_eq!(
+            parse_keybind_tuple(r#"("M-x", myAction) where myAction = spawn "x""#),
+            Ok((r#" where myAction = spawn "x""#, ("M-x", Some("myAction"))))
+        );
+        assert_eq!(
+            parse_keybind_tuple(
+                r#"("M-n", spawn "notify-send ':)'" >> (windows W.focusUp &&& id))"#
+            ),
+            Ok((
+                "",
+                (
+                    "M-n",
+                    Some(r#"spawn "notify-send ':)'" >> (windows W.focusUp &&& id)"#)
+                )
+            ))
+        );
+        assert_eq!(
+            parse_keybind_tuple(r#"("M-s", withFocused $ windows . W.sink)"#),
+            Ok(("", ("M-s", Some("withFocused $ windows . W.sink"))))
+        );
+        assert!(parse_keybind_tuple(r#"("M-b", sendMessage (IncMasterN 1)"#).is_err());
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(hs_string(r#""M-\\", kill"#), Ok((", kill", r"M-\\")));
+        assert_eq!(hs_string(r#""say \"hi\"" x"#), Ok((" x", r#"say \"hi\""#)));
+        assert_eq!(hs_string("\"a\\\n   \\b\""), Ok(("", "a\\\n   \\b")));
+        assert!(hs_string("\"a\nb\"").is_err());
+        assert!(hs_string(r#""M-\""#).is_err());
+
+        assert_eq!(unescape("M-p"), Cow::Borrowed("M-p"));
+        assert_eq!(unescape(r"M-\\"), r"M-\");
+        assert_eq!(unescape(r#"\"quoted\" \'a\'"#), r#""quoted" 'a'"#);
+        assert_eq!(unescape(r"a\tb\nc"), "a\tb\nc");
+        assert_eq!(unescape(r"\65\&1\x42\o103"), "A1BC");
+        assert_eq!(unescape(r"\ESC\SO\SOH\DEL\^[\SP"), "\x1b\x0e\x01\x7f\x1b ");
+        assert_eq!(unescape("gap\\\n    \\ped"), "gapped");
+        assert_eq!(unescape(r"\q"), r"\q");
+
+        assert_eq!(
+            parse_keybind_tuple(r#"("M-\\", spawn "x")"#),
+            Ok(("", (r"M-\\", Some(r#"spawn "x""#))))
+        );
+        assert_eq!(
+            parse_keybind_comment("-- \"M-\\\"\" Insert a quote\n"),
+            Ok(("", KeybindToken("M-\"".into(), "Insert a quote", None)))
+        );
+        assert_eq!(
+            parse_keybind_declaration("-- Backslash\n, (\"M-\\\\\", kill)\n"),
+            Ok((
+                "\n",
+                vec![KeybindToken(r"M-\".into(), "Backslash", Some("kill"))]
+            ))
+        );
+    }
+
+    #[test]
+    fn keybind_comprehension_parsing() {
+        assert_eq!(
+            parse_keybind_comprehension(
+                r#"++ [("M-" ++ show n, windows $ W.greedyView n) | n <- [1..9]]"#
+            ),
+            Ok((
+                "]",
+                ("M-[1..9]".to_owned(), Some("windows $ W.greedyView n"))
+            ))
+        );
+        assert_eq!(
+            parse_keybind_comprehension(
+                r#"++ [ ("M-S-" ++ k, windows $ W.shift w)
+                   | (k, w) <- zip ["1", "2", "3"] myWorkspaces
+                   ]"#
+            ),
+            Ok((
+                " myWorkspaces\n                   ]",
+                (
+                    r#"M-S-["1", "2", "3"]"#.to_owned(),
+                    Some("windows $ W.shift w")
+                )
+            ))
+        );
+        assert!(parse_keybind_comprehension(r#", ("M-t", spawn "t")"#).is_err());
+    }
+
+    #[test]
+    fn keybind_declaration_comprehension_parsing() {
+        assert_eq!(
+            parse_keybind_declaration(
+                r#"  -- Switch to workspace N
+    ++ [("M-" ++ show n, windows $ W.greedyView n) | n <- [1..9]]"#
+            ),
+            Ok((
+                "]",
+                vec![KeybindToken(
+                    "M-[1..9]".into(),
+                    "Switch to workspace N",
+                    Some("windows $ W.greedyView n")
+                )]
+            ))
+        );
+    }
+
     #[test]
     fn keybind_inline_parsing() {
         assert_eq!(
             parse_keybind_comment("-- \"M-<[]>\" Move to next/previous screen\n"),
-            Ok(("", KeybindToken("M-<[]>", "Move to next/previous screen")))
+            Ok((
+                "",
+                KeybindToken("M-<[]>".into(), "Move to next/previous screen", None)
+            ))
         );
         assert_eq!(
             parse_keybind_comment("--\"M-<[]>\"Move to next/previous screen\n"),
-            Ok(("", KeybindToken("M-<[]>", "Move to next/previous screen")))
+            Ok((
+                "",
+                KeybindToken("M-<[]>".into(), "Move to next/previous screen", None)
+            ))
         );
         assert!(parse_keybind_comment("--# \"M-d\" description\n").is_err());
         assert!(parse_keybind_comment("-- # \"M-d\" description\n").is_err());
@@ -403,6 +1466,75 @@ mod tests {
         assert!(parse_keybind_comment("-- ! \"M-t\"Open a terminal\n").is_err());
     }
 
+    #[test]
+    fn tabs_and_trailing_whitespace_parsing() {
+        assert_eq!(
+            parse_boundary("\t--\t# Title \t\n"),
+            Ok(("", Some("Title")))
+        );
+        assert_eq!(parse_boundary("\t-- #\t\n"), Ok(("", None)));
+        assert_eq!(
+            parse_section_tag("\t--\t##\tFool\t\n"),
+            Ok(("", Some("Fool")))
+        );
+        assert_eq!(
+            parse_keybind_description("\t--\tA description \t\n"),
+            Ok(("", "A description"))
+        );
+        assert_eq!(
+            parse_keybind_comment("\t--\t\"M-a\"\tdesc a\t\n"),
+            Ok(("", KeybindToken("M-a".into(), "desc a", None)))
+        );
+        assert_eq!(
+            parse_keybind_definition("\t,\t(\t\"M-a\",\tspawn \"a\")"),
+            Ok(("", ("M-a", Some("spawn \"a\""))))
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings_parsing() {
+        assert_eq!(parse_boundary("-- # Title\r\n"), Ok(("", Some("Title"))));
+        assert_eq!(parse_section_tag("-- ## Fool\r\n"), Ok(("", Some("Fool"))));
+        assert_eq!(
+            parse_keybind_comment("-- \"M-a\" desc a\r\n"),
+            Ok(("", KeybindToken("M-a".into(), "desc a", None)))
+        );
+    }
+
+    #[test]
+    fn parse_section_indented_with_tabs() {
+        assert_eq!(
+            parse_section(
+                "\t-- ## A section\n\t-- \"M-1\" desc 1\n\t-- desc a\n\t, (\"M-a\",\tspawn \"lock.sh\")\n"
+            ),
+            Ok((
+                "",
+                Section {
+                    title: Some("A section"),
+                    description: None,
+                    ignored: vec![],
+                    keybinds: vec![KeybindToken("M-1".into(), "desc 1", None), KeybindToken("M-a".into(), "desc a", Some(r#"spawn "lock.sh""#)),]
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_section_with_description() {
+        assert_eq!(
+            parse_section("-- ## Media\n-- : Controls volume and playback\n-- \"M-m\" mute\n"),
+            Ok((
+                "",
+                Section {
+                    title: Some("Media"),
+                    description: Some("Controls volume and playback"),
+                    ignored: vec![],
+                    keybinds: vec![KeybindToken("M-m".into(), "mute", None)]
+                }
+            ))
+        );
+    }
+
     #[test]
     fn parse_empty_section1() {
         assert_eq!(
@@ -444,6 +1576,8 @@ mod tests {
                 "",
                 Section {
                     title: Some("-- ##"),
+                    description: None,
+                    ignored: vec![],
                     ..Default::default()
                 }
             ))
@@ -462,6 +1596,8 @@ mod tests {
                 "-- ## Another Section",
                 Section {
                     title: Some("Section"),
+                    description: None,
+                    ignored: vec![],
                     keybinds: vec![]
                 }
             ))
@@ -479,6 +1615,8 @@ mod tests {
                 "",
                 Section {
                     title: Some("Section"),
+                    description: None,
+                    ignored: vec![],
                     keybinds: vec![]
                 }
             ))
@@ -496,6 +1634,8 @@ mod tests {
                 "",
                 Section {
                     title: Some("-- ##"),
+                    description: None,
+                    ignored: vec![],
                     keybinds: vec![]
                 }
             ))
@@ -516,7 +1656,9 @@ mod tests {
                 "-- #\n                    -- \"M-b\" desc for B\n                  ",
                 Section {
                     title: Some("Section"),
-                    keybinds: vec![KeybindToken("M-a", "desc for A")]
+                    description: None,
+                    ignored: vec![],
+                    keybinds: vec![KeybindToken("M-a".into(), "desc for A", None)]
                 }
             ))
         );
@@ -535,6 +1677,8 @@ mod tests {
                 "",
                 Section {
                     title: Some("A section"),
+                    description: None,
+                    ignored: vec![],
                     keybinds: vec![]
                 }
             ))
@@ -554,6 +1698,8 @@ mod tests {
                 "",
                 Section {
                     title: Some("A section"),
+                    description: None,
+                    ignored: vec![],
                     keybinds: vec![]
                 }
             ))
@@ -574,7 +1720,9 @@ mod tests {
                 "",
                 Section {
                     title: Some("A section"),
-                    keybinds: vec![KeybindToken("M-1", "desc 1")]
+                    description: None,
+                    ignored: vec![],
+                    keybinds: vec![KeybindToken("M-1".into(), "desc 1", None)]
                 }
             ))
         );
@@ -594,7 +1742,12 @@ mod tests {
                 "",
                 Section {
                     title: Some("A section"),
-                    keybinds: vec![KeybindToken("M-1", "desc 1"), KeybindToken("M-2", "desc 2"),]
+                    description: None,
+                    ignored: vec![],
+                    keybinds: vec![
+                        KeybindToken("M-1".into(), "desc 1", None),
+                        KeybindToken("M-2".into(), "desc 2", None),
+                    ]
                 }
             ))
         );
@@ -615,7 +1768,12 @@ mod tests {
                 "",
                 Section {
                     title: Some("A section"),
-                    keybinds: vec![KeybindToken("M-1", "desc 1"), KeybindToken("M-a", "desc a"),]
+                    description: None,
+                    ignored: vec![],
+                    keybinds: vec![
+                        KeybindToken("M-1".into(), "desc 1", None),
+                        KeybindToken("M-a".into(), "desc a", Some(r#"spawn "lock.sh""#)),
+                    ]
                 }
             ))
         );
@@ -639,11 +1797,13 @@ mod tests {
                 "",
                 Section {
                     title: Some("A section"),
+                    description: None,
+                    ignored: vec![],
                     keybinds: vec![
-                        KeybindToken("M-1", "desc 1"),
-                        KeybindToken("M-a", "desc a"),
-                        KeybindToken("M-2", "desc 2"),
-                        KeybindToken("M-b", "desc b"),
+                        KeybindToken("M-1".into(), "desc 1", None),
+                        KeybindToken("M-a".into(), "desc a", Some(r#"spawn "lock.sh""#)),
+                        KeybindToken("M-2".into(), "desc 2", None),
+                        KeybindToken("M-b".into(), "desc b", Some("sendMessage (IncMasterN 1)")),
                     ]
                 }
             ))
@@ -714,28 +1874,442 @@ mod tests {
                     vec![
                         Section {
                             title: Some("Section One"),
+                            description: None,
+                            ignored: vec![],
                             keybinds: vec![
-                                KeybindToken("M-1", "desc 1"),
-                                KeybindToken("M-a", "desc a"),
-                                KeybindToken("M-2", "desc 2"),
-                                KeybindToken("M-b", "desc b"),
+                                KeybindToken("M-1".into(), "desc 1", None),
+                                KeybindToken("M-a".into(), "desc a", Some(r#"spawn "lock.sh""#)),
+                                KeybindToken("M-2".into(), "desc 2", None),
+                                KeybindToken(
+                                    "M-b".into(),
+                                    "desc b",
+                                    Some("sendMessage (IncMasterN 1)")
+                                ),
                             ]
                         },
                         Section {
                             title: Some("Section Two"),
+                            description: None,
+                            ignored: vec![KeybindToken("M-p".into(), "Nope", Some("hi"))],
                             keybinds: vec![
-                                KeybindToken("M-1", "desc 1"),
-                                KeybindToken("M-2", "desc 2"),
-                                KeybindToken("M-b", "desc b"),
+                                KeybindToken("M-1".into(), "desc 1", None),
+                                KeybindToken("M-2".into(), "desc 2", None),
+                                KeybindToken(
+                                    "M-b".into(),
+                                    "desc b",
+                                    Some("sendMessage (IncMasterN 1)")
+                                ),
                             ]
                         },
                         Section {
                             title: Some("Section Three"),
-                            keybinds: vec![KeybindToken("M-t", "desc t"),]
+                            description: None,
+                            ignored: vec![],
+                            keybinds: vec![KeybindToken("M-t".into(), "desc t", None),]
                         }
                     ]
                 )
             ))
         );
     }
+
+    #[test]
+    fn haddock_comments() {
+        let input = r#"-- ## Section
+        -- | launch a terminal
+        , ("M-<Return>", spawn "alacritty")
+        -- |
+        , ("M-q", kill)
+        --> not a comment
+        , ("M-c", spawn "code")
+        -- desc x
+        , ("M-x", spawn "xterm")
+        --
+        , ("M-e", spawn "emacs")
+        "#;
+        let (_, section) = parse_section(input).unwrap();
+        assert_eq!(
+            handle_haddock(section.clone(), Haddock::Strip, false).keybinds,
+            vec![
+                KeybindToken(
+                    "M-<Return>".into(),
+                    "launch a terminal",
+                    Some(r#"spawn "alacritty""#)
+                ),
+                KeybindToken("M-x".into(), "desc x", Some(r#"spawn "xterm""#)),
+                // an empty description without marker is kept
+                KeybindToken("M-e".into(), "", Some(r#"spawn "emacs""#)),
+            ]
+        );
+        assert_eq!(
+            handle_haddock(section.clone(), Haddock::Strip, true).keybinds[1],
+            KeybindToken("M-q".into(), "", Some("kill"))
+        );
+        assert_eq!(
+            handle_haddock(section, Haddock::Skip, false).keybinds,
+            vec![
+                KeybindToken("M-x".into(), "desc x", Some(r#"spawn "xterm""#)),
+                KeybindToken("M-e".into(), "", Some(r#"spawn "emacs""#)),
+            ]
+        );
+        assert_eq!(strip_haddock_marker("^ desc"), Some("desc"));
+        assert_eq!(strip_haddock_marker("|desc"), None);
+    }
+
+    #[test]
+    fn submap_parsing() {
+        let parser = Parser::new(
+            r#"-- # Title
+-- ## Apps
+-- Open an app
+, ("M-o", submap . mkKeymap c $
+    -- Firefox
+    [ ("f", spawn "firefox")
+    , ("x", spawn "undocumented")
+    -- Editors
+    , ("e", submap . mkKeymap c $
+        -- Emacs
+        [ ("e", spawn "emacs")
+        ])
+    ])
+-- Terminal
+, ("M-<Return>", spawn "alacritty")
+-- #
+"#,
+            Haddock::default(),
+            false,
+        );
+        let tokens = parser.tokens().unwrap();
+        let keybinds: Vec<_> = tokens.sections[0]
+            .keybinds
+            .iter()
+            .map(|k| (k.keys.as_str(), k.description.as_str(), k.submap))
+            .collect();
+        assert_eq!(
+            keybinds,
+            vec![
+                ("M-o", "Open an app", false),
+                ("M-o f", "Firefox", true),
+                ("M-o e", "Editors", true),
+                ("M-o e e", "Emacs", true),
+                ("M-<Return>", "Terminal", false),
+            ]
+        );
+        assert_eq!(
+            tokens.sections[0].keybinds[1].command.as_deref(),
+            Some("firefox")
+        );
+    }
+
+    #[test]
+    fn fallback_description() {
+        let content = r#"-- # Title
+-- ## Apps
+-- Terminal
+, ("M-<Return>", spawn "alacritty")
+, ("M-q", kill), ("M-S-q", io exitSuccess)
+--
+, ("M-<Space>", sendMessage NextLayout)
+, [("M-" ++ show n, windows $ W.greedyView n) | n <- [1..9]]
+-- #
+"#;
+        let keybinds = |fallback| {
+            Parser::new(content, Haddock::default(), fallback)
+                .tokens()
+                .unwrap()
+                .sections[0]
+                .keybinds
+                .iter()
+                .map(|k| (k.keys.clone(), k.description.clone()))
+                .collect::<Vec<_>>()
+        };
+        // an empty `--` description is kept without the fallback
+        assert_eq!(
+            keybinds(false),
+            vec![
+                ("M-<Return>".into(), "Terminal".into()),
+                ("M-<Space>".into(), "sendMessage NextLayout".into()),
+            ]
+        );
+        assert_eq!(
+            keybinds(true),
+            vec![
+                ("M-<Return>".into(), "Terminal".into()),
+                ("M-q".into(), "kill".into()),
+                ("M-S-q".into(), "io exitSuccess".into()),
+                ("M-<Space>".into(), "sendMessage NextLayout".into()),
+            ]
+        );
+        assert_eq!(
+            action_description("spawn \"rofi -show run\""),
+            "spawn rofi -show run"
+        );
+        assert_eq!(
+            action_description("submap . mkKeymap c $\n  []"),
+            "submap . mkKeymap c"
+        );
+    }
+
+    #[test]
+    fn ignore_markers() {
+        let content = r#"-- # Title
+-- ## Apps
+-- Terminal
+, ("M-<Return>", spawn "alacritty")
+-- ! "M-x" Fake keybind
+-- ! Browser
+, ("M-b", spawn "firefox")
+-- !
+, ("M-S-q", io exitSuccess)
+-- ! ## Debug
+-- Reload
+, ("M-r", refresh)
+-- "M-d" Debug
+-- ## Layouts
+-- Next layout
+, ("M-<Space>", sendMessage NextLayout)
+-- #
+"#;
+        let tokens = Parser::new(content, Haddock::default(), false)
+            .tokens()
+            .unwrap();
+        let keys = |keybinds: &[crate::token::Keybind]| {
+            keybinds.iter().map(|k| k.keys.clone()).collect::<Vec<_>>()
+        };
+        let titles: Vec<_> = tokens.sections.iter().map(|s| s.title.clone()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                Some("Apps".into()),
+                Some("Debug".into()),
+                Some("Layouts".into())
+            ]
+        );
+        assert_eq!(keys(&tokens.sections[0].keybinds), vec!["M-<Return>"]);
+        assert!(tokens.sections[1].keybinds.is_empty());
+        assert_eq!(
+            keys(&tokens.ignored),
+            vec!["M-x", "M-b", "M-S-q", "M-r", "M-d"]
+        );
+        assert!(tokens.ignored.iter().all(|k| k.ignored));
+        assert_eq!(tokens.ignored[0].description, "Fake keybind");
+    }
+
+    #[test]
+    fn layout_annotations() {
+        assert_eq!(
+            parse_layout_tag("-- ##layout Tall, Full\n"),
+            Ok(("", vec!["Tall", "Full"]))
+        );
+        assert_eq!(parse_layout_tag("-- ##layout *\n"), Ok(("", vec![])));
+        assert!(parse_section_tag("-- ##layout Tall\n").is_err());
+
+        let content = r#"-- # Title
+-- ## Windows
+-- Close
+, ("M-S-c", kill)
+-- ##layout Tall, Mirror Tall
+-- Shrink
+, ("M-h", sendMessage Shrink)
+-- "M-l" Expand
+-- ##layout *
+-- Focus down
+, ("M-j", windows W.focusDown)
+-- ##layout Full
+-- ## Apps
+-- Terminal
+, ("M-<Return>", spawn "alacritty")
+-- #
+"#;
+        let tokens = Parser::new(content, Haddock::default(), false)
+            .tokens()
+            .unwrap();
+        let layouts: Vec<_> = tokens
+            .keybinds()
+            .into_iter()
+            .map(|k| (k.keys, k.layouts))
+            .collect();
+        let tall = vec!["Tall".to_owned(), "Mirror Tall".to_owned()];
+        assert_eq!(
+            layouts,
+            vec![
+                ("M-S-c".to_owned(), vec![]),
+                ("M-h".to_owned(), tall.clone()),
+                ("M-l".to_owned(), tall),
+                ("M-j".to_owned(), vec![]),
+                ("M-<Return>".to_owned(), vec![]),
+            ]
+        );
+        assert_eq!(tokens.layouts(), vec!["Mirror Tall", "Tall"]);
+    }
+
+    #[test]
+    fn keymap_reading() {
+        // fails when read past the keymap
+        struct Unreadable;
+        impl io::Read for Unreadable {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("read past the keymap"))
+            }
+        }
+        let content = format!(
+            "{}-- # Title\n-- ## Apps\n-- Browser\n, (\"M-f\", spawn \"firefox\")\n-- #\n",
+            "import XMonad\n".repeat(1000)
+        );
+        let reader = io::BufReader::new(io::Read::chain(content.as_bytes(), Unreadable));
+        let parser = Parser::read(reader, Haddock::default(), false).unwrap();
+        assert_eq!(parser.content.lines().count(), 5);
+        let keybind = &parser.tokens().unwrap().sections[0].keybinds[0];
+        assert_eq!(keybind.line, Some(1004));
+
+        let invalid = Parser::read(&b"-- # Title\n-- \xff\n"[..], Haddock::default(), false);
+        assert_eq!(invalid.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn unclosed_keymap() {
+        let parser = Parser::new(
+            "main = xmonad\n-- # Title\n-- ## Section\n-- Foo\n",
+            Haddock::default(),
+            false,
+        );
+        assert_eq!(parser.unclosed_keymap(), Some(2));
+        let tokens = parser.tokens().unwrap();
+        assert_eq!(tokens.title.as_deref(), Some("Title"));
+        assert_eq!(tokens.section_count(), 1);
+        assert_eq!(
+            tokens.warnings,
+            vec![
+                "the keymap opened at line 2 has no closing `-- #` boundary, \
+                 parsed up to the end of the file"
+            ]
+        );
+
+        // the last keybind, without line ending, is kept
+        let content =
+            "-- # Title\n-- ## Apps\n-- Terminal\n, (\"M-<Return>\", spawn \"alacritty\")\n\
+                       -- ## Windows\ngarbage\n-- Close\n, (\"M-S-c\", kill)";
+        for content in [content.to_owned(), format!("{content}\n  ]\n")] {
+            let tokens = Parser::new(&content, Haddock::default(), false)
+                .tokens()
+                .unwrap();
+            let keys: Vec<_> = tokens.keybinds().into_iter().map(|k| k.keys).collect();
+            assert_eq!(keys, vec!["M-<Return>", "M-S-c"]);
+            assert_eq!(tokens.warnings.len(), 1);
+        }
+
+        let closed = Parser::new("-- # Title\n-- ## Apps\n-- #", Haddock::default(), false);
+        assert_eq!(closed.unclosed_keymap(), None);
+        assert!(closed.tokens().unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn keybind_source_lines() {
+        let parser = Parser::new(
+            r#"-- # Keymap
+-- ## Apps
+    -- browser
+    , ("M-f", spawn "firefox")
+    -- ratio
+    , ("M-r",
+        sendMessage Shrink)
+    -- "M-x" comment only
+    -- workspaces
+    , [("M-" ++ show n, windows $ W.greedyView n) | n <- [1..9]]
+-- # end
+"#,
+            Haddock::default(),
+            false,
+        );
+        let (_, sections) = parser.sections().unwrap();
+        let sources: Vec<_> = sections[0]
+            .keybinds
+            .iter()
+            .map(|k| parser.source_lines(k))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                Some(r#"    , ("M-f", spawn "firefox")"#),
+                Some("    , (\"M-r\",\n        sendMessage Shrink)"),
+                None,
+                Some(r#"    , [("M-" ++ show n, windows $ W.greedyView n) | n <- [1..9]]"#),
+            ]
+        );
+        let lines: Vec<_> = parser
+            .tokens()
+            .unwrap()
+            .keybinds()
+            .into_iter()
+            .map(|k| k.line)
+            .collect();
+        // the generated keys point to their description
+        assert_eq!(lines, vec![Some(4), Some(6), Some(8), Some(9)]);
+    }
+
+    #[test]
+    fn format_checking() {
+        let check_format = |content| Parser::new(content, Haddock::default(), false).check_format();
+        assert!(check_format("import XMonad\n-- # Keymap\n-- #\n").is_ok());
+        assert!(check_format("\x7fELF\0\0").is_err());
+        let err = check_format("import XMonad\n\nmain = xmonad def\n-- ## Apps\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("No `-- #` boundary found"));
+        assert!(err.ends_with("import XMonad\nmain = xmonad def\n-- ## Apps"));
+    }
+
+    #[test]
+    fn parse_fixtures() {
+        let tokens = Parser::new(
+            include_str!("../fixtures/basic.hs"),
+            Haddock::default(),
+            false,
+        )
+        .tokens()
+        .unwrap();
+        assert_eq!(tokens.title.as_deref(), Some("XMonad keymap"));
+        assert_eq!(tokens.section_count(), 3);
+        assert_eq!(tokens.keybind_count(), 13);
+    }
+
+    #[test]
+    fn parse_where_fixture() {
+        let tokens = Parser::new(
+            include_str!("../fixtures/where.hs"),
+            Haddock::default(),
+            false,
+        )
+        .tokens()
+        .unwrap();
+        assert_eq!(tokens.title.as_deref(), Some("Keymap in a where clause"));
+        let sections: Vec<_> = tokens
+            .sections
+            .iter()
+            .map(|s| (s.title.as_deref(), s.description.as_deref()))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                (Some("Apps"), Some("Launched with spawn")),
+                (Some("Windows"), None)
+            ]
+        );
+        let keybinds: Vec<_> = tokens
+            .sections
+            .iter()
+            .flat_map(|s| &s.keybinds)
+            .map(|k| (k.keys.as_str(), k.description.as_str()))
+            .collect();
+        assert_eq!(
+            keybinds,
+            vec![
+                ("M-<Return>", "Terminal"),
+                ("M-b", "Browser"),
+                ("M-p", "Launcher, bound elsewhere"),
+                ("M-q", "Kill the focused window"),
+            ]
+        );
+        assert_eq!(tokens.ignored[0].description, "Debug");
+    }
 }
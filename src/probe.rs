@@ -0,0 +1,153 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// What is really bound system-wide: the keys grabbed on the X11 root
+// window against the documented keymap, whatever the window manager.
+// Only the first chord of a sequence is grabbed, and the keybinds of a
+// submap are grabbed once it is entered, they are not probed.
+
+use eyre::Result;
+use std::collections::HashSet;
+use tracing::{debug, instrument, trace};
+
+use crate::{
+    app::AppConfig,
+    export::read_tokens,
+    hotkey::{self, Hotkey},
+    token::Tokens,
+};
+
+/// Differences between the documented and the grabbed keys
+#[derive(Debug, Default, PartialEq)]
+pub struct Probe {
+    /// Keys and description of the keybinds nobody grabbed
+    pub not_grabbed: Vec<(String, String)>,
+    /// Grabbed keys missing from the keymap
+    pub not_documented: Vec<Hotkey>,
+    /// Keybinds whose key apekey can't grab, e.g. the XF86 media keys
+    pub skipped: usize,
+}
+
+/// Probes the grabbed keys and renders the differences with the keymap
+#[instrument(skip(config))]
+pub fn run(config: &AppConfig) -> Result<String> {
+    let tokens = read_tokens(config)?;
+    let hotkeys: Vec<Hotkey> = documented(&tokens)
+        .into_iter()
+        .filter_map(|(hotkey, ..)| hotkey)
+        .collect();
+    let grabbed = hotkey::grabbed_keys(&hotkeys)?;
+    debug!("{} grabbed keys", grabbed.len());
+    Ok(render(&compare(&tokens, &grabbed)))
+}
+
+// first chord of each keybind, `None` when it can't be grabbed
+fn documented(tokens: &Tokens) -> Vec<(Option<Hotkey>, &str, &str)> {
+    tokens
+        .sections
+        .iter()
+        .flat_map(|s| &s.keybinds)
+        .filter(|k| !k.submap)
+        .map(|k| {
+            let chord = k.keys.split_whitespace().next().unwrap_or_default();
+            let hotkey = Hotkey::parse(chord)
+                .map_err(|e| trace!("`{}` not probed: {e}", k.keys))
+                .ok();
+            (hotkey, k.keys.as_str(), k.description.as_str())
+        })
+        .collect()
+}
+
+fn compare(tokens: &Tokens, grabbed: &HashSet<Hotkey>) -> Probe {
+    let mut probe = Probe::default();
+    let mut seen = HashSet::new();
+    for (hotkey, keys, description) in documented(tokens) {
+        let Some(hotkey) = hotkey else {
+            probe.skipped += 1;
+            continue;
+        };
+        if !grabbed.contains(&hotkey) {
+            probe
+                .not_grabbed
+                .push((keys.to_owned(), description.to_owned()));
+        }
+        seen.insert(hotkey);
+    }
+    probe.not_documented = grabbed.difference(&seen).cloned().collect();
+    probe
+        .not_documented
+        .sort_by_key(|hotkey| hotkey.to_string());
+    probe
+}
+
+fn render(probe: &Probe) -> String {
+    let mut lines = vec![];
+    if !probe.not_grabbed.is_empty() {
+        lines.push("Documented but not grabbed:".to_owned());
+        lines.extend(
+            probe
+                .not_grabbed
+                .iter()
+                .map(|(keys, description)| format!("  {keys:<16} {description}")),
+        );
+    }
+    if !probe.not_documented.is_empty() {
+        lines.push("Grabbed but not documented:".to_owned());
+        lines.extend(
+            probe
+                .not_documented
+                .iter()
+                .map(|hotkey| format!("  {hotkey}")),
+        );
+    }
+    if lines.is_empty() {
+        lines.push("The grabbed keys match the keymap".to_owned());
+    }
+    if probe.skipped > 0 {
+        lines.push(format!("{} keybinds not probed", probe.skipped));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{Keybind, Section};
+
+    #[test]
+    fn grabbed_keys_comparison() {
+        let tokens = Tokens {
+            sections: vec![Section {
+                title: None,
+                description: None,
+                keybinds: vec![
+                    Keybind::new("M-p", "Launcher", None),
+                    Keybind::new("M-S-<Return>", "Terminal", None),
+                    Keybind::new("M-x a", "Sequence", None),
+                    Keybind::new("<XF86AudioMute>", "Mute", None),
+                ],
+            }],
+            ..Default::default()
+        };
+        let grabbed = ["S-M-<Return>", "M-x", "M1-<F4>"]
+            .into_iter()
+            .map(|keys| Hotkey::parse(keys).unwrap())
+            .collect();
+        let probe = compare(&tokens, &grabbed);
+        assert_eq!(
+            probe,
+            Probe {
+                not_grabbed: vec![("M-p".into(), "Launcher".into())],
+                not_documented: vec![Hotkey::parse("M1-<F4>").unwrap()],
+                skipped: 1,
+            }
+        );
+        assert_eq!(
+            render(&probe),
+            "Documented but not grabbed:\n  M-p              Launcher\n\
+             Grabbed but not documented:\n  M1-<F4>\n\
+             1 keybinds not probed"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// What the window shows, decided from the state of the app apart from
+// the widgets drawing it. The views are tested on it without window:
+// the snapshot tests compare its text outline with the files of
+// `snapshots/`, and the text the widgets draw with the `.widgets.txt`
+// ones. `UPDATE_SNAPSHOTS=1 cargo test` writes them again.
+
+use std::{borrow::Cow, collections::HashSet, fmt};
+
+use crate::{
+    compare::ComparedKeys,
+    token::{ScoredKeybind, Section, Tokens},
+    view_model::ViewModel,
+};
+
+// width of the keys in the outline
+const KEYS_WIDTH: usize = 20;
+
+pub enum Screen<'a> {
+    /// A step of the loading
+    Progress(&'static str),
+    Error(&'a str),
+    /// One section at a time, with large fonts
+    Presenting {
+        keymap: Cow<'a, Tokens>,
+        section: usize,
+    },
+    /// The title, the search bar and the open panels over the listing
+    Keymap {
+        title: String,
+        search: &'a ViewModel,
+        panels: Vec<Panel<'a>>,
+        listing: Listing<'a>,
+    },
+    /// A filter line over the listing, see `--minimal`
+    Minimal {
+        query: &'a str,
+        panels: Vec<Panel<'a>>,
+        listing: Listing<'a>,
+    },
+}
+
+/// Panels stacked under the search bar
+pub enum Panel<'a> {
+    ChordPicker,
+    About,
+    Diagnostics(Vec<String>),
+    // command waiting for the user confirmation
+    Confirmation(&'a str),
+    Toast(&'a str),
+}
+
+/// The keybinds listed under the header
+pub enum Listing<'a> {
+    /// By section, the collapsed ones without their keybinds
+    Sections {
+        keymap: Cow<'a, Tokens>,
+        collapsed: &'a HashSet<String>,
+    },
+    /// The search matches, best first
+    Matches(Vec<ScoredKeybind>),
+    /// My keybinds and the ones of the compared keymap side by side
+    Comparison {
+        rows: Vec<ComparedKeys>,
+        title: Option<&'a str>,
+    },
+    /// Nothing matches the query
+    Empty {
+        query: &'a str,
+        suggestions: &'a [ScoredKeybind],
+    },
+}
+
+impl fmt::Display for Screen<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Screen::Progress(label) => writeln!(f, "progress: {}", label.trim_end()),
+            Screen::Error(err) => writeln!(f, "error: {err}"),
+            Screen::Presenting { keymap, section } => {
+                writeln!(f, "presenting {} / {}", section + 1, keymap.section_count())?;
+                match keymap.sections.get(*section) {
+                    Some(section) => write_section(f, section, false),
+                    None => writeln!(f, "Empty keymap"),
+                }
+            }
+            Screen::Keymap {
+                title,
+                search,
+                panels,
+                listing,
+            } => {
+                writeln!(f, "# {title}")?;
+                writeln!(
+                    f,
+                    "search: {:?} {:?} {:?}",
+                    search.input_value, search.matcher, search.search_mode
+                )?;
+                for panel in panels {
+                    write!(f, "{panel}")?;
+                }
+                writeln!(f, "---")?;
+                write!(f, "{listing}")
+            }
+            Screen::Minimal {
+                query,
+                panels,
+                listing,
+            } => {
+                writeln!(f, "filter: {query:?}")?;
+                for panel in panels {
+                    write!(f, "{panel}")?;
+                }
+                write!(f, "{listing}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Panel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Panel::ChordPicker => writeln!(f, "[chord picker]"),
+            Panel::About => writeln!(f, "[about]"),
+            Panel::Diagnostics(warnings) => warnings
+                .iter()
+                .try_for_each(|warning| writeln!(f, "[diagnostic] {warning}")),
+            Panel::Confirmation(command) => writeln!(f, "[confirm] {command}"),
+            Panel::Toast(text) => writeln!(f, "[toast] {text}"),
+        }
+    }
+}
+
+impl fmt::Display for Listing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listing::Sections { keymap, collapsed } => keymap.sections.iter().try_for_each(|s| {
+                let is_collapsed = s.title.as_ref().is_some_and(|t| collapsed.contains(t));
+                write_section(f, s, is_collapsed)
+            }),
+            Listing::Matches(matches) => matches
+                .iter()
+                .try_for_each(|k| write_keybind(f, &k.keys, &k.description, k.highlight)),
+            Listing::Comparison { rows, title } => {
+                writeln!(f, "{:KEYS_WIDTH$} Mine | {}", "", title.unwrap_or("Theirs"))?;
+                rows.iter().try_for_each(|row| {
+                    let cell = |description: &Option<String>| {
+                        description.clone().unwrap_or_else(|| "—".to_owned())
+                    };
+                    writeln!(
+                        f,
+                        "{:KEYS_WIDTH$} {} | {}",
+                        row.keys,
+                        cell(&row.mine),
+                        cell(&row.theirs)
+                    )
+                })
+            }
+            Listing::Empty { query, suggestions } => {
+                writeln!(f, "No bindings match '{query}'")?;
+                if !suggestions.is_empty() {
+                    writeln!(f, "Closest bindings")?;
+                }
+                suggestions
+                    .iter()
+                    .try_for_each(|k| write_keybind(f, &k.keys, &k.description, false))
+            }
+        }
+    }
+}
+
+fn write_section(f: &mut fmt::Formatter<'_>, section: &Section, collapsed: bool) -> fmt::Result {
+    let title = section.title.as_deref().unwrap_or_default();
+    if collapsed {
+        return writeln!(f, "## {title} (collapsed)");
+    }
+    writeln!(f, "## {title}")?;
+    if let Some(description) = &section.description {
+        writeln!(f, ": {description}")?;
+    }
+    section
+        .keybinds
+        .iter()
+        .try_for_each(|k| write_keybind(f, &k.keys, &k.description, k.highlight))
+}
+
+// highlighted keybinds are starred
+fn write_keybind(
+    f: &mut fmt::Formatter<'_>,
+    keys: &str,
+    description: &str,
+    highlight: bool,
+) -> fmt::Result {
+    let mark = if highlight { '*' } else { ' ' };
+    writeln!(
+        f,
+        "{mark} {keys:KEYS_WIDTH$} {}",
+        description.replace('\n', " ")
+    )
+}
+
+/// Compares the outline of a screen with `snapshots/<name>.txt`
+#[cfg(test)]
+pub fn assert_snapshot(name: &str, screen: &Screen) {
+    compare_snapshot(&format!("{name}.txt"), &screen.to_string());
+}
+
+/// Compares the text drawn by the widgets of a view, with its position in
+/// a window of `widgets::SIZE`, with `snapshots/<name>.widgets.txt`
+#[cfg(test)]
+pub fn assert_widgets_snapshot(
+    name: &str,
+    view: iced::Element<crate::app::Message>,
+    theme: &iced::Theme,
+) {
+    compare_snapshot(
+        &format!("{name}.widgets.txt"),
+        &widgets::outline(view, theme),
+    );
+}
+
+// a missing snapshot fails, so that one not committed is noticed, unless
+// `UPDATE_SNAPSHOTS` is set to write it
+#[cfg(test)]
+fn compare_snapshot(file: &str, outline: &str) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("snapshots")
+        .join(file);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, outline).unwrap();
+        return;
+    }
+    let Ok(snapshot) = std::fs::read_to_string(&path) else {
+        panic!(
+            "no snapshot {}, run the tests with UPDATE_SNAPSHOTS=1 to write it",
+            path.display()
+        );
+    };
+    let name = file.trim_end_matches(".txt");
+    assert!(
+        snapshot == outline,
+        "the {name} screen differs from {}, run the tests with \
+         UPDATE_SNAPSHOTS=1 if the change is expected\n--- snapshot\n{snapshot}\n--- screen\n{outline}",
+        path.display()
+    );
+}
+
+// Draws a view with the software renderer, without window, and lists the
+// text it draws in order
+#[cfg(test)]
+mod widgets {
+    use iced::{Element, Theme};
+    use iced_tiny_skia::{
+        core::{
+            layout::{Layout, Limits},
+            mouse, renderer,
+            text::Renderer as _,
+            widget::Tree,
+            Rectangle, Size, Vector,
+        },
+        graphics::Primitive,
+        Backend, Settings,
+    };
+    use std::{borrow::Cow, fmt::Write};
+
+    use crate::{
+        app::{Message, FONT_MONO},
+        user_config::FONT_SIZE,
+    };
+
+    pub const SIZE: Size = Size::new(1024.0, 2000.0);
+
+    pub fn outline(view: Element<Message>, theme: &Theme) -> String {
+        let mut renderer =
+            iced::Renderer::TinySkia(iced_tiny_skia::Renderer::new(Backend::new(Settings {
+                default_font: FONT_MONO,
+                default_text_size: f32::from(FONT_SIZE),
+            })));
+        // the bundled font, the positions do not depend on the fonts
+        // installed
+        renderer.load_font(Cow::Borrowed(include_bytes!(
+            "../assets/fonts/JetBrainsMono-Regular.ttf"
+        )));
+        let widget = view.as_widget();
+        let node = widget.layout(&renderer, &Limits::new(Size::ZERO, SIZE));
+        widget.draw(
+            &Tree::new(widget),
+            &mut renderer,
+            theme,
+            &renderer::Style {
+                text_color: theme.palette().text,
+            },
+            Layout::new(&node),
+            mouse::Cursor::Unavailable,
+            &Rectangle::with_size(SIZE),
+        );
+        let mut outline = String::new();
+        match &mut renderer {
+            iced::Renderer::TinySkia(renderer) => renderer.with_primitives(|_, primitives| {
+                write_texts(&mut outline, primitives, Vector::ZERO)
+            }),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("software renderer"),
+        }
+        outline
+    }
+
+    // one line per text, its position then its content
+    fn write_texts(outline: &mut String, primitives: &[iced_tiny_skia::Primitive], offset: Vector) {
+        for primitive in primitives {
+            match primitive {
+                Primitive::Text {
+                    content, bounds, ..
+                } if !content.trim().is_empty() => {
+                    writeln!(
+                        outline,
+                        "{:>4} {:>4} {}",
+                        (bounds.x + offset.x).round(),
+                        (bounds.y + offset.y).round(),
+                        content.replace('\n', " ")
+                    )
+                    .unwrap();
+                }
+                Primitive::Group { primitives } => write_texts(outline, primitives, offset),
+                Primitive::Clip { content, .. } => {
+                    write_texts(outline, std::slice::from_ref(content), offset)
+                }
+                Primitive::Translate {
+                    translation,
+                    content,
+                } => write_texts(
+                    outline,
+                    std::slice::from_ref(content),
+                    offset + *translation,
+                ),
+                Primitive::Cache { content } => {
+                    write_texts(outline, std::slice::from_ref(&**content), offset)
+                }
+                _ => {}
+            }
+        }
+    }
+}
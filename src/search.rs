@@ -0,0 +1,485 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::{
+    cmp::Ordering,
+    time::{Duration, Instant},
+};
+use tracing::{debug, instrument};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::token::{canonical_keys, ScoredKeybind};
+
+/// Keybinds matching the search, best first, and how long matching took
+pub type SearchResults = (Vec<ScoredKeybind>, Duration);
+
+static FUZZY_MATCHER: Lazy<SkimMatcherV2> = Lazy::new(SkimMatcherV2::default);
+// max number of keybinds suggested when nothing matches
+const SUGGESTIONS: usize = 3;
+
+/// How the search results are listed
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// One list, the best matches first
+    #[default]
+    Flat,
+    /// The matches in place, inside their sections
+    Sections,
+    /// The whole keymap, scrolled to the best match which flashes, `n`
+    /// and `N` go to the next and previous matches
+    Jump,
+}
+
+/// How the search pattern matches the keybinds
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Matcher {
+    /// Fuzzy matching of the keys, the description and the action
+    #[default]
+    Fuzzy,
+    /// A regular expression matched against `keys description`, e.g.
+    /// `^M-S-` lists the Super+Shift keybinds
+    Regex,
+}
+
+impl Matcher {
+    pub fn toggle(self) -> Self {
+        match self {
+            Matcher::Fuzzy => Matcher::Regex,
+            Matcher::Regex => Matcher::Fuzzy,
+        }
+    }
+}
+
+/// Factors of the scores of the matches in each field of a keybind, the
+/// keys first so that `m-s` finds `M-S-…` before a description with `ms`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct SearchWeights {
+    pub keys: f32,
+    pub description: f32,
+    pub action: f32,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        SearchWeights {
+            keys: 2.0,
+            description: 1.0,
+            action: 0.5,
+        }
+    }
+}
+
+impl SearchWeights {
+    fn lowest(&self) -> f32 {
+        self.keys.min(self.description).min(self.action)
+    }
+}
+
+/// Removes diacritics from `input`, e.g. `écran` becomes `ecran`.
+///
+/// Each char is folded into exactly one char so that the indices
+/// returned by the fuzzy matcher still point to the original string.
+pub fn fold(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| c.nfd().find(|d| !is_combining_mark(*d)).unwrap_or(c))
+        .collect()
+}
+
+/// Accent and case insensitive comparison, used to order texts in a
+/// way that matches what a human reader expects (`é` sorts with `e`).
+pub fn collate(a: &str, b: &str) -> Ordering {
+    fold(a)
+        .to_lowercase()
+        .cmp(&fold(b).to_lowercase())
+        .then_with(|| a.cmp(b))
+}
+
+/// Fuzzy matches the keys and description of the keybinds, and their
+/// action expression too with `search_actions`. Each term of the pattern
+/// must match, e.g. `media M-S` narrows to the Super+Shift media
+/// keybinds. The best field match of a term is scored by its weight, a
+/// quoted term spanning the fields, e.g. `"M-f browser"`, gets the lowest
+/// weight, the scores of the terms add up.
+#[instrument(skip_all)]
+pub async fn filter_tokens(
+    tokens: Vec<ScoredKeybind>,
+    pattern: String,
+    matcher: Matcher,
+    search_actions: bool,
+    weights: SearchWeights,
+) -> SearchResults {
+    match matcher {
+        Matcher::Fuzzy => fuzzy_filter(tokens, pattern, search_actions, weights),
+        Matcher::Regex => regex_filter(tokens, &pattern, search_actions),
+    }
+}
+
+fn fuzzy_filter(
+    mut tokens: Vec<ScoredKeybind>,
+    pattern: String,
+    search_actions: bool,
+    weights: SearchWeights,
+) -> SearchResults {
+    let start = Instant::now();
+    // `S-M-a` finds `M-S-a`
+    let terms: Vec<String> = terms(&pattern)
+        .iter()
+        .map(|term| fold(&canonical_keys(term)))
+        .collect();
+    let weighted = |text: &str, term: &str, weight: f32| {
+        FUZZY_MATCHER
+            .fuzzy(text, term, true)
+            .map(|(score, indices)| ((score as f32 * weight).round() as i64, indices))
+    };
+    for token in &mut tokens {
+        let keys = canonical_keys(&token.keys);
+        let action = token.action.as_deref().filter(|_| search_actions);
+        let mut text = format!("{keys} {}", token.description);
+        if let Some(action) = action {
+            text.push(' ');
+            text.push_str(action);
+        }
+        let fields = [
+            Some((fold(&keys), weights.keys)),
+            Some((fold(&token.description), weights.description)),
+            action.map(|action| (fold(action), weights.action)),
+        ];
+        let text = fold(&text);
+        token.score = terms
+            .iter()
+            .try_fold((0, vec![]), |(total, mut indices), term| {
+                let (score, found) = fields
+                    .iter()
+                    .flatten()
+                    .filter_map(|(field, weight)| weighted(field, term, *weight))
+                    .max_by_key(|(score, _)| *score)
+                    .or_else(|| weighted(&text, term, weights.lowest()))?;
+                indices.extend(found);
+                Some((total + score, indices))
+            });
+    }
+
+    sort_matches(tokens, start)
+}
+
+// whitespace separated terms, a quoted phrase is one term, e.g. `"volume
+// up" M-S`, an unclosed quote runs to the end while it is being typed
+fn terms(pattern: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut quoted = false;
+    for c in pattern.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            c => term.push(c),
+        }
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    terms
+}
+
+/// Builds the regex of a pattern, case insensitive unless it has an
+/// uppercase letter, like ripgrep's smart case
+pub fn build_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let pattern = fold(pattern);
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!pattern.chars().any(char::is_uppercase))
+        .build()
+}
+
+// the earlier the match the better, an invalid regex matches nothing
+fn regex_filter(
+    mut tokens: Vec<ScoredKeybind>,
+    pattern: &str,
+    search_actions: bool,
+) -> SearchResults {
+    let start = Instant::now();
+    let regex = match build_regex(pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            debug!("invalid regex {pattern:?}: {e}");
+            return (vec![], start.elapsed());
+        }
+    };
+    for token in &mut tokens {
+        let mut text = format!("{} {}", token.keys, token.description);
+        if let Some(action) = token.action.as_deref().filter(|_| search_actions) {
+            text.push(' ');
+            text.push_str(action);
+        }
+        let text = fold(&text);
+        token.score = regex.find(&text).map(|found| {
+            let first = text[..found.start()].chars().count();
+            let indices = (first..first + found.as_str().chars().count()).collect();
+            (-(first as i64), indices)
+        });
+    }
+    sort_matches(tokens, start)
+}
+
+fn sort_matches(tokens: Vec<ScoredKeybind>, start: Instant) -> SearchResults {
+    let mut filtered: Vec<ScoredKeybind> = tokens
+        .into_iter()
+        // only retains keybind tokens with a matching score
+        .filter(|token| token.score.is_some())
+        .collect();
+
+    // sort by score, ties are ordered by description
+    filtered.sort_by(|a, b| {
+        b.score
+            .as_ref()
+            .unwrap()
+            .0
+            .cmp(&a.score.as_ref().unwrap().0)
+            .then_with(|| collate(&a.description, &b.description))
+    });
+    let elapsed = start.elapsed();
+    debug!("{} matches in {elapsed:?}", filtered.len());
+    (filtered, elapsed)
+}
+
+/// Finds the keybinds closest to a pattern matching none of them, i.e.
+/// matching the longest start of the pattern, at least half of it
+#[instrument(skip_all)]
+pub async fn suggest(tokens: Vec<ScoredKeybind>, pattern: String) -> Vec<ScoredKeybind> {
+    let pattern: Vec<char> = fold(&pattern).chars().collect();
+    let min_len = pattern.len().div_ceil(2);
+    let mut suggestions: Vec<(usize, i64, ScoredKeybind)> = tokens
+        .into_iter()
+        .filter_map(|token| {
+            let text = fold(&token.to_string());
+            // a keybind matching a start of the pattern matches all the
+            // shorter ones, so the longest is the first found
+            (min_len.max(1)..pattern.len())
+                .rev()
+                .find_map(|len| {
+                    let prefix: String = pattern[..len].iter().collect();
+                    FUZZY_MATCHER
+                        .fuzzy(&text, &prefix, false)
+                        .map(|(score, _)| (len, score))
+                })
+                .map(|(len, score)| (len, score, token))
+        })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then_with(|| b.1.cmp(&a.1))
+            .then_with(|| collate(&a.2.description, &b.2.description))
+    });
+    suggestions
+        .into_iter()
+        .take(SUGGESTIONS)
+        .map(|(_, _, token)| token)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accents_folding() {
+        assert_eq!(fold("écran"), "ecran");
+        assert_eq!(fold("Fenêtre suivante"), "Fenetre suivante");
+        assert_eq!(fold("M-S-<Ç>"), "M-S-<C>");
+        assert_eq!(fold("écran").chars().count(), "écran".chars().count());
+    }
+
+    #[test]
+    fn accents_insensitive_collation() {
+        assert_eq!(collate("écran", "Ecran 2"), Ordering::Less);
+        assert_eq!(collate("été", "etre"), Ordering::Less);
+        assert_eq!(collate("Zoom", "écran"), Ordering::Greater);
+    }
+
+    #[test]
+    fn actions_searching() {
+        let tokens = vec![
+            ScoredKeybind {
+                keys: "<Print>".into(),
+                description: "screenshot".into(),
+                action: Some(r#"spawn "flameshot gui""#.into()),
+                ..Default::default()
+            },
+            ScoredKeybind {
+                keys: "M-f".into(),
+                description: "browser".into(),
+                ..Default::default()
+            },
+        ];
+        let search = |search_actions| {
+            iced::futures::executor::block_on(filter_tokens(
+                tokens.clone(),
+                "flameshot".into(),
+                Matcher::Fuzzy,
+                search_actions,
+                SearchWeights::default(),
+            ))
+            .0
+            .into_iter()
+            .map(|k| k.keys)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(search(true), vec!["<Print>"]);
+        assert!(search(false).is_empty());
+    }
+
+    #[test]
+    fn weighted_scoring() {
+        let tokens = vec![
+            ScoredKeybind {
+                keys: "M-g".into(),
+                description: "Play the ms-dos game".into(),
+                ..Default::default()
+            },
+            ScoredKeybind {
+                keys: "M-S-q".into(),
+                description: "Quit".into(),
+                ..Default::default()
+            },
+            ScoredKeybind {
+                keys: "M-f".into(),
+                description: "Web".into(),
+                ..Default::default()
+            },
+        ];
+        let search = |pattern: &str, weights| {
+            iced::futures::executor::block_on(filter_tokens(
+                tokens.clone(),
+                pattern.into(),
+                Matcher::Fuzzy,
+                false,
+                weights,
+            ))
+            .0
+            .into_iter()
+            .map(|k| k.keys)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            search("m-s", SearchWeights::default()),
+            vec!["M-S-q", "M-g"]
+        );
+        let description_first = SearchWeights {
+            keys: 1.0,
+            description: 3.0,
+            action: 1.0,
+        };
+        assert_eq!(search("m-s", description_first), vec!["M-g", "M-S-q"]);
+        // across the keys and the description
+        assert_eq!(search("m-f we", SearchWeights::default()), vec!["M-f"]);
+        assert_eq!(search("\"m-f we\"", SearchWeights::default()), vec!["M-f"]);
+    }
+
+    #[test]
+    fn terms_narrowing() {
+        let keybind = |keys: &str, description: &str| ScoredKeybind {
+            keys: keys.into(),
+            description: description.into(),
+            ..Default::default()
+        };
+        let tokens = vec![
+            keybind("M-S-<XF86AudioPlay>", "Play media in the background"),
+            keybind("M-<XF86AudioPlay>", "Play media"),
+            keybind("M-S-v", "Volume up"),
+            keybind("M-<XF86AudioNext>", "Next media"),
+        ];
+        let search = |pattern: &str| {
+            iced::futures::executor::block_on(filter_tokens(
+                tokens.clone(),
+                pattern.into(),
+                Matcher::Fuzzy,
+                false,
+                SearchWeights::default(),
+            ))
+            .0
+            .into_iter()
+            .map(|k| k.keys)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(search("media M-S"), vec!["M-S-<XF86AudioPlay>"]);
+        assert_eq!(search("  M-S   media "), vec!["M-S-<XF86AudioPlay>"]);
+        assert_eq!(search("next media").len(), 1);
+        assert!(search("media volume").is_empty());
+        assert_eq!(
+            terms(r#"media "in the" M-S"#),
+            vec!["media", "in the", "M-S"]
+        );
+        assert_eq!(terms(r#"M-S "volume u"#), vec!["M-S", "volume u"]);
+        assert!(terms("  ").is_empty());
+    }
+
+    #[test]
+    fn regex_matching() {
+        let keybind = |keys: &str, description: &str| ScoredKeybind {
+            keys: keys.into(),
+            description: description.into(),
+            ..Default::default()
+        };
+        let tokens = vec![
+            keybind("M-S-c", "Close the window"),
+            keybind("M-s", "Screenshot"),
+            keybind("M-S-<Return>", "Terminal"),
+            keybind("M-<Tab>", "Écran suivant"),
+        ];
+        let search = |pattern: &str| {
+            iced::futures::executor::block_on(filter_tokens(
+                tokens.clone(),
+                pattern.into(),
+                Matcher::Regex,
+                false,
+                SearchWeights::default(),
+            ))
+            .0
+            .into_iter()
+            .map(|k| k.keys)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(search("^M-S-"), vec!["M-S-c", "M-S-<Return>"]);
+        // smart case
+        assert_eq!(search("^m-s"), vec!["M-S-c", "M-s", "M-S-<Return>"]);
+        // the earliest match first
+        assert_eq!(search("c"), vec!["M-S-c", "M-s", "M-<Tab>"]);
+        assert_eq!(search("ecran"), vec!["M-<Tab>"]);
+        assert!(search("(").is_empty());
+        assert!(build_regex("(").is_err());
+    }
+
+    #[test]
+    fn closest_suggestions() {
+        let keybind = |keys: &str, description: &str| ScoredKeybind {
+            keys: keys.into(),
+            description: description.into(),
+            ..Default::default()
+        };
+        let tokens = vec![
+            keybind("M-f", "firefox"),
+            keybind("M-v", "volume up"),
+            keybind("M-S-v", "volume down"),
+            keybind("M-t", "terminal"),
+        ];
+        let suggestions: Vec<_> =
+            iced::futures::executor::block_on(suggest(tokens.clone(), "volumx".into()))
+                .into_iter()
+                .map(|k| k.description)
+                .collect();
+        assert_eq!(suggestions, vec!["volume down", "volume up"]);
+        assert!(iced::futures::executor::block_on(suggest(tokens, "zzzz".into())).is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Extra sections generated by external commands, e.g. a script dumping
+// the current dynamic workspaces, read from the terminal configs or from
+// other annotated Haskell files, e.g. the modules of `lib/`. The sources
+// run concurrently, each in its own task.
+
+use eyre::{eyre, Result};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::{fs, process::Command, time};
+use tracing::{instrument, trace, warn};
+
+use crate::{
+    cache::ParseCache,
+    parser::{Haddock, Parser},
+    terminal,
+    token::{Keybind, Section},
+    user_config::deserialize_optional_path,
+};
+
+// a source must not block the rendering of the keymap
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceType {
+    /// Runs a shell command, its output is parsed by `parse_output`
+    Command,
+    /// The `map` lines of kitty.conf
+    Kitty,
+    /// The key bindings of alacritty.toml
+    Alacritty,
+    /// The `keys` of wezterm.lua
+    Wezterm,
+    /// An annotated Haskell file, its sections are added to the keymap
+    Xmonad,
+}
+
+impl SourceType {
+    fn default_title(self) -> Option<String> {
+        match self {
+            SourceType::Command | SourceType::Xmonad => None,
+            SourceType::Kitty => Some("kitty".to_owned()),
+            SourceType::Alacritty => Some("Alacritty".to_owned()),
+            SourceType::Wezterm => Some("WezTerm".to_owned()),
+        }
+    }
+
+    /// What the source reads, shown with its file
+    pub fn format(self) -> &'static str {
+        match self {
+            SourceType::Command => "command output",
+            SourceType::Kitty => "kitty.conf",
+            SourceType::Alacritty => "alacritty.toml",
+            SourceType::Wezterm => "wezterm.lua",
+            SourceType::Xmonad => "Haskell annotations",
+        }
+    }
+
+    // usual location of the terminal config, relative to the config dir
+    fn default_path(self) -> Option<&'static str> {
+        match self {
+            SourceType::Command | SourceType::Xmonad => None,
+            SourceType::Kitty => Some("kitty/kitty.conf"),
+            SourceType::Alacritty => Some("alacritty/alacritty.toml"),
+            SourceType::Wezterm => Some("wezterm/wezterm.lua"),
+        }
+    }
+}
+
+/// A `[[sources]]` entry of apekey.toml
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SourceConfig {
+    #[serde(rename = "type")]
+    pub kind: SourceType,
+    pub title: Option<String>,
+    // shell command of the `command` type
+    pub command: Option<String>,
+    // config of the terminals, defaults to its usual location, or the
+    // Haskell file of the `xmonad` type
+    #[serde(default, deserialize_with = "deserialize_optional_path")]
+    pub path: Option<String>,
+}
+
+impl SourceConfig {
+    /// The file read, the terminal config at its usual location unless
+    /// set, none for a command
+    pub fn file(&self) -> Option<String> {
+        if let Some(path) = &self.path {
+            return (self.kind != SourceType::Command).then(|| path.clone());
+        }
+        let default_path = self.kind.default_path()?;
+        dirs::config_dir().map(|dir| dir.join(default_path).display().to_string())
+    }
+}
+
+/// Runs the sources concurrently, a failing one is skipped. The sections
+/// keep the order of the sources whichever finishes first. The Haskell
+/// files are parsed like the xmonad config.
+#[instrument(skip_all)]
+pub async fn sections(
+    sources: &[SourceConfig],
+    haddock: Haddock,
+    fallback_description: bool,
+    cache: &ParseCache,
+) -> Vec<Section> {
+    let tasks: Vec<_> = sources
+        .iter()
+        .cloned()
+        .map(|source| {
+            let cache = cache.clone();
+            tokio::spawn(async move {
+                let sections = run(&source, haddock, fallback_description, &cache).await;
+                (source.kind, sections)
+            })
+        })
+        .collect();
+    let mut sections = vec![];
+    for task in tasks {
+        match task.await {
+            Ok((_, Ok(source_sections))) => sections.extend(source_sections),
+            Ok((kind, Err(e))) => warn!("{kind:?} source failed: {e}"),
+            Err(e) => warn!("source task failed: {e}"),
+        }
+    }
+    sections
+}
+
+async fn run(
+    source: &SourceConfig,
+    haddock: Haddock,
+    fallback_description: bool,
+    cache: &ParseCache,
+) -> Result<Vec<Section>> {
+    let title = source.title.clone().or_else(|| source.kind.default_title());
+    match source.kind {
+        SourceType::Command => {
+            let command = source
+                .command
+                .as_deref()
+                .ok_or_else(|| eyre!("`command` not set"))?;
+            return Ok(vec![run_command(title, command).await?]);
+        }
+        SourceType::Xmonad => {
+            let path = source
+                .path
+                .as_deref()
+                .ok_or_else(|| eyre!("`path` not set"))?;
+            return parse_haskell(title, path, haddock, fallback_description, cache).await;
+        }
+        _ => {}
+    }
+    let path = source
+        .file()
+        .ok_or_else(|| eyre!("`path` not set and the home directory unknown"))?;
+    let content = fs::read_to_string(&path)
+        .await
+        .map_err(|e| eyre!("{path}: {e}"))?;
+    let keybinds = match source.kind {
+        SourceType::Kitty => terminal::parse_kitty(&content),
+        SourceType::Alacritty => {
+            terminal::parse_alacritty(&content).map_err(|e| eyre!("{path}: {e}"))?
+        }
+        SourceType::Wezterm => terminal::parse_wezterm(&content),
+        SourceType::Command | SourceType::Xmonad => unreachable!("handled above"),
+    };
+    trace!("{} keybinds read from {path}", keybinds.len());
+    Ok(vec![Section {
+        title,
+        description: None,
+        keybinds,
+    }])
+}
+
+// the title of the source, if any, names the sections without title,
+// the file is parsed again only if it changed
+async fn parse_haskell(
+    title: Option<String>,
+    path: &str,
+    haddock: Haddock,
+    fallback_description: bool,
+    cache: &ParseCache,
+) -> Result<Vec<Section>> {
+    let content = fs::read_to_string(path)
+        .await
+        .map_err(|e| eyre!("{path}: {e}"))?;
+    let options = format!("{haddock:?} {fallback_description}");
+    let tokens = cache
+        .parse(path, &content, options, |content| {
+            Parser::new(content, haddock, fallback_description).tokens()
+        })
+        .map_err(|e| eyre!("{path}: {e}"))?;
+    trace!("{} sections parsed from {path}", tokens.sections.len());
+    Ok(tokens
+        .sections
+        .into_iter()
+        .map(|mut section| {
+            section.title = section.title.or_else(|| title.clone());
+            for keybind in &mut section.keybinds {
+                keybind.file = Some(path.to_owned());
+            }
+            section
+        })
+        .collect())
+}
+
+async fn run_command(title: Option<String>, command: &str) -> Result<Section> {
+    let output = time::timeout(
+        COMMAND_TIMEOUT,
+        Command::new("sh").arg("-c").arg(command).output(),
+    )
+    .await
+    .map_err(|_| eyre!("timed out after {}s", COMMAND_TIMEOUT.as_secs()))??;
+    if !output.status.success() {
+        return Err(eyre!("exited with {}", output.status));
+    }
+    Ok(parse_output(
+        title,
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+/// Parses the output of a source, one keybind per line with its keys
+/// and its description separated by a tab. Blank lines and `#` comments
+/// are ignored, a line without tab is a keybind without description.
+pub fn parse_output(title: Option<String>, output: &str) -> Section {
+    let keybinds = output
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .map(|line| {
+            let (keys, description) = line.split_once('\t').unwrap_or((line, ""));
+            trace!("source keybind {keys}");
+            Keybind::new(keys.trim(), description.trim(), None)
+        })
+        .collect();
+    Section {
+        title,
+        description: None,
+        keybinds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_files() {
+        let source = |kind, path: Option<&str>| SourceConfig {
+            kind,
+            title: None,
+            command: Some("echo".to_owned()),
+            path: path.map(str::to_owned),
+        };
+        assert_eq!(source(SourceType::Command, None).file(), None);
+        assert_eq!(source(SourceType::Command, Some("a.sh")).file(), None);
+        assert_eq!(
+            source(SourceType::Xmonad, Some("lib/Keys.hs")).file(),
+            Some("lib/Keys.hs".to_owned())
+        );
+        assert_eq!(source(SourceType::Xmonad, None).file(), None);
+        assert!(source(SourceType::Kitty, None)
+            .file()
+            .is_none_or(|file| file.ends_with("/kitty/kitty.conf")));
+    }
+
+    #[test]
+    fn output_parsing() {
+        let section = parse_output(
+            Some("Workspaces".to_owned()),
+            "# generated\nM-1\tweb\n\nM-2\tcode  \nM-3\n",
+        );
+        assert_eq!(section.title.as_deref(), Some("Workspaces"));
+        let keybinds: Vec<_> = section
+            .keybinds
+            .iter()
+            .map(|k| (k.keys.as_str(), k.description.as_str()))
+            .collect();
+        assert_eq!(keybinds, vec![("M-1", "web"), ("M-2", "code"), ("M-3", "")]);
+    }
+
+    #[test]
+    fn concurrent_sources_order() {
+        let command = |title: &str, command: &str| SourceConfig {
+            kind: SourceType::Command,
+            title: Some(title.to_owned()),
+            command: Some(command.to_owned()),
+            path: None,
+        };
+        let sources = vec![
+            command("Slow", "sleep 0.3; printf 'M-1\tslow'"),
+            command("Failing", "exit 1"),
+            SourceConfig {
+                kind: SourceType::Xmonad,
+                title: Some("Lib".to_owned()),
+                command: None,
+                path: Some(format!("{}/fixtures/where.hs", env!("CARGO_MANIFEST_DIR"))),
+            },
+            command("Fast", "printf 'M-2\tfast'"),
+        ];
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let sections = runtime.block_on(sections(
+            &sources,
+            Haddock::Strip,
+            false,
+            &ParseCache::default(),
+        ));
+        let titles: Vec<_> = sections.iter().map(|s| s.title.as_deref()).collect();
+        assert_eq!(titles.first(), Some(&Some("Slow")));
+        assert_eq!(titles.last(), Some(&Some("Fast")));
+        assert!(titles.len() > 2, "the Haskell sections are added");
+        assert!(!titles.contains(&Some("Failing")));
+        // the Haskell keybinds point to their file, the others to none
+        let haskell = &sections[1].keybinds[0];
+        assert!(haskell
+            .file
+            .as_deref()
+            .unwrap()
+            .ends_with("fixtures/where.hs"));
+        assert!(haskell.line.is_some());
+        assert_eq!(sections[0].keybinds[0].file, None);
+    }
+}
@@ -0,0 +1,212 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Layout of a keybind row set by `row_template`, e.g.
+// `{description:<40} {keys}` to show the description first. The
+// placeholders are `{keys}`, `{description}`, `{usage}` and `{action}`,
+// the Haskell code the keybind runs, a width in characters aligns them
+// like in Rust, left with `<` (default) or right with `>`. `{{` and `}}`
+// are literal braces.
+
+use eyre::{eyre, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Keys,
+    Description,
+    Usage,
+    Action,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Literal(String),
+    Field {
+        field: Field,
+        // alignment and width in characters
+        width: Option<(Align, usize)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowTemplate(pub Vec<Segment>);
+
+impl RowTemplate {
+    /// Parses a template, e.g. `{keys:>12}  {description}`
+    pub fn parse(template: &str) -> Result<Self> {
+        let template = RowTemplate::parse_line(template)?;
+        if !template.0.iter().any(|s| {
+            matches!(
+                s,
+                Segment::Field {
+                    field: Field::Keys,
+                    ..
+                }
+            )
+        }) {
+            return Err(eyre!("`{{keys}}` missing"));
+        }
+        Ok(template)
+    }
+
+    /// Parses a template of a line which may leave the keys out, e.g.
+    /// the one printed by `--pick`
+    pub fn parse_line(template: &str) -> Result<Self> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(eyre!("unclosed `{{{placeholder}`")),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => return Err(eyre!("unmatched `}}`, write `}}}}` for a brace")),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(RowTemplate(segments))
+    }
+
+    /// Composes a row, `value` gives the text of each field, padded to
+    /// its width, then `style` wraps the field, `None` for the literals,
+    /// e.g. in the bar markup
+    pub fn render(
+        &self,
+        value: impl Fn(Field) -> String,
+        style: impl Fn(Option<Field>, String) -> String,
+    ) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => style(None, text.clone()),
+                Segment::Field { field, width } => {
+                    let text = value(*field);
+                    let text = match width {
+                        Some((Align::Left, width)) => format!("{text:<width$}"),
+                        Some((Align::Right, width)) => format!("{text:>width$}"),
+                        None => text,
+                    };
+                    style(Some(*field), text)
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Segment> {
+    let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+    let field = match name.trim() {
+        "keys" => Field::Keys,
+        "description" => Field::Description,
+        "usage" => Field::Usage,
+        "action" => Field::Action,
+        name => {
+            return Err(eyre!(
+                "unknown placeholder `{{{name}}}`, one of keys, description, usage, action"
+            ))
+        }
+    };
+    let width = match spec.trim() {
+        "" => None,
+        spec => {
+            let (align, width) = match spec.strip_prefix('>') {
+                Some(width) => (Align::Right, width),
+                None => (Align::Left, spec.strip_prefix('<').unwrap_or(spec)),
+            };
+            let width = width
+                .parse()
+                .map_err(|_| eyre!("invalid width `{spec}` of `{{{name}}}`"))?;
+            Some((align, width))
+        }
+    };
+    Ok(Segment::Field { field, width })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_rendering() {
+        let template = RowTemplate::parse("{description:<8}| {keys:>6} {{x}}").unwrap();
+        let value = |field| match field {
+            Field::Keys => "M-p".to_owned(),
+            Field::Description => "dmenu".to_owned(),
+            Field::Usage | Field::Action => String::new(),
+        };
+        assert_eq!(
+            template.render(value, |_, text| text),
+            "dmenu   |    M-p {x}"
+        );
+        let styled = template.render(value, |field, text| match field {
+            Some(Field::Keys) => format!("<{}>", text.trim()),
+            _ => text,
+        });
+        assert_eq!(styled, "dmenu   | <M-p> {x}");
+
+        assert!(RowTemplate::parse("{description}").is_err());
+        assert!(RowTemplate::parse_line("{description}").is_ok());
+        assert!(RowTemplate::parse("{keys} {command}").is_err());
+        assert!(RowTemplate::parse("{keys:>wide}").is_err());
+        assert!(RowTemplate::parse("{keys} }").is_err());
+        assert!(RowTemplate::parse("{keys").is_err());
+    }
+
+    #[test]
+    fn line_parsing() {
+        assert_eq!(
+            RowTemplate::parse_line("{action}").unwrap(),
+            RowTemplate(vec![Segment::Field {
+                field: Field::Action,
+                width: None
+            }])
+        );
+        assert_eq!(
+            RowTemplate::parse_line("{{{description:>4}}} ").unwrap(),
+            RowTemplate(vec![
+                Segment::Literal("{".to_owned()),
+                Segment::Field {
+                    field: Field::Description,
+                    width: Some((Align::Right, 4))
+                },
+                Segment::Literal("} ".to_owned()),
+            ])
+        );
+        assert_eq!(
+            RowTemplate::parse_line("").unwrap(),
+            RowTemplate(vec![]),
+            "a line may be empty"
+        );
+        assert!(RowTemplate::parse_line("{usage").is_err());
+        assert!(RowTemplate::parse_line("{command}").is_err());
+    }
+}
@@ -0,0 +1,336 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Shortcuts of the terminal emulator configs, turned into EZConfig keys
+// so that they join the keymap as extra sections. The terminals do not
+// describe their shortcuts, the action stands for the description.
+
+use eyre::{eyre, Result};
+use tracing::trace;
+
+use crate::token::Keybind;
+
+// kitty `map` lines default modifier, overridden by a `kitty_mod` line
+const KITTY_MOD: &str = "ctrl+shift";
+
+/// Converts a modifier name of the terminals, or of xmonad, to its
+/// EZConfig prefix
+fn modifier(name: &str) -> Option<&'static str> {
+    match name.to_lowercase().as_str() {
+        "ctrl" | "control" | "c" | "⌃" => Some("C-"),
+        "shift" | "s" | "⇧" => Some("S-"),
+        "alt" | "opt" | "option" | "m1" | "mod1" | "⌥" => Some("M1-"),
+        "super" | "cmd" | "command" | "win" | "m4" | "mod4" | "⌘" => Some("M4-"),
+        "m" | "mod" => Some("M-"),
+        "m2" | "mod2" => Some("M2-"),
+        "m3" | "mod3" => Some("M3-"),
+        "m5" | "mod5" => Some("M5-"),
+        _ => None,
+    }
+}
+
+/// Converts a key name of the terminals to the EZConfig notation, e.g.
+/// `enter` gives `<Return>` and `page_up` gives `<Page_Up>`
+fn key(name: &str) -> String {
+    if name.len() > 2 && name.starts_with('<') && name.ends_with('>') {
+        return name.to_owned();
+    }
+    let lower = name.to_lowercase();
+    match lower.as_str() {
+        "enter" | "return" => return "<Return>".into(),
+        "esc" | "escape" => return "<Esc>".into(),
+        "pageup" | "page_up" => return "<Page_Up>".into(),
+        "pagedown" | "page_down" => return "<Page_Down>".into(),
+        "plus" => return "+".into(),
+        "minus" => return "-".into(),
+        "equal" => return "=".into(),
+        "comma" => return ",".into(),
+        "period" => return ".".into(),
+        "slash" => return "/".into(),
+        "backslash" => return "\\".into(),
+        _ => {}
+    }
+    if lower.chars().count() == 1 {
+        return lower;
+    }
+    // `f1`, `tab`, `home`... each word capitalized
+    let name = lower
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |c| {
+                c.to_uppercase().chain(chars).collect::<String>()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("_");
+    format!("<{name}>")
+}
+
+/// Converts a chord of modifiers and key names, e.g. `ctrl+shift+t`
+pub fn chord<'a>(parts: impl Iterator<Item = &'a str>) -> Result<String> {
+    let parts: Vec<&str> = parts.map(str::trim).filter(|p| !p.is_empty()).collect();
+    let (key_name, modifiers) = parts.split_last().ok_or_else(|| eyre!("empty shortcut"))?;
+    let mut chord = String::new();
+    for name in modifiers {
+        chord.push_str(modifier(name).ok_or_else(|| eyre!("unknown modifier `{name}`"))?);
+    }
+    chord.push_str(&key(key_name));
+    Ok(chord)
+}
+
+/// Parses the `map ctrl+shift+t new_tab` lines of a kitty.conf, `>`
+/// separating the chords of a sequence
+pub fn parse_kitty(content: &str) -> Vec<Keybind> {
+    let kitty_mod = content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("kitty_mod "))
+        .next_back()
+        .map_or(KITTY_MOD, str::trim);
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().strip_prefix("map ")?;
+            let (keys, action) = line.trim_start().split_once(char::is_whitespace)?;
+            let keys = keys.replace("kitty_mod", kitty_mod);
+            let keys = keys
+                .split('>')
+                .map(|c| chord(c.split('+')))
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| trace!("kitty shortcut `{keys}` skipped: {e}"))
+                .ok()?;
+            Some(Keybind::new(&keys.join(" "), action.trim(), None))
+        })
+        .collect()
+}
+
+/// Parses the `[[keyboard.bindings]]` of an alacritty.toml, or the
+/// `key_bindings` of the configs older than 0.13
+pub fn parse_alacritty(content: &str) -> Result<Vec<Keybind>> {
+    let config: toml::Table = toml::from_str(content)?;
+    let bindings = config
+        .get("keyboard")
+        .and_then(|keyboard| keyboard.get("bindings"))
+        .or_else(|| config.get("key_bindings"))
+        .and_then(toml::Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    Ok(bindings
+        .iter()
+        .filter_map(|binding| {
+            let field = |name| binding.get(name).and_then(toml::Value::as_str);
+            let mods = field("mods").unwrap_or_default();
+            let keys = chord(mods.split('|').chain([field("key")?]))
+                .map_err(|e| trace!("alacritty shortcut skipped: {e}"))
+                .ok()?;
+            let description = match (field("action"), field("chars"), binding.get("command")) {
+                (Some(action), ..) => action.to_owned(),
+                (_, Some(chars), _) => format!("send {chars:?}"),
+                (.., Some(toml::Value::String(program))) => format!("run {program}"),
+                (.., Some(command)) => format!(
+                    "run {}",
+                    command.get("program").and_then(toml::Value::as_str)?
+                ),
+                _ => return None,
+            };
+            Some(Keybind::new(&keys, &description, None))
+        })
+        .collect())
+}
+
+/// Parses the `{ key = 'l', mods = 'ALT', action = ... }` entries of a
+/// wezterm.lua. Lua is not evaluated, the entries must be literal tables,
+/// the `LEADER` modifier is rendered as a `<Leader>` chord.
+pub fn parse_wezterm(content: &str) -> Vec<Keybind> {
+    // without the comments, they could hold braces
+    let content: String = content
+        .lines()
+        .map(|line| line.split_once("--").map_or(line, |(code, _)| code))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut keybinds = vec![];
+    let mut rest = content.as_str();
+    while let Some(start) = rest.find('{') {
+        let Some(len) = table_len(&rest[start..]) else {
+            break;
+        };
+        let table = &rest[start + 1..start + len - 1];
+        match (lua_field(table, "key"), lua_field(table, "action")) {
+            (Some(key), Some(action)) => {
+                if let Some(keybind) = wezterm_keybind(key, lua_field(table, "mods"), action) {
+                    keybinds.push(keybind);
+                }
+                rest = &rest[start + len..];
+            }
+            // e.g. the `keys = { ... }` list, its entries are next
+            _ => rest = &rest[start + 1..],
+        }
+    }
+    keybinds
+}
+
+fn wezterm_keybind(key: &str, mods: Option<&str>, action: &str) -> Option<Keybind> {
+    let key = key.trim_matches(['\'', '"']);
+    let mods = mods.unwrap_or_default().trim_matches(['\'', '"']);
+    let leader = mods.split('|').any(|m| m.eq_ignore_ascii_case("leader"));
+    let mods = mods
+        .split('|')
+        .filter(|m| !m.eq_ignore_ascii_case("leader") && !m.eq_ignore_ascii_case("none"));
+    let keys = chord(mods.chain([key]))
+        .map_err(|e| trace!("wezterm shortcut skipped: {e}"))
+        .ok()?;
+    let keys = if leader {
+        format!("<Leader> {keys}")
+    } else {
+        keys
+    };
+    let action = action
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("wezterm.action.", "")
+        .replace("wezterm.action_callback", "callback");
+    let action = action.strip_prefix("act.").unwrap_or(&action);
+    Some(Keybind::new(&keys, action, None))
+}
+
+// length of the table starting at the beginning of the input, braces
+// included, `None` when it is not closed
+fn table_len(input: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// value of a `name = value` field at the top level of the table content
+fn lua_field<'a>(table: &'a str, name: &str) -> Option<&'a str> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut field_start = 0;
+    let mut fields = vec![];
+    for (i, c) in table.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '{' | '(') => depth += 1,
+            (None, '}' | ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                fields.push(&table[field_start..i]);
+                field_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&table[field_start..]);
+    fields.into_iter().find_map(|field| {
+        let (field_name, value) = field.split_once('=')?;
+        (field_name.trim() == name).then(|| value.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keybinds: &[Keybind]) -> Vec<(&str, &str)> {
+        keybinds
+            .iter()
+            .map(|k| (k.keys.as_str(), k.description.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn kitty_parsing() {
+        let content = "\
+# map ctrl+a commented
+kitty_mod ctrl+alt
+map kitty_mod+t new_tab
+map ctrl+shift+enter   new_window_with_cwd
+map ctrl+f>2 set_font_size 20
+map hyper+x unknown
+font_size 12
+";
+        assert_eq!(
+            keys(&parse_kitty(content)),
+            vec![
+                ("C-M1-t", "new_tab"),
+                ("C-S-<Return>", "new_window_with_cwd"),
+                ("C-f 2", "set_font_size 20"),
+            ]
+        );
+    }
+
+    #[test]
+    fn alacritty_parsing() {
+        let content = r#"
+[[keyboard.bindings]]
+key = "N"
+mods = "Control|Shift"
+action = "CreateNewWindow"
+
+[[keyboard.bindings]]
+key = "PageUp"
+mods = "Shift"
+chars = "\u001b[5;2~"
+
+[[keyboard.bindings]]
+key = "L"
+mods = "Alt"
+command = { program = "alacritty", args = ["msg", "create-window"] }
+"#;
+        assert_eq!(
+            keys(&parse_alacritty(content).unwrap()),
+            vec![
+                ("C-S-n", "CreateNewWindow"),
+                ("S-<Page_Up>", "send \"\\u{1b}[5;2~\""),
+                ("M1-l", "run alacritty"),
+            ]
+        );
+        assert!(parse_alacritty("key_bindings: [").is_err());
+    }
+
+    #[test]
+    fn wezterm_parsing() {
+        let content = r#"
+local wezterm = require 'wezterm'
+local act = wezterm.action
+return {
+  leader = { key = 'a', mods = 'CTRL' },
+  keys = {
+    -- { key = 'x', mods = 'CTRL', action = act.Nope },
+    { key = 'l', mods = 'ALT', action = wezterm.action.ShowLauncher },
+    { key = "Enter", mods = "CTRL|SHIFT", action = act.SpawnCommandInNewTab {
+        args = { 'top' },
+      },
+    },
+    { key = '|', mods = 'LEADER', action = act.SplitHorizontal },
+  },
+}
+"#;
+        assert_eq!(
+            keys(&parse_wezterm(content)),
+            vec![
+                ("M1-l", "ShowLauncher"),
+                ("C-S-<Return>", "SpawnCommandInNewTab { args = { 'top' }, }"),
+                ("<Leader> |", "SplitHorizontal"),
+            ]
+        );
+    }
+}
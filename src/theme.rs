@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use chrono::{Local, Timelike};
+use eyre::{eyre, Result};
+use iced::{application, theme::Palette, Color, Theme};
+use std::{fs, path::Path};
+use tracing::{debug, warn};
+
+use crate::user_config::{AutoTimeConfig, ColorsConfig};
+
+/// Theme picking the light theme during the day and the dark one at night
+pub const AUTO_TIME: &str = "auto-time";
+// default switch times, in minutes since midnight
+const DAY_START: u16 = 7 * 60;
+const NIGHT_START: u16 = 19 * 60;
+
+/// Names of the built-in themes, as used in the config
+pub const THEMES: [&str; 8] = [
+    "dark",
+    "light",
+    "tars",
+    "high-contrast",
+    "solarized-dark",
+    "solarized-light",
+    "deuteranopia-dark",
+    "deuteranopia-light",
+];
+
+/// Returns the built-in theme with the given name, case insensitive
+pub fn builtin(name: &str) -> Option<Theme> {
+    let theme = match name.to_lowercase().as_str() {
+        "dark" => Theme::Dark,
+        "light" => Theme::Light,
+        "tars" => Theme::custom(Palette {
+            background: Color::from_rgb8(33, 33, 33), // #212121
+            text: Color::from_rgb8(203, 205, 212),    // #CBCDD4
+            primary: Color::from_rgb8(253, 153, 53),  // #FD9935
+            success: Color::from_rgb8(188, 190, 196), // #212121
+            danger: Color::from_rgb8(248, 113, 113),  // #f87171
+        }),
+        "high-contrast" => Theme::custom(Palette {
+            background: Color::BLACK,
+            text: Color::WHITE,
+            primary: Color::from_rgb8(255, 214, 0), // #FFD600
+            success: Color::from_rgb8(0, 230, 118), // #00E676
+            danger: Color::from_rgb8(255, 82, 82),  // #FF5252
+        }),
+        "solarized-dark" => Theme::custom(Palette {
+            background: Color::from_rgb8(0, 43, 54), // #002B36
+            text: Color::from_rgb8(147, 161, 161),   // #93A1A1
+            primary: Color::from_rgb8(38, 139, 210), // #268BD2
+            success: Color::from_rgb8(133, 153, 0),  // #859900
+            danger: Color::from_rgb8(220, 50, 47),   // #DC322F
+        }),
+        "solarized-light" => Theme::custom(Palette {
+            background: Color::from_rgb8(253, 246, 227), // #FDF6E3
+            text: Color::from_rgb8(88, 110, 117),        // #586E75
+            primary: Color::from_rgb8(38, 139, 210),     // #268BD2
+            success: Color::from_rgb8(133, 153, 0),      // #859900
+            danger: Color::from_rgb8(220, 50, 47),       // #DC322F
+        }),
+        // Okabe-Ito colors, distinguishable with red-green color blindness
+        "deuteranopia-dark" => Theme::custom(Palette {
+            background: Color::from_rgb8(30, 30, 30), // #1E1E1E
+            text: Color::from_rgb8(230, 230, 230),    // #E6E6E6
+            primary: Color::from_rgb8(86, 180, 233),  // #56B4E9
+            success: Color::from_rgb8(0, 158, 115),   // #009E73
+            danger: Color::from_rgb8(230, 159, 0),    // #E69F00
+        }),
+        "deuteranopia-light" => Theme::custom(Palette {
+            background: Color::from_rgb8(250, 250, 250), // #FAFAFA
+            text: Color::from_rgb8(30, 30, 30),          // #1E1E1E
+            primary: Color::from_rgb8(0, 114, 178),      // #0072B2
+            success: Color::from_rgb8(0, 158, 115),      // #009E73
+            danger: Color::from_rgb8(213, 94, 0),        // #D55E00
+        }),
+        _ => return None,
+    };
+    Some(theme)
+}
+
+/// Switch times of the `auto-time` theme, in minutes since midnight, and
+/// the themes, with the user colors applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayNight {
+    pub day: u16,
+    pub night: u16,
+    pub light: Theme,
+    pub dark: Theme,
+}
+
+impl DayNight {
+    /// An invalid time is reported by the config linting, the default one
+    /// is used instead
+    pub fn new(config: &AutoTimeConfig, colors: Option<&ColorsConfig>) -> Self {
+        let time = |time: &Option<String>, default| {
+            time.as_deref().and_then(parse_time).unwrap_or(default)
+        };
+        let themed = |theme| match colors {
+            Some(colors) => with_colors(theme, colors),
+            None => theme,
+        };
+        DayNight {
+            day: time(&config.day, DAY_START),
+            night: time(&config.night, NIGHT_START),
+            light: themed(Theme::Light),
+            dark: themed(Theme::Dark),
+        }
+    }
+
+    /// Name and theme at the given time, the night may end after midnight
+    pub fn theme(&self, now: u16) -> (&'static str, Theme) {
+        let is_day = if self.day <= self.night {
+            (self.day..self.night).contains(&now)
+        } else {
+            !(self.night..self.day).contains(&now)
+        };
+        if is_day {
+            ("light", self.light.clone())
+        } else {
+            ("dark", self.dark.clone())
+        }
+    }
+}
+
+/// Parses a `HH:MM` time into minutes since midnight
+pub fn parse_time(time: &str) -> Option<u16> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    if minutes.len() != 2 {
+        return None;
+    }
+    let (hours, minutes) = (hours.parse::<u16>().ok()?, minutes.parse::<u16>().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Local time in minutes since midnight
+pub fn local_time() -> u16 {
+    let now = Local::now();
+    // at most 23 * 60 + 59
+    (now.hour() * 60 + now.minute()) as u16
+}
+
+/// Application style drawing the theme background with the given opacity,
+/// the window must be created transparent
+pub struct Translucent(pub f32);
+
+impl application::StyleSheet for Translucent {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> application::Appearance {
+        let palette = style.palette();
+        application::Appearance {
+            background_color: Color {
+                a: self.0,
+                ..palette.background
+            },
+            text_color: palette.text,
+        }
+    }
+}
+
+/// Overrides the palette colors of the theme with the user defined ones
+pub fn with_colors(theme: Theme, colors: &ColorsConfig) -> Theme {
+    let mut palette = theme.palette();
+    for (color, value) in [
+        (&mut palette.background, &colors.background),
+        (&mut palette.text, &colors.text),
+        (&mut palette.primary, &colors.primary),
+        (&mut palette.success, &colors.success),
+        (&mut palette.danger, &colors.danger),
+    ] {
+        if let Some(hex) = value {
+            match parse_hex_color(hex) {
+                Some(c) => *color = c,
+                None => warn!("invalid color {hex}, expected #rrggbb"),
+            }
+        }
+    }
+    Theme::custom(palette)
+}
+
+/// Reads the palette colors of a base16 scheme, YAML, or of the colors
+/// generated by pywal, JSON, e.g. `~/.cache/wal/colors.json`
+pub fn import_scheme(path: &str) -> Result<ColorsConfig> {
+    let content = fs::read_to_string(path).map_err(|e| eyre!("{path}: {e}"))?;
+    let colors = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("json") => pywal_colors(&content),
+        _ => base16_colors(&content),
+    };
+    if colors.background.is_none() && colors.text.is_none() {
+        return Err(eyre!("{path}: no base16 or pywal colors found"));
+    }
+    debug!("colors imported from {path}: {colors:?}");
+    Ok(colors)
+}
+
+// base16 `baseXX: "rrggbb"` lines, at any depth, e.g. under `palette:`
+// in the tinted-theming schemes. The background and text are the
+// default ones, the primary the functions color (blue), the success
+// the strings color (green) and the danger the variables color (red).
+fn base16_colors(content: &str) -> ColorsConfig {
+    let color = |name: &str| {
+        content.lines().find_map(|line| {
+            let value = line.trim().strip_prefix(name)?.strip_prefix(':')?.trim();
+            // quoted or up to a comment
+            let value = match value.strip_prefix(['"', '\'']) {
+                Some(quoted) => quoted.split(['"', '\'']).next()?,
+                None => value.split_whitespace().next()?,
+            };
+            let hex = value.strip_prefix('#').unwrap_or(value);
+            parse_hex_color(hex).map(|_| format!("#{}", hex.to_lowercase()))
+        })
+    };
+    ColorsConfig {
+        background: color("base00"),
+        text: color("base05"),
+        primary: color("base0D"),
+        success: color("base0B"),
+        danger: color("base08"),
+    }
+}
+
+// pywal `"name": "#rrggbb"` pairs, the special background and
+// foreground, then the blue, green and red of the terminal colors
+fn pywal_colors(content: &str) -> ColorsConfig {
+    let color = |name: &str| {
+        let key = format!("\"{name}\"");
+        let (_, rest) = content.split_once(&key)?;
+        let value = rest.trim_start().strip_prefix(':')?.trim_start();
+        let hex = value.strip_prefix('"')?.split('"').next()?;
+        parse_hex_color(hex).map(|_| hex.to_lowercase())
+    };
+    ColorsConfig {
+        background: color("background"),
+        text: color("foreground"),
+        primary: color("color4"),
+        success: color("color2"),
+        danger: color("color1"),
+    }
+}
+
+/// Parses a `#rrggbb` hex string into a color
+pub fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_themes() {
+        assert!(THEMES.iter().all(|name| builtin(name).is_some()));
+        assert!(builtin("Dark").is_some());
+        assert!(builtin("High-Contrast").is_some());
+        assert!(builtin("nope").is_none());
+    }
+
+    #[test]
+    fn day_and_night() {
+        assert_eq!(parse_time("07:30"), Some(450));
+        assert_eq!(parse_time("7:05\n"), Some(425));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("12:5"), None);
+        let config = AutoTimeConfig {
+            day: Some("06:00".into()),
+            night: None,
+        };
+        let day_night = DayNight::new(&config, None);
+        assert_eq!(day_night.theme(6 * 60).0, "light");
+        assert_eq!(day_night.theme(19 * 60).0, "dark");
+        assert_eq!(day_night.theme(0).0, "dark");
+        // a day shift, sleeping until the afternoon
+        let config = AutoTimeConfig {
+            day: Some("22:00".into()),
+            night: Some("14:00".into()),
+        };
+        let day_night = DayNight::new(&config, None);
+        assert_eq!(day_night.theme(23 * 60).0, "light");
+        assert_eq!(day_night.theme(60).0, "light");
+        assert_eq!(day_night.theme(15 * 60).0, "dark");
+    }
+
+    #[test]
+    fn scheme_import() {
+        let base16 = base16_colors(
+            "scheme: \"Tomorrow Night\"\nbase00: \"1D1F21\"\nbase05: \"c5c8c6\" # text\n\
+             base08: \"cc6666\"\nbase0B: \"b5bd68\"\n",
+        );
+        assert_eq!(base16.background.as_deref(), Some("#1d1f21"));
+        assert_eq!(base16.text.as_deref(), Some("#c5c8c6"));
+        assert_eq!(base16.primary, None);
+        assert_eq!(base16.success.as_deref(), Some("#b5bd68"));
+        let tinted = base16_colors("palette:\n  base00: \"#181818\"\n  base0D: '#7cafc2'\n");
+        assert_eq!(tinted.background.as_deref(), Some("#181818"));
+        assert_eq!(tinted.primary.as_deref(), Some("#7cafc2"));
+
+        let pywal = pywal_colors(
+            r##"{"special": {"background": "#0F1419", "foreground": "#c3c2bf"},
+             "colors": {"color1": "#AB4642", "color2": "#a1b56c", "color4": "#7cafc2"}}"##,
+        );
+        assert_eq!(pywal.background.as_deref(), Some("#0f1419"));
+        assert_eq!(pywal.text.as_deref(), Some("#c3c2bf"));
+        assert_eq!(pywal.primary.as_deref(), Some("#7cafc2"));
+        assert_eq!(pywal.success.as_deref(), Some("#a1b56c"));
+        assert_eq!(pywal.danger.as_deref(), Some("#ab4642"));
+    }
+
+    #[test]
+    fn hex_color_parsing() {
+        assert_eq!(
+            parse_hex_color("#61afef"),
+            Some(Color::from_rgb8(97, 175, 239))
+        );
+        assert_eq!(
+            parse_hex_color("61AFEF"),
+            Some(Color::from_rgb8(97, 175, 239))
+        );
+        assert_eq!(parse_hex_color("#61afe"), None);
+        assert_eq!(parse_hex_color("#61afeg"), None);
+        assert_eq!(parse_hex_color("#éafef"), None);
+    }
+}
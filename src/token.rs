@@ -2,25 +2,162 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::fmt;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
 
 use iced::{
-    alignment::Vertical,
     theme::Palette,
-    widget::{column, Row, Text},
-    Alignment, Element, Length, Padding,
+    widget::{column, row},
+    Element, Length,
 };
 use tracing::{instrument, trace};
 
 use crate::{
-    app::{AppConfig, Message},
-    parser::Section as ParsedSection,
+    app::{AppConfig, LayoutFilter, Message},
+    layout::Layout,
+    parser::{description_text, hs_string, unescape, Section as ParsedSection},
+    search::collate,
+    ui,
+    usage::Usage,
 };
 
+/// The kind of action a keybind triggers, guessed from its Haskell expression
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ActionKind {
+    Spawn,
+    Window,
+    Layout,
+    Workspace,
+    Screen,
+    Xmonad,
+    #[default]
+    Other,
+}
+
+impl ActionKind {
+    /// Classifies an action by looking at the functions it calls, the
+    /// first matching rule wins
+    pub fn classify(action: &str) -> Self {
+        const RULES: [(ActionKind, &[&str]); 6] = [
+            (
+                ActionKind::Spawn,
+                &["spawn", "safeSpawn", "unsafeSpawn", "runInTerm", "spawnOn"],
+            ),
+            (
+                ActionKind::Workspace,
+                &[
+                    "greedyView",
+                    "view",
+                    "shift",
+                    "toggleWS",
+                    "toggleRecentWS",
+                    "nextWS",
+                    "prevWS",
+                    "moveTo",
+                    "shiftTo",
+                    "addWorkspace",
+                    "removeWorkspace",
+                    "switchTopic",
+                    "chooseAction",
+                ],
+            ),
+            (
+                ActionKind::Screen,
+                &[
+                    "nextScreen",
+                    "prevScreen",
+                    "swapNextScreen",
+                    "swapPrevScreen",
+                    "shiftNextScreen",
+                    "shiftPrevScreen",
+                    "viewScreen",
+                    "sendToScreen",
+                ],
+            ),
+            (
+                ActionKind::Layout,
+                &["sendMessage", "setLayout", "NextLayout"],
+            ),
+            (
+                ActionKind::Window,
+                &[
+                    "kill",
+                    "kill1",
+                    "windows",
+                    "withFocused",
+                    "focusUp",
+                    "focusDown",
+                    "focusMaster",
+                    "swapUp",
+                    "swapDown",
+                    "swapMaster",
+                    "float",
+                    "sink",
+                    "windowGo",
+                    "windowSwap",
+                ],
+            ),
+            (
+                ActionKind::Xmonad,
+                &["refresh", "restart", "exitSuccess", "exitWith", "recompile"],
+            ),
+        ];
+        let words: Vec<&str> = action
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+            .filter(|w| !w.is_empty())
+            .collect();
+        RULES
+            .iter()
+            .find(|(_, functions)| functions.iter().any(|f| words.contains(f)))
+            .map(|(kind, _)| *kind)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ActionKind::Spawn => "spawn",
+            ActionKind::Window => "window",
+            ActionKind::Layout => "layout",
+            ActionKind::Workspace => "workspace",
+            ActionKind::Screen => "screen",
+            ActionKind::Xmonad => "xmonad",
+            ActionKind::Other => "other",
+        };
+        write!(f, "{kind}")
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Keybind {
     pub keys: String,
     pub description: String,
+    pub kind: ActionKind,
+    // Haskell expression of the action, when declared inline
+    pub action: Option<String>,
+    // shell command of a `spawn "cmd"` action
+    pub command: Option<String>,
+    // times the keybind was used, when a usage log is configured
+    pub usage: Option<u64>,
+    pub highlight: bool,
+    // lines of the config declaring the keybind
+    pub source: Option<String>,
+    // line of its keys in the config, or of its description
+    pub line: Option<usize>,
+    // file of a keybind of the `[[sources]]`, the config otherwise
+    pub file: Option<String>,
+    // declared in the keymap of a `submap`, its keys start with the ones
+    // of the parent keybind
+    pub submap: bool,
+    // annotated with `-- !`, greyed out when shown
+    pub ignored: bool,
+    // layouts set by `-- ##layout`, the keybind is global when empty
+    pub layouts: Vec<String>,
 }
 
 impl fmt::Display for Keybind {
@@ -30,46 +167,76 @@ impl fmt::Display for Keybind {
 }
 
 impl Keybind {
-    pub fn new(keys: &str, desc: &str) -> Self {
+    pub fn new(keys: &str, desc: &str, action: Option<&str>) -> Self {
         Keybind {
             keys: keys.to_owned(),
             description: desc.to_owned(),
+            kind: action.map(ActionKind::classify).unwrap_or_default(),
+            action: action.map(str::to_owned),
+            command: action.and_then(spawn_command),
+            usage: None,
+            highlight: false,
+            source: None,
+            line: None,
+            file: None,
+            submap: false,
+            ignored: false,
+            layouts: vec![],
         }
     }
 
     fn view(&self, config: &AppConfig, palette: &Palette) -> Element<'static, Message> {
-        render_keybind(self.keys.clone(), self.description.clone(), config, palette)
+        ui::keybind_row(self.into(), config, palette)
     }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Section {
     pub title: Option<String>,
+    pub description: Option<String>,
     pub keybinds: Vec<Keybind>,
 }
 
 impl Section {
+    /// Renders the section, `collapsed` is `None` when it can't be
+    /// collapsed, e.g. in presentation mode
     #[instrument(skip_all)]
-    fn view(&self, config: &AppConfig, palette: &Palette) -> Element<'static, Message> {
+    pub fn view(
+        &self,
+        config: &AppConfig,
+        palette: &Palette,
+        collapsed: Option<bool>,
+    ) -> Element<'static, Message> {
         trace!("rendering section {:?}", &self.title);
         let mut content = column![];
         if let Some(t) = &self.title {
-            content = content.push(
-                Text::new(t.clone())
-                    .size(config.ui.section_size)
-                    .vertical_alignment(Vertical::Center),
-            );
+            content = content.push(ui::section_header(t, collapsed, config));
+        }
+        if collapsed == Some(true) {
+            return content.into();
+        }
+        if let Some(d) = &self.description {
+            content = content.push(ui::section_description(d, config, palette));
         }
 
-        let keybinds = self.keybinds.iter().fold(column![], |column, keybind| {
-            column
-                .push(keybind.view(config, palette))
-                .width(Length::Fill)
-                .spacing(8)
-                .padding(Padding::from([12, 0, 0, 12])) // top, right, bottom, left
-        });
+        let keybinds = |keybinds: &[Keybind]| {
+            keybinds.iter().fold(column![], |column, keybind| {
+                column
+                    .push(keybind.view(config, palette))
+                    .spacing(config.ui.metrics.row_spacing)
+                    .padding(config.ui.metrics.section_padding)
+            })
+        };
 
-        content.push(keybinds).into()
+        match ui::sub_column_split(&self.keybinds, config) {
+            Some(split) => content
+                .push(row![
+                    keybinds(&self.keybinds[..split]).width(Length::Fill),
+                    keybinds(&self.keybinds[split..]).width(Length::Fill),
+                ])
+                .into(),
+            None => content.push(keybinds(&self.keybinds)).into(),
+        }
     }
 }
 
@@ -77,6 +244,10 @@ impl Section {
 pub struct Tokens {
     pub title: Option<String>,
     pub sections: Vec<Section>,
+    // keybinds annotated with `-- !`, only shown on demand
+    pub ignored: Vec<Keybind>,
+    // problems found while parsing, e.g. the keymap not closed
+    pub warnings: Vec<String>,
 }
 
 impl Tokens {
@@ -98,17 +269,198 @@ impl Tokens {
         })
     }
 
+    /// Highlights the keybinds that are not part of `previous`
+    pub fn highlight_new(&mut self, previous: &Tokens) {
+        let known: HashSet<(String, &str)> = previous
+            .sections
+            .iter()
+            .flat_map(|s| &s.keybinds)
+            .map(|k| (canonical_keys(&k.keys), k.description.as_str()))
+            .collect();
+        for keybind in self.sections.iter_mut().flat_map(|s| s.keybinds.iter_mut()) {
+            keybind.highlight =
+                !known.contains(&(canonical_keys(&keybind.keys), keybind.description.as_str()));
+        }
+    }
+
+    pub fn clear_highlight(&mut self) {
+        for keybind in self.sections.iter_mut().flat_map(|s| s.keybinds.iter_mut()) {
+            keybind.highlight = false;
+        }
+    }
+
+    /// Orders the sections, the ones equal keep their declaration order
+    pub fn sort_sections(&mut self, sort: SectionSort) {
+        match sort {
+            SectionSort::Source => {}
+            SectionSort::Alpha => self.sections.sort_by(|a, b| {
+                collate(
+                    a.title.as_deref().unwrap_or_default(),
+                    b.title.as_deref().unwrap_or_default(),
+                )
+            }),
+            SectionSort::Count => self
+                .sections
+                .sort_by_key(|s| std::cmp::Reverse(s.keybinds.len())),
+        }
+    }
+
+    /// Shows the keys as printed on the keyboard with the given layout
+    pub fn translate_keys(&mut self, layout: &Layout) {
+        for keybind in self.sections.iter_mut().flat_map(|s| s.keybinds.iter_mut()) {
+            keybind.keys = layout.translate(&keybind.keys);
+        }
+    }
+
+    /// Sets the usage count of every keybind from the usage log
+    pub fn set_usage(&mut self, usage: &Usage) {
+        for keybind in self.sections.iter_mut().flat_map(|s| s.keybinds.iter_mut()) {
+            let keys = canonical_keys(&keybind.keys);
+            keybind.usage = Some(usage.get(&keys).copied().unwrap_or_default());
+        }
+    }
+
+    /// Returns a copy of the tokens only holding the keybinds to keep,
+    /// empty sections are dropped
+    fn filtered(&self, keep: impl Fn(&Keybind) -> bool) -> Tokens {
+        Tokens {
+            title: self.title.clone(),
+            sections: self
+                .sections
+                .iter()
+                .map(|s| Section {
+                    title: s.title.clone(),
+                    description: s.description.clone(),
+                    keybinds: s.keybinds.iter().filter(|k| keep(k)).cloned().collect(),
+                })
+                .filter(|s| !s.keybinds.is_empty())
+                .collect(),
+            ignored: self.ignored.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Returns a copy of the tokens only holding the keybinds of the given
+    /// action kind, empty sections are dropped
+    pub fn with_kind(&self, kind: ActionKind) -> Tokens {
+        self.filtered(|k| k.kind == kind)
+    }
+
+    /// Returns a copy of the tokens only holding the keybinds active in
+    /// the layouts of the filter, empty sections are dropped
+    pub fn with_layout(&self, filter: &LayoutFilter) -> Tokens {
+        self.filtered(|k| filter.matches(&k.layouts))
+    }
+
+    /// The layouts some keybinds are restricted to, sorted
+    pub fn layouts(&self) -> Vec<String> {
+        let mut layouts: Vec<String> = self
+            .sections
+            .iter()
+            .flat_map(|s| s.keybinds.iter())
+            .flat_map(|k| k.layouts.iter().cloned())
+            .collect();
+        layouts.sort();
+        layouts.dedup();
+        layouts
+    }
+
+    /// Returns a copy of the tokens with only the keybinds matched by a
+    /// search, in their sections
+    pub fn matching(&self, matches: &[ScoredKeybind]) -> Tokens {
+        let matches: HashSet<(&str, &str)> = matches
+            .iter()
+            .map(|k| (k.keys.as_str(), k.description.as_str()))
+            .collect();
+        self.filtered(|k| matches.contains(&(k.keys.as_str(), k.description.as_str())))
+    }
+
+    /// Returns the index of the section of a keybind matched by a search
+    /// and its index in the section
+    pub fn position(&self, keybind: &ScoredKeybind) -> Option<(usize, usize)> {
+        self.sections
+            .iter()
+            .enumerate()
+            .find_map(|(index, section)| {
+                section
+                    .keybinds
+                    .iter()
+                    .position(|k| k.keys == keybind.keys && k.description == keybind.description)
+                    .map(|row| (index, row))
+            })
+    }
+
+    /// Returns a copy of the tokens with only the keybinds having the
+    /// picked chord
+    pub fn with_chord(&self, chord: &Chord) -> Tokens {
+        self.filtered(|k| chord.matches(&k.keys))
+    }
+
+    /// Returns the keys of all the chords, without their modifiers, sorted
+    /// and deduplicated
+    pub fn chord_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .sections
+            .iter()
+            .flat_map(|s| &s.keybinds)
+            .flat_map(|k| k.keys.split_whitespace())
+            .map(|chord| split_chord(chord).1.to_owned())
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    /// Returns a copy of the tokens where the keybinds are grouped by the
+    /// modifier combo of their first chord instead of by section, e.g.
+    /// `Mod+Shift`. Groups with fewer modifiers come first.
+    pub fn by_modifier(&self) -> Tokens {
+        let mut groups: BTreeMap<(usize, Vec<usize>), Vec<Keybind>> = BTreeMap::new();
+        for keybind in self.sections.iter().flat_map(|s| &s.keybinds) {
+            let modifiers = modifiers(&keybind.keys);
+            groups
+                .entry((modifiers.len(), modifiers))
+                .or_default()
+                .push(keybind.clone());
+        }
+        Tokens {
+            title: self.title.clone(),
+            sections: groups
+                .into_iter()
+                .map(|((_, modifiers), keybinds)| Section {
+                    title: Some(modifier_combo_name(&modifiers)),
+                    description: None,
+                    keybinds,
+                })
+                .collect(),
+            ignored: self.ignored.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Renders the sections, the ones titled in `collapsed` without their
+    /// keybinds
     #[instrument(skip_all)]
-    pub fn view(&self, config: &AppConfig, palette: &Palette) -> Element<'static, Message> {
+    pub fn view(
+        &self,
+        config: &AppConfig,
+        palette: &Palette,
+        collapsed: &HashSet<String>,
+    ) -> Element<'static, Message> {
         trace!("view");
         self.sections
             .iter()
             .fold(column![], |column, section| {
-                column.push(section.view(config, palette)).spacing(8)
+                let is_collapsed = section
+                    .title
+                    .as_ref()
+                    .map(|title| collapsed.contains(title));
+                column
+                    .push(section.view(config, palette, is_collapsed))
+                    .spacing(8)
             })
-            .width(Length::Fill)
-            .spacing(28)
-            .padding(Padding::from([35, 30, 30, 30])) // top, right, bottom, left
+            .spacing(config.ui.metrics.section_spacing)
+            .padding(config.ui.metrics.keymap_padding)
             .into()
     }
 }
@@ -117,12 +469,23 @@ impl Tokens {
 pub struct ScoredKeybind {
     pub keys: String,
     pub description: String,
+    pub kind: ActionKind,
+    pub action: Option<String>,
+    pub command: Option<String>,
+    pub usage: Option<u64>,
+    pub highlight: bool,
+    pub source: Option<String>,
+    pub line: Option<usize>,
+    pub file: Option<String>,
+    pub submap: bool,
+    pub ignored: bool,
+    pub layouts: Vec<String>,
     pub score: Option<(i64, Vec<usize>)>,
 }
 
 impl ScoredKeybind {
     pub fn view(&self, config: &AppConfig, palette: &Palette) -> Element<'static, Message> {
-        render_keybind(self.keys.clone(), self.description.clone(), config, palette)
+        ui::keybind_row(self.clone(), config, palette)
     }
 }
 
@@ -137,6 +500,17 @@ impl From<&Keybind> for ScoredKeybind {
         ScoredKeybind {
             keys: keybind.keys.clone(),
             description: keybind.description.clone(),
+            kind: keybind.kind,
+            action: keybind.action.clone(),
+            command: keybind.command.clone(),
+            usage: keybind.usage,
+            highlight: keybind.highlight,
+            source: keybind.source.clone(),
+            line: keybind.line,
+            file: keybind.file.clone(),
+            submap: keybind.submap,
+            ignored: keybind.ignored,
+            layouts: keybind.layouts.clone(),
             score: None,
         }
     }
@@ -149,34 +523,477 @@ impl<'input> From<(Option<&str>, Vec<ParsedSection<'input>>)> for Tokens {
             .iter()
             .map(|s| Section {
                 title: s.title.map(|t| t.to_owned()),
+                description: s.description.map(|d| d.to_owned()),
                 keybinds: s
                     .keybinds
                     .iter()
-                    .map(|token| Keybind::new(token.0, token.1))
+                    .map(|token| Keybind::new(&token.0, &description_text(token.1), token.2))
                     .collect(),
             })
             .collect();
+        let ignored = parsed
+            .1
+            .iter()
+            .flat_map(|s| &s.ignored)
+            .map(|token| Keybind {
+                ignored: true,
+                ..Keybind::new(&token.0, &description_text(token.1), token.2)
+            })
+            .collect();
         Tokens {
             title: parsed.0.map(|t| t.to_owned()),
             sections,
+            ignored,
+            warnings: vec![],
+        }
+    }
+}
+
+/// Extracts the shell command of a simple `spawn "cmd"` action
+fn spawn_command(action: &str) -> Option<String> {
+    let literal = action.strip_prefix("spawn")?.trim();
+    let (rest, command) = hs_string(literal).ok()?;
+    (rest.is_empty() && !command.is_empty()).then(|| unescape(command).into_owned())
+}
+
+// EZConfig modifier prefixes, in display order
+const MODIFIERS: [(&str, &str); 8] = [
+    ("M-", "Mod"),
+    ("M4-", "Super"),
+    ("M1-", "Alt"),
+    ("M2-", "Mod2"),
+    ("M3-", "Mod3"),
+    ("M5-", "Mod5"),
+    ("C-", "Ctrl"),
+    ("S-", "Shift"),
+];
+
+/// Modifiers of the chord picker and their index in `MODIFIERS`, Super
+/// standing for the XMonad mod key, `M-` or `M4-`
+pub const CHORD_MODIFIERS: [(&str, usize); 4] =
+    [("Super", 0), ("Shift", 7), ("Ctrl", 6), ("Alt", 2)];
+
+/// Chord picked with the mouse, the sorted indexes in `MODIFIERS` of its
+/// modifiers and its key, any key when none is picked
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chord {
+    pub modifiers: Vec<usize>,
+    pub key: Option<String>,
+}
+
+impl Chord {
+    pub fn is_empty(&self) -> bool {
+        self.modifiers.is_empty() && self.key.is_none()
+    }
+
+    pub fn toggle(&mut self, modifier: usize) {
+        match self.modifiers.binary_search(&modifier) {
+            Ok(i) => {
+                self.modifiers.remove(i);
+            }
+            Err(i) => self.modifiers.insert(i, modifier),
+        }
+    }
+
+    /// Whether one of the chords of the keys has exactly the modifiers
+    /// and the key of this one, e.g. Super and `p` match `M-x M4-p`
+    pub fn matches(&self, keys: &str) -> bool {
+        keys.split_whitespace().any(|chord| {
+            let (modifiers, key) = split_chord(chord);
+            let mut modifiers: Vec<usize> = modifiers
+                .into_iter()
+                .map(|i| if MODIFIERS[i].0 == "M4-" { 0 } else { i })
+                .collect();
+            modifiers.sort_unstable();
+            modifiers.dedup();
+            modifiers == self.modifiers && self.key.as_ref().is_none_or(|k| k == key)
+        })
+    }
+}
+
+/// Returns the sorted indexes in `MODIFIERS` of the modifiers of the
+/// first chord of the keys, e.g. `S-M-x M-y` gives Mod and Shift
+fn modifiers(keys: &str) -> Vec<usize> {
+    let (mut modifiers, _) = split_chord(keys.split_whitespace().next().unwrap_or_default());
+    modifiers.sort_unstable();
+    modifiers.dedup();
+    modifiers
+}
+
+/// Splits a chord into the indexes in `MODIFIERS` of its modifiers and
+/// its key, e.g. `M-S-<Return>` gives Mod, Shift and `<Return>`
+pub fn split_chord(mut chord: &str) -> (Vec<usize>, &str) {
+    let mut modifiers = vec![];
+    // the longest prefix wins so that `M1-` is not read as `M-`
+    while let Some((i, (prefix, _))) = MODIFIERS
+        .iter()
+        .enumerate()
+        .filter(|(_, (prefix, _))| chord.starts_with(prefix))
+        .max_by_key(|(_, (prefix, _))| prefix.len())
+    {
+        // `M--` is the `-` key with Mod
+        if chord.len() == prefix.len() {
+            break;
+        }
+        chord = &chord[prefix.len()..];
+        modifiers.push(i);
+    }
+    (modifiers, chord)
+}
+
+/// Order of the sections of the keymap
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionSort {
+    /// As declared in the config, then the sources
+    #[default]
+    Source,
+    /// By title, ignoring the case and the accents
+    Alpha,
+    /// The sections with the most keybinds first
+    Count,
+}
+
+/// Where the modifiers of a chord are rendered
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModifiersOrder {
+    /// `M-S-t`
+    #[default]
+    Before,
+    /// `t-S-M`, the modifiers are reversed to read outwards from the key
+    After,
+}
+
+/// How the keys are rendered, the EZConfig notation by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyStyle {
+    pub order: ModifiersOrder,
+    // between the modifiers and the key of a chord
+    pub separator: String,
+    // rendered name of the modifiers by EZConfig prefix, e.g. `S` = "⇧"
+    pub names: HashMap<String, String>,
+}
+
+impl Default for KeyStyle {
+    fn default() -> Self {
+        KeyStyle {
+            order: ModifiersOrder::default(),
+            separator: "-".to_owned(),
+            names: HashMap::new(),
         }
     }
 }
 
-fn render_keybind(
-    keys: String,
-    desc: String,
-    config: &AppConfig,
-    palette: &Palette,
-) -> Element<'static, Message> {
-    Row::new()
-        .spacing(20)
-        .align_items(Alignment::Center)
-        .push(
-            Text::new(keys)
-                .size(config.ui.keybind_size)
-                .style(palette.primary),
-        )
-        .push(Text::new(desc).size(config.ui.text_size))
-        .into()
+/// Renders the keys with the given style, e.g. `M-S-t` gives
+/// `t + Shift + Super` with the modifiers after the key, ` + ` as
+/// separator and names for `M` and `S`
+pub fn render_keys(keys: &str, style: &KeyStyle) -> String {
+    keys.split(' ')
+        .map(|chord| {
+            let (modifiers, key) = split_chord(chord);
+            let mut parts: Vec<&str> = modifiers
+                .iter()
+                .map(|&i| {
+                    let prefix = MODIFIERS[i].0.trim_end_matches('-');
+                    style.names.get(prefix).map_or(prefix, String::as_str)
+                })
+                .collect();
+            match style.order {
+                ModifiersOrder::Before => parts.push(key),
+                ModifiersOrder::After => {
+                    parts.reverse();
+                    parts.insert(0, key);
+                }
+            }
+            parts.join(&style.separator)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Wraps the chords of the keys onto several lines of at most `width`
+/// chars, a chord longer than that gets its own line
+pub fn wrap_keys(keys: &str, width: usize) -> String {
+    let mut lines: Vec<String> = vec![];
+    for chord in keys.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + chord.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(chord);
+            }
+            _ => lines.push(chord.to_owned()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Orders the modifiers of each chord as in `MODIFIERS`, so that the
+/// same binding always compares equal, e.g. `S-M-a` gives `M-S-a`
+pub fn canonical_keys(keys: &str) -> String {
+    keys.split_whitespace()
+        .map(|chord| {
+            let (mut modifiers, key) = split_chord(chord);
+            modifiers.sort_unstable();
+            modifiers.dedup();
+            modifiers
+                .iter()
+                .map(|&i| MODIFIERS[i].0)
+                .chain([key])
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn modifier_combo_name(modifiers: &[usize]) -> String {
+    if modifiers.is_empty() {
+        return "No modifier".to_string();
+    }
+    modifiers
+        .iter()
+        .map(|&i| MODIFIERS[i].1)
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_classification() {
+        assert_eq!(
+            ActionKind::classify(r#"spawn "firefox""#),
+            ActionKind::Spawn
+        );
+        assert_eq!(
+            ActionKind::classify("spawn $ myTerminal"),
+            ActionKind::Spawn
+        );
+        assert_eq!(
+            ActionKind::classify("windows W.focusUp"),
+            ActionKind::Window
+        );
+        assert_eq!(ActionKind::classify("kill"), ActionKind::Window);
+        assert_eq!(
+            ActionKind::classify("windows $ W.greedyView \"1\""),
+            ActionKind::Workspace
+        );
+        assert_eq!(
+            ActionKind::classify("toggleRecentWS"),
+            ActionKind::Workspace
+        );
+        assert_eq!(
+            ActionKind::classify("sendMessage (IncMasterN 1)"),
+            ActionKind::Layout
+        );
+        assert_eq!(ActionKind::classify("nextScreen"), ActionKind::Screen);
+        assert_eq!(ActionKind::classify("io exitSuccess"), ActionKind::Xmonad);
+        assert_eq!(ActionKind::classify("myCustomAction"), ActionKind::Other);
+    }
+
+    #[test]
+    fn spawn_command_extraction() {
+        assert_eq!(
+            spawn_command(r#"spawn "firefox""#),
+            Some("firefox".to_owned())
+        );
+        assert_eq!(
+            spawn_command(r#"spawn   "xmonad --recompile; xmonad --restart""#),
+            Some("xmonad --recompile; xmonad --restart".to_owned())
+        );
+        assert_eq!(
+            spawn_command(r#"spawn "notify-send \"hi\" \\o/""#),
+            Some(r#"notify-send "hi" \o/"#.to_owned())
+        );
+        assert_eq!(spawn_command("spawn $ myTerminal"), None);
+        assert_eq!(spawn_command(r#"spawn ("a" ++ "b")"#), None);
+        assert_eq!(spawn_command(r#"spawnOn "1" "firefox""#), None);
+    }
+
+    #[test]
+    fn modifier_grouping() {
+        let keybind = |keys: &str| Keybind::new(keys, "", None);
+        let tokens = Tokens {
+            title: None,
+            ignored: vec![],
+            warnings: vec![],
+            sections: vec![
+                Section {
+                    title: Some("Apps".into()),
+                    description: None,
+                    keybinds: vec![keybind("M-S-<Return>"), keybind("M-f")],
+                },
+                Section {
+                    title: Some("Media".into()),
+                    description: None,
+                    keybinds: vec![
+                        keybind("<XF86AudioMute>"),
+                        keybind("S-M-q"),
+                        keybind("M1-C-l M-x"),
+                        keybind("M--"),
+                    ],
+                },
+            ],
+        };
+        let grouped = tokens.by_modifier();
+        let titles: Vec<_> = grouped
+            .sections
+            .iter()
+            .map(|s| s.title.clone().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["No modifier", "Mod", "Mod+Shift", "Alt+Ctrl"]);
+        let keys: Vec<_> = grouped.sections[2]
+            .keybinds
+            .iter()
+            .map(|k| k.keys.as_str())
+            .collect();
+        assert_eq!(keys, vec!["M-S-<Return>", "S-M-q"]);
+        assert_eq!(grouped.sections[1].keybinds.len(), 2);
+    }
+
+    #[test]
+    fn chord_picking() {
+        let mut chord = Chord::default();
+        assert!(chord.is_empty());
+        chord.toggle(7);
+        chord.toggle(0);
+        assert_eq!(chord.modifiers, vec![0, 7]);
+        assert!(chord.matches("S-M-<Return>"));
+        assert!(chord.matches("M-x M4-S-y"));
+        assert!(!chord.matches("M-<Return>"));
+        assert!(!chord.matches("M-S-C-<Return>"));
+        chord.key = Some("<Return>".into());
+        assert!(chord.matches("M-S-<Return>"));
+        assert!(!chord.matches("M-S-q"));
+        chord.toggle(7);
+        assert!(chord.matches("M-<Return>"));
+    }
+
+    #[test]
+    fn matches_in_sections() {
+        let keybind = |keys: &str, desc: &str| Keybind::new(keys, desc, None);
+        let tokens = Tokens {
+            title: None,
+            ignored: vec![],
+            warnings: vec![],
+            sections: vec![
+                Section {
+                    title: Some("Apps".into()),
+                    description: None,
+                    keybinds: vec![keybind("M-p", "dmenu"), keybind("M-f", "firefox")],
+                },
+                Section {
+                    title: Some("Media".into()),
+                    description: None,
+                    keybinds: vec![keybind("<XF86AudioMute>", "mute")],
+                },
+            ],
+        };
+        let matches = vec![ScoredKeybind::from(&keybind("M-f", "firefox"))];
+        let matching = tokens.matching(&matches);
+        assert_eq!(matching.section_count(), 1);
+        assert_eq!(matching.sections[0].title.as_deref(), Some("Apps"));
+        assert_eq!(matching.sections[0].keybinds[0].keys, "M-f");
+        assert_eq!(matching.keybind_count(), 1);
+        assert_eq!(tokens.position(&matches[0]), Some((0, 1)));
+        assert_eq!(
+            tokens.position(&ScoredKeybind::from(&keybind("M-f", "chromium"))),
+            None
+        );
+    }
+
+    #[test]
+    fn sub_columns() {
+        let mut config = AppConfig::from(crate::user_config::UserConfig::default());
+        config.ui.text_size = 14;
+        config.ui.metrics.sub_columns_from = 4;
+        let mut keybinds: Vec<Keybind> = (1..=5)
+            .map(|i| Keybind::new(&format!("M-{i}"), "Workspace", None))
+            .collect();
+        config.ui.window_width = 800.0;
+        assert_eq!(ui::sub_column_split(&keybinds, &config), None, "too narrow");
+        config.ui.window_width = 1920.0;
+        assert_eq!(ui::sub_column_split(&keybinds, &config), Some(3));
+        assert_eq!(ui::sub_column_split(&keybinds[..3], &config), None);
+        // the keybinds of a submap follow the one opening it
+        keybinds[3].submap = true;
+        assert_eq!(ui::sub_column_split(&keybinds, &config), Some(4));
+        config.ui.metrics.sub_columns_from = 0;
+        assert_eq!(ui::sub_column_split(&keybinds, &config), None);
+    }
+
+    #[test]
+    fn sections_sorting() {
+        let section = |title: &str, count: usize| Section {
+            title: Some(title.to_owned()),
+            description: None,
+            keybinds: vec![Keybind::new("M-a", "A", None); count],
+        };
+        let mut tokens = Tokens {
+            title: None,
+            sections: vec![
+                section("Workspaces", 9),
+                section("écran", 2),
+                section("Apps", 4),
+                section("Layout", 2),
+            ],
+            ignored: vec![],
+            warnings: vec![],
+        };
+        let titles = |tokens: &Tokens| -> Vec<String> {
+            tokens
+                .sections
+                .iter()
+                .filter_map(|s| s.title.clone())
+                .collect()
+        };
+        tokens.sort_sections(SectionSort::Source);
+        assert_eq!(titles(&tokens), ["Workspaces", "écran", "Apps", "Layout"]);
+        tokens.sort_sections(SectionSort::Count);
+        assert_eq!(titles(&tokens), ["Workspaces", "Apps", "écran", "Layout"]);
+        tokens.sort_sections(SectionSort::Alpha);
+        assert_eq!(titles(&tokens), ["Apps", "écran", "Layout", "Workspaces"]);
+    }
+
+    #[test]
+    fn keys_canonicalization() {
+        assert_eq!(canonical_keys("S-M-a"), "M-S-a");
+        assert_eq!(canonical_keys("M-S-a"), "M-S-a");
+        assert_eq!(canonical_keys("C-M1-l S-M-x"), "M1-C-l M-S-x");
+        assert_eq!(canonical_keys("S-M--"), "M-S--");
+        assert_eq!(canonical_keys("<XF86AudioMute>"), "<XF86AudioMute>");
+    }
+
+    #[test]
+    fn keys_rendering() {
+        let default = KeyStyle::default();
+        assert_eq!(render_keys("M-S-t", &default), "M-S-t");
+        assert_eq!(render_keys("M1-C-l M-x", &default), "M1-C-l M-x");
+        assert_eq!(render_keys("M--", &default), "M--");
+        let style = KeyStyle {
+            order: ModifiersOrder::Before,
+            separator: " ".into(),
+            names: HashMap::from([("M".into(), "Super".into()), ("S".into(), "⇧".into())]),
+        };
+        assert_eq!(render_keys("M-S-t", &style), "Super ⇧ t");
+        let style = KeyStyle {
+            order: ModifiersOrder::After,
+            separator: " + ".into(),
+            names: HashMap::from([("M".into(), "Super".into()), ("S".into(), "Shift".into())]),
+        };
+        assert_eq!(render_keys("M-S-t", &style), "t + Shift + Super");
+        assert_eq!(render_keys("<Print>", &style), "<Print>");
+    }
+
+    #[test]
+    fn keys_wrapping() {
+        assert_eq!(wrap_keys("M-S-<Return>", 24), "M-S-<Return>");
+        assert_eq!(
+            wrap_keys("M-S-C-<XF86AudioRaiseVolume> M-a M-b", 24),
+            "M-S-C-<XF86AudioRaiseVolume>\nM-a M-b"
+        );
+        assert_eq!(wrap_keys("M-a M-b M-c", 7), "M-a M-b\nM-c");
+    }
 }
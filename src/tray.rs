@@ -0,0 +1,159 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Icon in the system tray, a StatusNotifierItem over D-Bus, e.g. in the
+// tray of a panel or of a bar like polybar or waybar, or in stalonetray
+// and trayer through snixembed, for when the toggle hotkey is forgotten.
+// A click toggles the window, the menu has the other actions.
+
+use iced::{
+    futures::{channel::mpsc, SinkExt, StreamExt},
+    subscription, Subscription,
+};
+use ksni::{menu::StandardItem, MenuItem, ToolTip, TrayMethods};
+use tracing::{error, info, instrument, trace};
+
+use crate::app::Message;
+
+// freedesktop icon name, of the icon theme in use
+const ICON_NAME: &str = "input-keyboard";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TrayItem {
+    Show,
+    Reload,
+    SwitchProfile,
+    Quit,
+}
+
+impl TrayItem {
+    const ALL: [TrayItem; 4] = [
+        TrayItem::Show,
+        TrayItem::Reload,
+        TrayItem::SwitchProfile,
+        TrayItem::Quit,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            TrayItem::Show => "Show keymap",
+            TrayItem::Reload => "Reload",
+            TrayItem::SwitchProfile => "Switch profile",
+            TrayItem::Quit => "Quit",
+        }
+    }
+
+    fn message(&self) -> Message {
+        match self {
+            TrayItem::Show => Message::ShowWindow,
+            TrayItem::Reload => Message::Reload,
+            TrayItem::SwitchProfile => Message::ProfileSwitched,
+            TrayItem::Quit => Message::Quit,
+        }
+    }
+}
+
+// sends the message of each action to the app, the callbacks of ksni
+// must not block
+struct Tray(mpsc::UnboundedSender<Message>);
+
+impl Tray {
+    fn send(&self, message: Message) {
+        trace!("tray action {message}");
+        self.0.unbounded_send(message).ok();
+    }
+}
+
+impl ksni::Tray for Tray {
+    fn id(&self) -> String {
+        env!("CARGO_PKG_NAME").into()
+    }
+
+    fn title(&self) -> String {
+        "apekey".into()
+    }
+
+    fn icon_name(&self) -> String {
+        ICON_NAME.into()
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            title: "apekey".into(),
+            description: "Click to toggle the keymap".into(),
+            ..Default::default()
+        }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.send(Message::ToggleWindow);
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        TrayItem::ALL
+            .iter()
+            .map(|&item| {
+                StandardItem {
+                    label: item.label().into(),
+                    activate: Box::new(move |tray: &mut Tray| tray.send(item.message())),
+                    ..Default::default()
+                }
+                .into()
+            })
+            .collect()
+    }
+}
+
+/// Registers the icon, its clicks and menu items sending their message
+#[instrument(skip_all)]
+pub fn subscription() -> Subscription<Message> {
+    struct TrayIcon;
+
+    subscription::channel(
+        std::any::TypeId::of::<TrayIcon>(),
+        10,
+        |mut output| async move {
+            let (sender, mut receiver) = mpsc::unbounded();
+            // the service runs in a task of its own
+            match Tray(sender).spawn().await {
+                Ok(_) => info!("tray icon registered"),
+                Err(e) => error!("failed to show the tray icon: {e}"),
+            }
+            while let Some(message) = receiver.next().await {
+                output.send(message).await.ok();
+            }
+            iced::futures::future::pending().await
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ksni::Tray as _;
+
+    #[test]
+    fn menu_items() {
+        let (sender, mut receiver) = mpsc::unbounded();
+        let mut tray = Tray(sender);
+        let menu = tray.menu();
+        let labels: Vec<&str> = menu
+            .iter()
+            .filter_map(|item| match item {
+                MenuItem::Standard(item) => Some(item.label.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(labels, ["Show keymap", "Reload", "Switch profile", "Quit"]);
+        if let MenuItem::Standard(item) = &menu[1] {
+            (item.activate)(&mut tray);
+        }
+        tray.activate(0, 0);
+        let sent: Vec<String> = [receiver.try_next(), receiver.try_next()]
+            .into_iter()
+            .map(|message| message.unwrap().unwrap().to_string())
+            .collect();
+        assert_eq!(sent, ["Reload", "ToggleWindow"]);
+    }
+}
@@ -0,0 +1,900 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Reusable widgets of the views, styled from the app config and the
+// theme palette
+
+use iced::{
+    alignment::{Horizontal, Vertical},
+    font,
+    theme::{self, Palette},
+    widget::{
+        button, column, container, pick_list, row, scrollable, text_input, tooltip,
+        tooltip::Position, Column, Row, Scrollable, Text,
+    },
+    Alignment, Color, Element, Font, Length, Padding,
+};
+use serde::Deserialize;
+use std::{path::Path, time::Duration};
+// TODO once stable drop once_cell crate and use `std::sync::{LazyLock, OnceLock}`
+use once_cell::sync::Lazy;
+
+use crate::{
+    app::{ActionFilter, AppConfig, LayoutFilter, Message, FONT_MONO, STDIN_PATH},
+    compare::ComparedKeys,
+    export::{hex_color, row_value},
+    markup::{self, Span},
+    search::{Matcher, SearchMode},
+    template::{Align, Field, Segment},
+    token::{render_keys, wrap_keys, Chord, Keybind, ScoredKeybind, Tokens, CHORD_MODIFIERS},
+    view_model::ViewModel,
+};
+
+pub static INPUT_ID: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
+pub static KEYMAP_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
+// advance of a glyph of the mono font, relative to the font size
+const GLYPH_ADVANCE: f32 = 0.6;
+const SEARCH_WIDTH: f32 = 180.0;
+const RULE_THICKNESS: u16 = 1;
+const SUB_COLUMNS_FROM: usize = 40;
+// width of a description, in characters, a sub-column must fit
+const DESCRIPTION_COLUMN: u16 = 36;
+
+/// How tight the keymap is laid out
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    /// Fits large keymaps, e.g. 200 keybinds, on fewer screens
+    Compact,
+    #[default]
+    Comfortable,
+}
+
+impl Density {
+    /// Spacing between the keybind rows
+    pub fn row_spacing(self) -> u16 {
+        match self {
+            Density::Compact => 2,
+            Density::Comfortable => 8,
+        }
+    }
+
+    /// Spacing between the sections
+    pub fn section_spacing(self) -> u16 {
+        match self {
+            Density::Compact => 12,
+            Density::Comfortable => 28,
+        }
+    }
+
+    /// Padding of the keybinds of a section, under its header
+    pub fn section_padding(self) -> Padding {
+        match self {
+            Density::Compact => Padding::from([4, 0, 0, 12]), // top, right, bottom, left
+            Density::Comfortable => Padding::from([12, 0, 0, 12]),
+        }
+    }
+
+    /// Padding around the keymap
+    pub fn keymap_padding(self) -> Padding {
+        match self {
+            Density::Compact => Padding::from([16, 20, 16, 20]), // top, right, bottom, left
+            Density::Comfortable => Padding::from([35, 30, 30, 30]),
+        }
+    }
+
+    /// Padding of the header and of its widgets
+    pub fn header_padding(self) -> u16 {
+        match self {
+            Density::Compact => 10,
+            Density::Comfortable => 20,
+        }
+    }
+
+    /// Spacing between the title, the search bar and the panels
+    pub fn header_spacing(self) -> u16 {
+        match self {
+            Density::Compact => 8,
+            Density::Comfortable => 14,
+        }
+    }
+}
+
+/// Paddings, spacings and sizes of the window layout, the ones of the
+/// density unless set in the `[layout]` table
+#[derive(Debug, Clone, Copy)]
+pub struct Metrics {
+    pub row_spacing: u16,
+    pub section_spacing: u16,
+    pub section_padding: Padding,
+    pub keymap_padding: Padding,
+    pub header_padding: u16,
+    pub header_spacing: u16,
+    // thickness of the rule under the header
+    pub rule_thickness: u16,
+    // width of the search field
+    pub search_width: f32,
+    // keybinds from which a section flows into two sub-columns, never
+    // when 0
+    pub sub_columns_from: usize,
+}
+
+impl Metrics {
+    fn control_padding(&self) -> u16 {
+        self.header_padding / 2
+    }
+}
+
+impl From<Density> for Metrics {
+    fn from(density: Density) -> Self {
+        Metrics {
+            row_spacing: density.row_spacing(),
+            section_spacing: density.section_spacing(),
+            section_padding: density.section_padding(),
+            keymap_padding: density.keymap_padding(),
+            header_padding: density.header_padding(),
+            header_spacing: density.header_spacing(),
+            rule_thickness: RULE_THICKNESS,
+            search_width: SEARCH_WIDTH,
+            sub_columns_from: SUB_COLUMNS_FROM,
+        }
+    }
+}
+
+/// Index a long section is split at to flow into two balanced
+/// sub-columns, when the window is wide enough for both. The keybinds
+/// of a submap stay with the one opening it.
+pub fn sub_column_split(keybinds: &[Keybind], config: &AppConfig) -> Option<usize> {
+    let metrics = &config.ui.metrics;
+    if metrics.sub_columns_from == 0 || keybinds.len() < metrics.sub_columns_from {
+        return None;
+    }
+    let column_width = f32::from(config.ui.key_column + DESCRIPTION_COLUMN)
+        * f32::from(config.ui.text_size)
+        * GLYPH_ADVANCE
+        + metrics.section_padding.left
+        + 20.0;
+    let padding = metrics.keymap_padding.left + metrics.keymap_padding.right;
+    if config.ui.window_width < 2.0 * column_width + padding {
+        return None;
+    }
+    let mut split = keybinds.len().div_ceil(2);
+    while keybinds.get(split).is_some_and(|k| k.submap) {
+        split += 1;
+    }
+    (split < keybinds.len()).then_some(split)
+}
+
+/// Color of secondary information, e.g. counts or hints
+pub fn muted(palette: &Palette) -> Color {
+    Color {
+        a: 0.5,
+        ..palette.text
+    }
+}
+
+/// Centered loading step, e.g. `▪▫▫ Reading xmonad.hs`
+pub fn progress(label: &'static str) -> Element<'static, Message> {
+    container(Text::new(label).font(FONT_MONO))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding(20)
+        .center_x()
+        .center_y()
+        .into()
+}
+
+/// Names and formats of the files the keymap is read from, under the
+/// title, a click opens the file in the editor
+pub fn sources_header(config: &AppConfig, palette: &Palette) -> Element<'static, Message> {
+    let size = config.ui.text_size.saturating_sub(2);
+    let keymap = (config.config_path != STDIN_PATH).then(|| config.config_path.clone());
+    let files = [(keymap, config.profile.format())].into_iter().chain(
+        config
+            .sources
+            .iter()
+            .map(|source| (source.file(), source.kind.format())),
+    );
+    files
+        .fold(row![].spacing(16), |row, (path, format)| {
+            let label = match path.as_deref().and_then(|path| Path::new(path).file_name()) {
+                Some(name) => format!("{} · {format}", name.to_string_lossy()),
+                None => format.to_owned(),
+            };
+            let label = Text::new(label).size(size).style(muted(palette));
+            match path {
+                Some(path) => row.push(
+                    button(label)
+                        .padding(0)
+                        .style(theme::Button::Text)
+                        .on_press(Message::OpenFile(path)),
+                ),
+                // a command or the standard input
+                None => row.push(label),
+            }
+        })
+        .into()
+}
+
+/// The load error, with a button to fix the keymap in the editor
+pub fn error_panel(err: &str, config: &AppConfig, palette: &Palette) -> Element<'static, Message> {
+    container(
+        column![
+            Text::new(err.to_owned())
+                .size(config.ui.error_size)
+                .style(palette.danger)
+                .width(Length::Fixed(400.0)),
+            button(Text::new("Open in editor (e)").size(config.ui.text_size))
+                .style(theme::Button::Secondary)
+                .on_press(Message::OpenEditor),
+        ]
+        .spacing(20),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(20)
+    .center_x()
+    .center_y()
+    .into()
+}
+
+/// Scrollable of the keybinds, horizontally too so that long descriptions
+/// stay readable with large fonts
+pub fn keymap_scrollable<'a>(content: impl Into<Element<'a, Message>>) -> Scrollable<'a, Message> {
+    scrollable(content)
+        .id(KEYMAP_ID.clone())
+        .direction(scrollable::Direction::Both {
+            vertical: scrollable::Properties::default(),
+            horizontal: scrollable::Properties::default(),
+        })
+}
+
+/// Action and layout filters, grouping and search mode toggles and
+/// search field, with the number of matches and the search duration
+/// under it
+pub fn search_bar(
+    view: &ViewModel,
+    chord_picker: bool,
+    config: &AppConfig,
+) -> Element<'static, Message> {
+    let mut controls = row![pick_list(
+        &ActionFilter::ALL[..],
+        Some(view.action_filter),
+        Message::ActionFilterSelected
+    )
+    .padding(config.ui.metrics.control_padding())
+    .text_size(config.ui.text_size)]
+    .spacing(10);
+    // the layout dropdown is only useful when some keybinds have one
+    let layouts = view.keymap.layouts();
+    if !layouts.is_empty() {
+        let filters: Vec<LayoutFilter> = [LayoutFilter::All, LayoutFilter::Global]
+            .into_iter()
+            .chain(layouts.into_iter().map(LayoutFilter::Layout))
+            .collect();
+        controls = controls.push(
+            pick_list(
+                filters,
+                Some(view.layout_filter.clone()),
+                Message::LayoutFilterSelected,
+            )
+            .padding(config.ui.metrics.control_padding())
+            .text_size(config.ui.text_size),
+        );
+    }
+    let controls = controls.push(
+        row![
+            button(
+                Text::new(if view.by_modifier {
+                    "By modifier"
+                } else {
+                    "By section"
+                })
+                .size(config.ui.text_size)
+            )
+            .padding(config.ui.metrics.control_padding())
+            .style(theme::Button::Secondary)
+            .on_press(Message::GroupingToggled),
+            button(
+                Text::new(match view.search_mode {
+                    SearchMode::Flat => "Flat results",
+                    SearchMode::Sections => "Results in sections",
+                    SearchMode::Jump => "Jump to matches",
+                })
+                .size(config.ui.text_size)
+            )
+            .padding(config.ui.metrics.control_padding())
+            .style(theme::Button::Secondary)
+            .on_press(Message::SearchModeToggled),
+            button(
+                Text::new(match view.matcher {
+                    Matcher::Fuzzy => "Fuzzy",
+                    Matcher::Regex => "Regex",
+                })
+                .size(config.ui.text_size)
+            )
+            .padding(config.ui.metrics.control_padding())
+            .style(theme::Button::Secondary)
+            .on_press(Message::MatcherToggled),
+            button(Text::new("Chord").size(config.ui.text_size))
+                .padding(config.ui.metrics.control_padding())
+                .style(if chord_picker {
+                    theme::Button::Primary
+                } else {
+                    theme::Button::Secondary
+                })
+                .on_press(Message::ChordPickerToggled),
+            text_input("Search", &view.input_value)
+                .id(INPUT_ID.clone())
+                .padding(config.ui.metrics.control_padding())
+                .width(Length::Fixed(config.ui.metrics.search_width))
+                .size(config.ui.text_size)
+                .on_input(Message::InputChanged)
+                .on_submit(Message::SearchSubmitted),
+        ]
+        .spacing(10),
+    );
+    let mut content = column![controls].spacing(4).align_items(Alignment::End);
+    if view.is_searching() {
+        let stats = view
+            .regex_error
+            .clone()
+            .unwrap_or_else(|| search_stats(view.tokens.len(), view.search_time));
+        content = content.push(Text::new(stats).size(config.ui.text_size.saturating_sub(2)));
+    }
+    container(content)
+        .width(Length::Fill)
+        .align_x(Horizontal::Right)
+        .into()
+}
+
+/// Bare search field of the minimal mode, see `--minimal`
+pub fn filter_line(query: &str, config: &AppConfig) -> Element<'static, Message> {
+    text_input("Filter", query)
+        .id(INPUT_ID.clone())
+        .padding(config.ui.metrics.control_padding())
+        .size(config.ui.text_size)
+        .on_input(Message::InputChanged)
+        .on_submit(Message::SearchSubmitted)
+        .into()
+}
+
+/// Keybinds in a single column, without section headers nor spacing
+pub fn dense_list(
+    keybinds: impl Iterator<Item = ScoredKeybind>,
+    config: &AppConfig,
+    palette: &Palette,
+) -> Scrollable<'static, Message> {
+    keymap_scrollable(
+        keybinds
+            .fold(column![], |column, keybind| {
+                column.push(keybind_row(keybind, config, palette))
+            })
+            .padding(config.ui.metrics.section_padding),
+    )
+}
+
+/// Modifier toggles and key dropdown to find the keybinds having a chord
+pub fn chord_picker(
+    chord: &Chord,
+    keys: Vec<String>,
+    config: &AppConfig,
+) -> Element<'static, Message> {
+    let toggles = CHORD_MODIFIERS
+        .iter()
+        .fold(row![], |row, (name, modifier)| {
+            row.push(
+                button(Text::new(*name).size(config.ui.text_size))
+                    .padding(config.ui.metrics.control_padding())
+                    .style(if chord.modifiers.contains(modifier) {
+                        theme::Button::Primary
+                    } else {
+                        theme::Button::Secondary
+                    })
+                    .on_press(Message::ChordModifierToggled(*modifier)),
+            )
+        });
+    container(
+        row![
+            toggles.spacing(6),
+            pick_list(keys, chord.key.clone(), Message::ChordKeySelected)
+                .placeholder("Any key")
+                .padding(config.ui.metrics.control_padding())
+                .text_size(config.ui.text_size),
+            button(Text::new("Clear").size(config.ui.text_size))
+                .padding(config.ui.metrics.control_padding())
+                .style(theme::Button::Text)
+                .on_press(Message::ChordCleared),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center),
+    )
+    .width(Length::Fill)
+    .align_x(Horizontal::Right)
+    .into()
+}
+
+/// e.g. `23 matches (3 ms)`, a search under a millisecond shows `<1 ms`
+fn search_stats(count: usize, elapsed: Duration) -> String {
+    let plural = if count == 1 { "" } else { "es" };
+    match elapsed.as_millis() {
+        0 => format!("{count} match{plural} (<1 ms)"),
+        ms => format!("{count} match{plural} ({ms} ms)"),
+    }
+}
+
+/// Problems found in the apekey config
+pub fn diagnostics(
+    warnings: &[String],
+    config: &AppConfig,
+    palette: &Palette,
+) -> Element<'static, Message> {
+    let size = config.ui.text_size;
+    let list = warnings.iter().fold(column![], |column, warning| {
+        column.push(
+            Text::new(format!("⚠ {warning}"))
+                .size(size)
+                .style(palette.danger),
+        )
+    });
+    row![
+        list.spacing(4).width(Length::Fill),
+        button(Text::new("Dismiss").size(size))
+            .style(theme::Button::Secondary)
+            .on_press(Message::DiagnosticsDismissed),
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+/// A transient notice, dismissed before it expires with `dismiss`
+pub fn toast(
+    text: &str,
+    dismiss: Message,
+    config: &AppConfig,
+    palette: &Palette,
+) -> Element<'static, Message> {
+    let size = config.ui.text_size;
+    container(
+        row![
+            Text::new(text.to_owned())
+                .size(size)
+                .style(palette.danger)
+                .width(Length::Fill),
+            button(Text::new("Dismiss").size(size))
+                .style(theme::Button::Secondary)
+                .on_press(dismiss),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center),
+    )
+    .padding(6)
+    .style(theme::Container::Box)
+    .into()
+}
+
+/// Version, paths and stats of the loaded keymap, to help debugging
+pub fn about(keymap: &Tokens, config: &AppConfig, palette: &Palette) -> Element<'static, Message> {
+    let size = config.ui.text_size;
+    let lines = [
+        format!("apekey {}", env!("CARGO_PKG_VERSION")),
+        format!(
+            "Keymap: {} ({})",
+            config.config_path,
+            config.profile.format()
+        ),
+        format!(
+            "Config: {}",
+            config
+                .user_config_path
+                .as_deref()
+                .unwrap_or("none, defaults")
+        ),
+        format!(
+            "{} sections, {} keybinds",
+            keymap.section_count(),
+            keymap.keybind_count()
+        ),
+        format!("Theme: {}", config.theme_name),
+    ];
+    let info = lines.into_iter().fold(column![], |column, line| {
+        column.push(Text::new(line).size(size))
+    });
+    let swatches = [
+        ("background", palette.background),
+        ("text", palette.text),
+        ("primary", palette.primary),
+        ("success", palette.success),
+        ("danger", palette.danger),
+    ]
+    .into_iter()
+    .fold(row![].spacing(12), |row, (name, color)| {
+        row.push(
+            Text::new(format!("■ {name} {}", hex_color(color)))
+                .size(size)
+                .style(color),
+        )
+    });
+    row![
+        info.push(swatches).spacing(4).width(Length::Fill),
+        button(Text::new("Close").size(size))
+            .style(theme::Button::Secondary)
+            .on_press(Message::AboutToggled),
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+/// Asks to confirm running the command of a keybind
+pub fn confirmation(command: &str, config: &AppConfig) -> Element<'static, Message> {
+    let size = config.ui.text_size;
+    row![
+        Text::new(format!("Run `{command}`?")).size(size),
+        button(Text::new("Run").size(size)).on_press(Message::ExecuteConfirmed),
+        button(Text::new("Cancel").size(size))
+            .style(theme::Button::Secondary)
+            .on_press(Message::ExecuteCancelled),
+    ]
+    .spacing(10)
+    .align_items(Alignment::Center)
+    .into()
+}
+
+/// Title of a section, a button collapsing it when it is collapsible
+pub fn section_header(
+    title: &str,
+    collapsed: Option<bool>,
+    config: &AppConfig,
+) -> Element<'static, Message> {
+    let text = |content: String| {
+        Text::new(content)
+            .size(config.ui.section_size)
+            .vertical_alignment(Vertical::Center)
+    };
+    match collapsed {
+        Some(collapsed) => {
+            let arrow = if collapsed { "▸" } else { "▾" };
+            button(text(format!("{arrow} {title}")))
+                .style(theme::Button::Text)
+                .padding(0)
+                .on_press(Message::SectionToggled(title.to_owned()))
+                .into()
+        }
+        None => text(title.to_owned()).into(),
+    }
+}
+
+/// Subtitle under a section header
+pub fn section_description(
+    description: &str,
+    config: &AppConfig,
+    palette: &Palette,
+) -> Text<'static> {
+    Text::new(description.to_owned())
+        .size(config.ui.text_size)
+        .style(muted(palette))
+}
+
+/// Keys, description, usage count and actions of a keybind
+pub fn keybind_row(
+    keybind: ScoredKeybind,
+    config: &AppConfig,
+    palette: &Palette,
+) -> Element<'static, Message> {
+    // the ignored keybinds, shown on demand, are greyed out
+    let greyed;
+    let palette = if keybind.ignored {
+        greyed = Palette {
+            text: muted(palette),
+            primary: muted(palette),
+            ..*palette
+        };
+        &greyed
+    } else {
+        palette
+    };
+    // keybinds added by the last reload are highlighted for a while
+    let keys_color = if keybind.highlight {
+        palette.success
+    } else {
+        palette.primary
+    };
+    // fixed width so that the descriptions are aligned, long keys wrap
+    let key_column = config.ui.key_column;
+    // the keybinds of a submap are nested under the keybind opening it
+    let (keys, depth) = if keybind.submap {
+        let chords: Vec<String> = keybind
+            .keys
+            .split(' ')
+            .map(|chord| render_keys(chord, &config.key_style))
+            .collect();
+        (chords.join(" then "), chords.len() - 1)
+    } else {
+        (render_keys(&keybind.keys, &config.key_style), 0)
+    };
+    let keys_text = |width: usize, align: Horizontal| {
+        Text::new(wrap_keys(&keys, width))
+            .size(config.ui.keybind_size)
+            .style(keys_color)
+            .horizontal_alignment(align)
+            .width(Length::Fixed(
+                width as f32 * f32::from(config.ui.keybind_size) * GLYPH_ADVANCE,
+            ))
+    };
+    let mut usage = keybind.usage.map(|count| {
+        // never used keybinds stand out, they are candidates for removal
+        let color = if count == 0 {
+            palette.danger
+        } else {
+            muted(palette)
+        };
+        Text::new(format!("×{count}"))
+            .size(config.ui.text_size)
+            .style(color)
+    });
+    let fields = Row::new().align_items(Alignment::Center);
+    let fields = match &config.row_template {
+        // the template spaces the fields, the widths are in characters
+        Some(template) => template
+            .0
+            .iter()
+            .fold(fields, |row, segment| match segment {
+                Segment::Literal(text) => row.push(
+                    Text::new(text.clone())
+                        .size(config.ui.text_size)
+                        .style(muted(palette)),
+                ),
+                Segment::Field {
+                    field: Field::Keys,
+                    width,
+                } => row.push(match width {
+                    Some((align, width)) => keys_text(*width, horizontal(*align)),
+                    None => Text::new(keys.clone())
+                        .size(config.ui.keybind_size)
+                        .style(keys_color),
+                }),
+                Segment::Field {
+                    field: Field::Description,
+                    width,
+                } => {
+                    let content = container(description(&keybind.description, config, palette));
+                    row.push(match width {
+                        Some((align, width)) => content
+                            .width(Length::Fixed(
+                                *width as f32 * f32::from(config.ui.text_size) * GLYPH_ADVANCE,
+                            ))
+                            .align_x(horizontal(*align)),
+                        None => content,
+                    })
+                }
+                Segment::Field {
+                    field: Field::Usage,
+                    ..
+                } => match usage.take() {
+                    Some(usage) => row.push(usage),
+                    None => row,
+                },
+                Segment::Field {
+                    field: Field::Action,
+                    width,
+                } => {
+                    let action = Text::new(keybind.action.clone().unwrap_or_default())
+                        .font(FONT_MONO)
+                        .size(config.ui.text_size)
+                        .style(muted(palette));
+                    row.push(match width {
+                        Some((align, width)) => action
+                            .width(Length::Fixed(
+                                *width as f32 * f32::from(config.ui.text_size) * GLYPH_ADVANCE,
+                            ))
+                            .horizontal_alignment(horizontal(*align)),
+                        None => action,
+                    })
+                }
+            }),
+        None => fields
+            .spacing(20)
+            .push(keys_text(usize::from(key_column), Horizontal::Left))
+            .push(description(&keybind.description, config, palette)),
+    };
+    // in `--pick` mode a click picks the keybind
+    let fields: Element<'static, Message> = match &config.pick {
+        Some(pick) => {
+            let line = pick
+                .template
+                .render(row_value(&keybind, config), |_, text| text);
+            button(fields)
+                .style(theme::Button::Text)
+                .padding(0)
+                .on_press(Message::Picked(line))
+                .into()
+        }
+        None => fields.into(),
+    };
+    let fields = tooltip(
+        fields,
+        keybind_tooltip(&keybind, config),
+        Position::FollowCursor,
+    )
+    .size(config.ui.text_size)
+    .padding(6)
+    .style(theme::Container::Box);
+    let mut row = Row::new()
+        .spacing(20)
+        .align_items(Alignment::Center)
+        .padding(Padding::from([0, 0, 0, 16 * depth as u16]))
+        .push(fields);
+    if let Some(usage) = usage {
+        row = row.push(usage);
+    }
+    if !keybind.layouts.is_empty() {
+        row = row.push(
+            Text::new(keybind.layouts.join(", "))
+                .size(config.ui.text_size)
+                .style(muted(palette)),
+        );
+    }
+    if let Some(command) = keybind.command {
+        row = row.push(icon_button("▶", "Run", Message::Execute(command), config));
+    }
+    if let Some(source) = keybind.source {
+        row = row.push(icon_button(
+            "⧉",
+            "Copy source",
+            Message::CopySource(source),
+            config,
+        ));
+    }
+    row.into()
+}
+
+/// The full description, the action and where the keybind is declared,
+/// shown when hovering its row
+fn keybind_tooltip(keybind: &ScoredKeybind, config: &AppConfig) -> String {
+    let mut lines = vec![markup::strip(&keybind.description)];
+    if let Some(action) = &keybind.action {
+        lines.push(action.clone());
+    }
+    let file = keybind.file.as_deref().unwrap_or(&config.config_path);
+    if let (Some(line), false) = (keybind.line, file == STDIN_PATH) {
+        lines.push(format!("{file}:{line}"));
+    }
+    lines.join("\n")
+}
+
+fn horizontal(align: Align) -> Horizontal {
+    match align {
+        Align::Left => Horizontal::Left,
+        Align::Right => Horizontal::Right,
+    }
+}
+
+/// A button showing an icon, or its label in the accessible mode
+fn icon_button(
+    icon: &'static str,
+    label: &'static str,
+    message: Message,
+    config: &AppConfig,
+) -> Element<'static, Message> {
+    let content = if config.accessible { label } else { icon };
+    button(Text::new(content).size(config.ui.text_size))
+        .style(theme::Button::Text)
+        .padding(0)
+        .on_press(message)
+        .into()
+}
+
+/// Description with its markup rendered, a row per line, each wrapped
+/// at the width left
+fn description(desc: &str, config: &AppConfig, palette: &Palette) -> Column<'static, Message> {
+    desc.lines().fold(Column::new(), |column, line| {
+        column.push(
+            markup::parse(line)
+                .into_iter()
+                .fold(Row::new(), |row, span| {
+                    let text = Text::new(span.content().to_owned()).size(config.ui.text_size);
+                    row.push(match span {
+                        Span::Text(_) => text.style(palette.text),
+                        Span::Bold(_) => text.style(palette.text).font(Font {
+                            weight: font::Weight::Bold,
+                            ..FONT_MONO
+                        }),
+                        Span::Code(_) => text.style(palette.primary),
+                    })
+                }),
+        )
+    })
+}
+
+/// Both keymaps side by side, one row per keys. A description differing
+/// from the other side stands out, a missing one is a dash.
+pub fn comparison(
+    rows: &[ComparedKeys],
+    their_title: Option<&str>,
+    config: &AppConfig,
+    palette: &Palette,
+) -> Element<'static, Message> {
+    let key_width =
+        f32::from(config.ui.key_column) * f32::from(config.ui.keybind_size) * GLYPH_ADVANCE;
+    let header = row![
+        Text::new("").width(Length::Fixed(key_width)),
+        Text::new("Mine")
+            .size(config.ui.section_size)
+            .width(Length::FillPortion(1)),
+        Text::new(their_title.unwrap_or("Theirs").to_owned())
+            .size(config.ui.section_size)
+            .width(Length::FillPortion(1)),
+    ]
+    .spacing(20);
+    let cell = |description: &Option<String>, same: bool| {
+        let (content, color) = match description {
+            Some(description) if same => (description.clone(), palette.text),
+            Some(description) => (description.clone(), palette.primary),
+            None => ("—".to_owned(), muted(palette)),
+        };
+        Text::new(content)
+            .size(config.ui.text_size)
+            .style(color)
+            .width(Length::FillPortion(1))
+    };
+    rows.iter()
+        .fold(column![header], |column, keys| {
+            column.push(
+                row![
+                    Text::new(wrap_keys(
+                        &render_keys(&keys.keys, &config.key_style),
+                        usize::from(config.ui.key_column),
+                    ))
+                    .size(config.ui.keybind_size)
+                    .style(palette.primary)
+                    .width(Length::Fixed(key_width)),
+                    cell(&keys.mine, keys.is_same()),
+                    cell(&keys.theirs, keys.is_same()),
+                ]
+                .spacing(20)
+                .align_items(Alignment::Center),
+            )
+        })
+        .spacing(config.ui.metrics.row_spacing)
+        .padding(config.ui.metrics.keymap_padding)
+        .into()
+}
+
+/// Shown in place of the keybinds when the search matches none
+pub fn empty_state(
+    query: &str,
+    suggestions: &[ScoredKeybind],
+    config: &AppConfig,
+    palette: &Palette,
+) -> Element<'static, Message> {
+    let size = config.ui.text_size;
+    let mut content = column![
+        Text::new(format!("No bindings match '{query}'")).size(config.ui.section_size),
+        row![
+            Text::new("Try another search or").size(size),
+            button(Text::new("clear it").size(size))
+                .style(theme::Button::Text)
+                .padding(0)
+                .on_press(Message::InputChanged(String::new())),
+        ]
+        .spacing(5)
+        .align_items(Alignment::Center),
+    ]
+    .spacing(12)
+    .width(Length::Fill)
+    .padding(config.ui.metrics.keymap_padding);
+    if !suggestions.is_empty() {
+        content = content.push(
+            Text::new("Closest bindings")
+                .size(size)
+                .style(muted(palette)),
+        );
+        content = suggestions.iter().fold(content, |column, keybind| {
+            column.push(keybind_row(keybind.clone(), config, palette))
+        });
+    }
+    content.into()
+}
@@ -0,0 +1,70 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::HashMap;
+use tokio::fs;
+use tracing::{instrument, warn};
+
+use crate::token::canonical_keys;
+
+/// Number of times each keybind was used, by canonical keys
+pub type Usage = HashMap<String, u64>;
+
+/// Reads the usage log, a missing or unreadable log is not an error,
+/// the keybinds just won't show any usage
+#[instrument]
+pub async fn read_usage(path: String) -> Option<Usage> {
+    match fs::read_to_string(&path).await {
+        Ok(content) => Some(parse_usage(&content)),
+        Err(e) => {
+            warn!("failed to read usage log {path}: {e}");
+            None
+        }
+    }
+}
+
+/// Parses a usage log. Each line is either the keys of a used keybind,
+/// e.g. appended by xmonad's logHook, or a count followed by the keys
+/// as produced by an external counter, e.g. `42 M-S-<Return>`.
+pub fn parse_usage(content: &str) -> Usage {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .fold(HashMap::new(), |mut usage, line| {
+            let (count, keys) = match line.split_once(char::is_whitespace) {
+                Some((count, keys)) => match count.parse::<u64>() {
+                    Ok(count) => (count, keys.trim()),
+                    Err(_) => (1, line),
+                },
+                None => (1, line),
+            };
+            *usage.entry(canonical_keys(keys)).or_default() += count;
+            usage
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_parsing() {
+        let usage = parse_usage(
+            r#"
+# apekey usage
+M-x
+M-x
+42 M-S-<Return>
+3   M-x
+M-<Space>
+S-M-<Return>
+"#,
+        );
+        assert_eq!(usage.get("M-x"), Some(&5));
+        assert_eq!(usage.get("M-S-<Return>"), Some(&43));
+        assert_eq!(usage.get("M-<Space>"), Some(&1));
+        assert_eq!(usage.len(), 3);
+    }
+}
@@ -3,31 +3,312 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use eyre::{eyre, Context, Result};
-use serde::Deserialize;
-use std::{env, fs, str};
-use tracing::{debug, error, instrument};
+use iced::Padding;
+use serde::{Deserialize, Deserializer};
+use std::{collections::HashMap, env, fs, str};
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::{
+    app::{Profile, STDIN_PATH},
+    compose,
+    display::{Renderer, WindowMode},
+    export::ClipboardFormat,
+    parser::Haddock,
+    search::{Matcher, SearchMode, SearchWeights},
+    source::SourceConfig,
+    theme::parse_hex_color,
+    token::{ModifiersOrder, SectionSort},
+    ui::Density,
+};
 
 // default values
 const XMONAD_HS_PATH: &str = "~/.config/xmonad/xmonad.hs";
 pub const FONT_SIZE: u16 = 16;
 pub const TITLE_FONT_SIZE: u16 = 22;
+// font sizes outside of this range are most likely a typo
+const FONT_SIZE_RANGE: std::ops::RangeInclusive<u16> = 6..=96;
 
-#[derive(Deserialize, Debug, Clone)]
-pub enum Theme {
-    Dark,
-    Light,
-    Tars,
-}
+// known keys, top level ones first then the ones of each table
+const KEYS: [&str; 31] = [
+    "xmonad_config",
+    "font",
+    "theme",
+    "auto_time",
+    "colors",
+    "color_scheme",
+    "confirm_exec",
+    "usage_log",
+    "haddock",
+    "fallback_description",
+    "show_ignored",
+    "window",
+    "keyboard_layout",
+    "close_on_focus_loss",
+    "toggle_hotkey",
+    "tray",
+    "http_port",
+    "renderer",
+    "sources",
+    "wm",
+    "density",
+    "layout",
+    "row_template",
+    "clipboard_format",
+    "section_sort",
+    "search_actions",
+    "search_weights",
+    "search_mode",
+    "search_matcher",
+    "keys",
+    "startup",
+];
+const TABLE_KEYS: [(&str, &[&str]); 8] = [
+    (
+        "font",
+        &[
+            "autoscale",
+            "title_size",
+            "section_size",
+            "keybind_size",
+            "text_size",
+            "error_size",
+        ],
+    ),
+    (
+        "colors",
+        &["background", "text", "primary", "success", "danger"],
+    ),
+    ("window", &["opacity", "key_column", "mode"]),
+    (
+        "layout",
+        &[
+            "row_spacing",
+            "section_spacing",
+            "section_padding",
+            "keymap_padding",
+            "header_padding",
+            "header_spacing",
+            "rule_thickness",
+            "search_width",
+            "sub_columns_from",
+        ],
+    ),
+    ("auto_time", &["day", "night"]),
+    ("search_weights", &["keys", "description", "action"]),
+    ("keys", &["order", "separator", "names"]),
+    (
+        "startup",
+        &[
+            "focus_search",
+            "collapsed_sections",
+            "initial_query",
+            "initial_section",
+        ],
+    ),
+];
+// keys of the `[wm.<name>]` tables
+const WM_KEYS: [&str; 3] = ["profile", "path", "sources"];
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct UserConfig {
+    #[serde(deserialize_with = "deserialize_path")]
     pub xmonad_config: String,
     pub font: Option<FontConfig>,
-    pub theme: Option<Theme>,
+    // name of a built-in theme, see `theme::THEMES`
+    pub theme: Option<String>,
+    pub auto_time: Option<AutoTimeConfig>,
+    pub colors: Option<ColorsConfig>,
+    // base16 scheme or pywal colors the palette is imported from
+    #[serde(default, deserialize_with = "deserialize_optional_path")]
+    pub color_scheme: Option<String>,
+    pub confirm_exec: Option<bool>,
+    pub search_actions: Option<bool>,
+    // factors of the match scores in the keys, description and action
+    pub search_weights: Option<SearchWeights>,
+    pub search_mode: Option<SearchMode>,
+    // fuzzy or regex matching of the search pattern
+    pub search_matcher: Option<Matcher>,
+    #[serde(default, deserialize_with = "deserialize_optional_path")]
+    pub usage_log: Option<String>,
+    pub haddock: Option<Haddock>,
+    pub fallback_description: Option<bool>,
+    // show the ignored keybinds, greyed out, from the start
+    pub show_ignored: Option<bool>,
+    pub window: Option<WindowConfig>,
+    pub keys: Option<KeysConfig>,
+    pub startup: Option<StartupConfig>,
+    // show the digits as printed on the keyboard with the current layout
+    pub keyboard_layout: Option<bool>,
+    pub close_on_focus_loss: Option<bool>,
+    pub toggle_hotkey: Option<String>,
+    pub tray: Option<bool>,
+    // port of the local HTTP endpoint, off when not set
+    pub http_port: Option<u16>,
+    // GPU or software rendering, see `display::Renderer`
+    pub renderer: Option<Renderer>,
+    pub density: Option<Density>,
+    // overrides of the paddings and spacings of the density
+    pub layout: Option<LayoutConfig>,
+    // layout of the keybind rows, see `template::RowTemplate`
+    pub row_template: Option<String>,
+    // format of the search results copied with Ctrl+Shift+C
+    pub clipboard_format: Option<ClipboardFormat>,
+    // order of the sections, as declared by default
+    pub section_sort: Option<SectionSort>,
+    // extra sections generated by commands
+    pub sources: Option<Vec<SourceConfig>>,
+    // overrides of each window manager, by lowercase name
+    pub wm: Option<HashMap<String, WmConfig>>,
+    // set by the table of the running window manager, if any
+    #[serde(skip)]
+    pub profile: Option<Profile>,
+    // profile, file and sources before the table of the running window
+    // manager was applied
+    #[serde(skip)]
+    default_profile: Option<WmConfig>,
+    // problems found in the config, shown in the diagnostics panel
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+    // where the config was read from, none when using the defaults
+    #[serde(skip)]
+    pub path: Option<String>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct WindowConfig {
+    // background opacity, from 0.0 (transparent) to 1.0 (opaque)
+    pub opacity: Option<f32>,
+    // maximum width of the keys column, in characters
+    pub key_column: Option<u16>,
+    pub mode: Option<WindowMode>,
+}
+
+/// Paddings, spacings and sizes in pixels, overriding the ones of the
+/// density
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct LayoutConfig {
+    pub row_spacing: Option<u16>,
+    pub section_spacing: Option<u16>,
+    // padding of the keybinds of a section, under its header
+    pub section_padding: Option<PaddingConfig>,
+    pub keymap_padding: Option<PaddingConfig>,
+    pub header_padding: Option<u16>,
+    // spacing between the title, the search bar and the panels
+    pub header_spacing: Option<u16>,
+    // thickness of the rule under the header
+    pub rule_thickness: Option<u16>,
+    pub search_width: Option<u16>,
+    // keybinds from which a section flows into two sub-columns
+    pub sub_columns_from: Option<usize>,
+}
+
+/// A padding on every side, or `[top, right, bottom, left]`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum PaddingConfig {
+    Uniform(u16),
+    Sides([u16; 4]),
+}
+
+impl From<PaddingConfig> for Padding {
+    fn from(padding: PaddingConfig) -> Self {
+        match padding {
+            PaddingConfig::Uniform(padding) => Padding::from(padding),
+            PaddingConfig::Sides(sides) => Padding::from(sides),
+        }
+    }
+}
+
+/// A `[wm.<name>]` table, applied when this window manager runs
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct WmConfig {
+    pub profile: Option<Profile>,
+    // file the profile reads, replaces `xmonad_config`
+    #[serde(default, deserialize_with = "deserialize_optional_path")]
+    pub path: Option<String>,
+    // replace the top level `[[sources]]`
+    pub sources: Option<Vec<SourceConfig>>,
+}
+
+/// How the window looks like once loaded
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct StartupConfig {
+    pub focus_search: Option<bool>,
+    // titles of the sections
+    pub collapsed_sections: Option<Vec<String>>,
+    // overridden by `--query`
+    pub initial_query: Option<String>,
+    // title of the section to scroll to
+    pub initial_section: Option<String>,
+}
+
+/// Rendering of the keys, e.g. `Super ⇧ t` instead of `M-S-t`
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct KeysConfig {
+    pub order: Option<ModifiersOrder>,
+    pub separator: Option<String>,
+    // rendered name of each modifier, by EZConfig prefix
+    pub names: Option<HashMap<String, String>>,
+}
+
+/// Switch times of the `auto-time` theme, as `HH:MM` local times
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AutoTimeConfig {
+    // from when the light theme is used
+    pub day: Option<String>,
+    // from when the dark theme is used
+    pub night: Option<String>,
+}
+
+/// Colors overriding the ones of the theme palette, as `#rrggbb` hex strings
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ColorsConfig {
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub primary: Option<String>,
+    pub success: Option<String>,
+    pub danger: Option<String>,
+}
+
+/// Theme and accent color given on the command line, they win over the
+/// config and are kept when it is reloaded
+#[derive(Debug, Clone, Default)]
+pub struct ThemeOverride {
+    pub theme: Option<String>,
+    pub accent: Option<String>,
+}
+
+impl ThemeOverride {
+    pub fn apply(&self, config: &mut UserConfig) {
+        // the colors of the config are tweaks of its own theme
+        if let Some(theme) = &self.theme {
+            config.theme = Some(theme.clone());
+            config.colors = None;
+            config.color_scheme = None;
+        }
+        if let Some(accent) = &self.accent {
+            config.colors.get_or_insert_with(Default::default).primary = Some(accent.clone());
+        }
+    }
+}
+
+impl ColorsConfig {
+    /// The colors set, the ones of `base` otherwise
+    fn or(self, base: ColorsConfig) -> ColorsConfig {
+        ColorsConfig {
+            background: self.background.or(base.background),
+            text: self.text.or(base.text),
+            primary: self.primary.or(base.primary),
+            success: self.success.or(base.success),
+            danger: self.danger.or(base.danger),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct FontConfig {
+    // scale the default sizes to the monitor resolution and DPI
+    pub autoscale: Option<bool>,
     pub title_size: Option<u16>,
     pub section_size: Option<u16>,
     pub keybind_size: Option<u16>,
@@ -36,39 +317,590 @@ pub struct FontConfig {
 }
 
 impl UserConfig {
+    /// Reads the config at `path` if given, else at `$APEKEY_CONFIG`, else
+    /// at `apekey/apekey.toml` in the config directory
     #[instrument]
-    pub fn try_read() -> Result<Self> {
-        let home = env::var("HOME").context("Environment variable HOME not set")?;
-        let xdg_config_path =
-            env::var("XDG_CONFIG_HOME").unwrap_or_else(|_| format!("{home}/.config"));
-        let config_path = format!("{xdg_config_path}/apekey/apekey.toml");
+    pub fn try_read(path: Option<String>) -> Result<Self> {
+        let config_path = match path.or_else(|| env::var("APEKEY_CONFIG").ok()) {
+            Some(path) => path,
+            None => dirs::config_dir()
+                .ok_or_else(|| eyre!("No config directory, the home directory is unknown"))?
+                .join("apekey/apekey.toml")
+                .display()
+                .to_string(),
+        };
         debug!("user config path {}", config_path);
-        let content = fs::read(&config_path).context(config_path)?;
-        toml::from_str::<UserConfig>(str::from_utf8(&content)?).map_err(|e| {
+        let content = fs::read(&config_path).context(config_path.clone())?;
+        let content = str::from_utf8(&content)?;
+        let mut config = toml::from_str::<UserConfig>(content).map_err(|e| {
             error!("{}", e);
             eyre!("{e}")
-        })
+        })?;
+        if let Ok(table) = toml::from_str::<toml::Table>(content) {
+            config.warnings = unknown_keys(&table);
+        }
+        config.warnings.extend(config.lint());
+        for warning in &config.warnings {
+            warn!("{config_path}: {warning}");
+        }
+        config.path = Some(config_path);
+        Ok(config)
     }
+
+    /// Applies the `[wm.<name>]` table of the running window manager,
+    /// see `Desktop::wm`. A profile without path reads its default file,
+    /// like with `--profile`.
+    pub fn apply_wm(&mut self, name: Option<&str>) {
+        let Some((name, wm)) = name.and_then(|name| {
+            self.wm
+                .as_ref()?
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(key, wm)| (key.clone(), wm.clone()))
+        }) else {
+            return;
+        };
+        info!("applying the [wm.{name}] table");
+        self.default_profile = Some(WmConfig {
+            profile: self.profile,
+            path: Some(self.xmonad_config.clone()),
+            sources: Some(self.sources.clone().unwrap_or_default()),
+        });
+        self.apply_profile(wm);
+    }
+
+    fn apply_profile(&mut self, wm: WmConfig) {
+        self.profile = wm.profile;
+        match (wm.path, wm.profile) {
+            (Some(path), _) => self.xmonad_config = path,
+            (None, Some(Profile::Xcompose)) => self.xmonad_config = compose::default_path(),
+            (None, Some(Profile::Dump)) => self.xmonad_config = STDIN_PATH.to_owned(),
+            (None, _) => {}
+        }
+        if wm.sources.is_some() {
+            self.sources = wm.sources;
+        }
+    }
+
+    /// The default profile then the one of each `[wm.<name>]` table, by
+    /// lowercase name, e.g. to export them all
+    pub fn profiles(&self) -> Vec<(String, UserConfig)> {
+        let mut default = self.clone();
+        if let Some(profile) = self.default_profile.clone() {
+            default.apply_profile(profile);
+        }
+        let mut tables: Vec<(&String, &WmConfig)> = self.wm.iter().flatten().collect();
+        tables.sort_by_key(|(name, _)| name.to_lowercase());
+        let mut profiles = vec![("default".to_owned(), default.clone())];
+        for (name, wm) in tables {
+            let mut config = default.clone();
+            config.apply_profile(wm.clone());
+            profiles.push((name.to_lowercase(), config));
+        }
+        profiles
+    }
+
+    /// The `[colors]` over the ones imported from `color_scheme`, if any
+    pub fn colors(&self) -> Option<ColorsConfig> {
+        let imported = self.color_scheme.as_deref().and_then(|path| {
+            crate::theme::import_scheme(path)
+                .map_err(|e| warn!("{e}"))
+                .ok()
+        });
+        match (self.colors.clone(), imported) {
+            (Some(colors), Some(imported)) => Some(colors.or(imported)),
+            (colors, imported) => colors.or(imported),
+        }
+    }
+
+    /// Checks the values of the config
+    fn lint(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if let Some(theme) = &self.theme {
+            if crate::theme::builtin(theme).is_none() && theme != crate::theme::AUTO_TIME {
+                warnings.push(format!(
+                    "unknown theme `{theme}`, one of {}, {}",
+                    crate::theme::THEMES.join(", "),
+                    crate::theme::AUTO_TIME
+                ));
+            }
+        }
+        if let Some(auto_time) = &self.auto_time {
+            for (key, time) in [("day", &auto_time.day), ("night", &auto_time.night)] {
+                if let Some(time) = time
+                    .as_ref()
+                    .filter(|t| crate::theme::parse_time(t).is_none())
+                {
+                    warnings.push(format!("auto_time.{key} = \"{time}\" is not a HH:MM time"));
+                }
+            }
+        }
+        if let Some(font) = &self.font {
+            for (key, size) in [
+                ("title_size", font.title_size),
+                ("section_size", font.section_size),
+                ("keybind_size", font.keybind_size),
+                ("text_size", font.text_size),
+                ("error_size", font.error_size),
+            ] {
+                if let Some(size) = size.filter(|s| !FONT_SIZE_RANGE.contains(s)) {
+                    warnings.push(format!(
+                        "font.{key} = {size} is out of the {}..{} range",
+                        FONT_SIZE_RANGE.start(),
+                        FONT_SIZE_RANGE.end()
+                    ));
+                }
+            }
+        }
+        if let Some(colors) = &self.colors {
+            for (key, color) in [
+                ("background", &colors.background),
+                ("text", &colors.text),
+                ("primary", &colors.primary),
+                ("success", &colors.success),
+                ("danger", &colors.danger),
+            ] {
+                if let Some(color) = color.as_ref().filter(|c| parse_hex_color(c).is_none()) {
+                    warnings.push(format!("colors.{key} = \"{color}\" is not a #rrggbb color"));
+                }
+            }
+        }
+        if let Some(Err(e)) = self
+            .color_scheme
+            .as_deref()
+            .map(crate::theme::import_scheme)
+        {
+            warnings.push(format!("color_scheme: {e}"));
+        }
+        if let Some(keys) = &self.toggle_hotkey {
+            if let Err(e) = crate::hotkey::Hotkey::parse(keys) {
+                warnings.push(format!("toggle_hotkey = \"{keys}\": {e}"));
+            }
+        }
+        if let Some(template) = &self.row_template {
+            if let Err(e) = crate::template::RowTemplate::parse(template) {
+                warnings.push(format!("row_template = \"{template}\": {e}"));
+            }
+        }
+        if let Some(weights) = &self.search_weights {
+            for (key, weight) in [
+                ("keys", weights.keys),
+                ("description", weights.description),
+                ("action", weights.action),
+            ] {
+                if weight <= 0.0 {
+                    warnings.push(format!(
+                        "search_weights.{key} = {weight} is not positive, its matches rank last"
+                    ));
+                }
+            }
+        }
+        if let Some(opacity) = self.window.as_ref().and_then(|w| w.opacity) {
+            if !(0.0..=1.0).contains(&opacity) {
+                warnings.push(format!(
+                    "window.opacity = {opacity} is out of the 0.0..1.0 range"
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Reports the keys serde would silently ignore, with the known key
+/// they are the closest to
+fn unknown_keys(table: &toml::Table) -> Vec<String> {
+    let mut warnings = vec![];
+    let mut check = |prefix: &str, key: &str, known: &[&str]| {
+        if !known.contains(&key) {
+            let suggestion = known
+                .iter()
+                .map(|k| (edit_distance(k, key), k))
+                .filter(|(distance, _)| *distance <= 2)
+                .min()
+                .map(|(_, k)| format!(", did you mean `{prefix}{k}`?"))
+                .unwrap_or_default();
+            warnings.push(format!("unknown key `{prefix}{key}`{suggestion}"));
+        }
+    };
+    for (key, value) in table {
+        check("", key, &KEYS);
+        if let (Some((_, known)), Some(inner)) = (
+            TABLE_KEYS.iter().find(|(table, _)| table == key),
+            value.as_table(),
+        ) {
+            for inner_key in inner.keys() {
+                check(&format!("{key}."), inner_key, known);
+            }
+        }
+    }
+    let wm_tables = table.get("wm").and_then(|wm| wm.as_table());
+    for (name, wm) in wm_tables.into_iter().flatten() {
+        for key in wm.as_table().into_iter().flat_map(|wm| wm.keys()) {
+            check(&format!("wm.{name}."), key, &WM_KEYS);
+        }
+    }
+    warnings
+}
+
+/// Expands a leading `~` and the `$VAR` or `${VAR}` environment
+/// variables of a path, so that a config can be shared across machines.
+/// A variable not set is left as is, so that only the path using it fails
+/// to be read.
+pub fn expand_path(path: &str) -> Result<String> {
+    let (mut expanded, path) = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home =
+                dirs::home_dir().ok_or_else(|| eyre!("home directory of path `{path}` unknown"))?;
+            (home.display().to_string(), rest.to_owned())
+        }
+        _ => (String::with_capacity(path.len()), path.to_owned()),
+    };
+    let mut rest = path.as_str();
+    while let Some(i) = rest.find('$') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let braced = rest.starts_with('{');
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => braced
+                .split_once('}')
+                .ok_or_else(|| eyre!("unclosed `${{` in path `{path}`"))?,
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                rest.split_at(end)
+            }
+        };
+        // a lone `$` is kept as is
+        if name.is_empty() {
+            expanded.push('$');
+        } else if let Ok(value) = env::var(name) {
+            expanded.push_str(&value);
+        } else {
+            warn!("environment variable {name} of path `{path}` not set");
+            if braced {
+                expanded.push_str(&format!("${{{name}}}"));
+            } else {
+                expanded.push_str(&format!("${name}"));
+            }
+        }
+        rest = after;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn deserialize_path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let path = String::deserialize(deserializer)?;
+    expand_path(&path).map_err(serde::de::Error::custom)
+}
+
+pub fn deserialize_optional_path<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|path| expand_path(&path).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
 }
 
 impl Default for UserConfig {
     fn default() -> Self {
         UserConfig {
-            xmonad_config: XMONAD_HS_PATH.into(),
+            xmonad_config: expand_path(XMONAD_HS_PATH).unwrap_or_else(|_| XMONAD_HS_PATH.into()),
             font: Some(FontConfig::default()),
             theme: None,
+            auto_time: None,
+            colors: None,
+            color_scheme: None,
+            confirm_exec: None,
+            usage_log: None,
+            haddock: None,
+            fallback_description: None,
+            show_ignored: None,
+            window: None,
+            keys: None,
+            startup: None,
+            keyboard_layout: None,
+            close_on_focus_loss: None,
+            toggle_hotkey: None,
+            tray: None,
+            http_port: None,
+            renderer: None,
+            sources: None,
+            wm: None,
+            profile: None,
+            default_profile: None,
+            density: None,
+            layout: None,
+            row_template: None,
+            clipboard_format: None,
+            section_sort: None,
+            search_actions: None,
+            search_weights: None,
+            search_mode: None,
+            search_matcher: None,
+            path: None,
+            warnings: vec![],
         }
     }
 }
 
-impl Default for FontConfig {
-    fn default() -> Self {
-        FontConfig {
-            title_size: Some(TITLE_FONT_SIZE),
-            section_size: Some(FONT_SIZE),
-            keybind_size: Some(FONT_SIZE),
-            text_size: Some(FONT_SIZE),
-            error_size: Some(FONT_SIZE),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppConfig;
+
+    #[test]
+    fn unknown_keys_reporting() {
+        let table = toml::from_str::<toml::Table>(
+            r##"
+xmonad_config = "xmonad.hs"
+them = "dark"
+shortcuts = true
+[font]
+text_sise = 14
+[colors]
+primary = "#61afef"
+"##,
+        )
+        .unwrap();
+        assert_eq!(
+            unknown_keys(&table),
+            vec![
+                "unknown key `font.text_sise`, did you mean `font.text_size`?",
+                "unknown key `shortcuts`",
+                "unknown key `them`, did you mean `theme`?",
+            ]
+        );
+    }
+
+    #[test]
+    fn theme_overriding() {
+        let mut config = toml::from_str::<UserConfig>(
+            r##"
+xmonad_config = "xmonad.hs"
+theme = "dark"
+[colors]
+background = "#212121"
+primary = "#61afef"
+"##,
+        )
+        .unwrap();
+        let mut accented = config.clone();
+        ThemeOverride {
+            theme: None,
+            accent: Some("#ff8800".into()),
+        }
+        .apply(&mut accented);
+        let colors = accented.colors.unwrap();
+        assert_eq!(colors.primary.as_deref(), Some("#ff8800"));
+        assert_eq!(colors.background.as_deref(), Some("#212121"));
+
+        ThemeOverride {
+            theme: Some("light".into()),
+            accent: None,
         }
+        .apply(&mut config);
+        assert_eq!(config.theme.as_deref(), Some("light"));
+        assert!(config.colors.is_none());
+    }
+
+    #[test]
+    fn window_manager_tables() {
+        let content = r#"
+xmonad_config = "/home/ape/.config/xmonad/xmonad.hs"
+[[sources]]
+type = "kitty"
+[wm.xmonad]
+profile = "xmonad"
+[wm.Hyprland]
+profile = "dump"
+path = "/home/ape/.config/hypr/keys.txt"
+sources = []
+[wm.i3]
+profle = "dump"
+"#;
+        let config = toml::from_str::<UserConfig>(content).unwrap();
+        let applied = |name| {
+            let mut config = config.clone();
+            config.apply_wm(name);
+            (
+                config.profile,
+                config.xmonad_config,
+                config.sources.unwrap().len(),
+            )
+        };
+        assert_eq!(
+            applied(Some("xmonad")),
+            (
+                Some(Profile::Xmonad),
+                "/home/ape/.config/xmonad/xmonad.hs".into(),
+                1
+            )
+        );
+        assert_eq!(
+            applied(Some("hyprland")),
+            (
+                Some(Profile::Dump),
+                "/home/ape/.config/hypr/keys.txt".into(),
+                0
+            )
+        );
+        assert_eq!(applied(None), applied(Some("sway")));
+
+        let mut running = config.clone();
+        running.apply_wm(Some("Hyprland"));
+        let profiles: Vec<_> = running
+            .profiles()
+            .into_iter()
+            .map(|(name, config)| (name, config.profile, config.sources.unwrap().len()))
+            .collect();
+        assert_eq!(
+            profiles,
+            vec![
+                ("default".to_owned(), None, 1),
+                ("hyprland".to_owned(), Some(Profile::Dump), 0),
+                ("i3".to_owned(), None, 1),
+                ("xmonad".to_owned(), Some(Profile::Xmonad), 1),
+            ]
+        );
+        assert_eq!(
+            unknown_keys(&toml::from_str(content).unwrap()),
+            vec!["unknown key `wm.i3.profle`, did you mean `wm.i3.profile`?"]
+        );
+    }
+
+    #[test]
+    fn path_expansion() {
+        env::set_var("APEKEY_TEST_HOME", "/home/ape");
+        env::set_var("APEKEY_TEST_DIR", "xmonad");
+        assert_eq!(
+            expand_path("$APEKEY_TEST_HOME/.config/${APEKEY_TEST_DIR}/xmonad.hs").unwrap(),
+            "/home/ape/.config/xmonad/xmonad.hs"
+        );
+        assert_eq!(
+            expand_path("/tmp/a$/b~").unwrap(),
+            "/tmp/a$/b~",
+            "a lone `$` and a `~` not leading are literal"
+        );
+        assert_eq!(
+            expand_path("~/xmonad.hs").unwrap(),
+            dirs::home_dir()
+                .unwrap()
+                .join("xmonad.hs")
+                .display()
+                .to_string()
+        );
+        assert_eq!(
+            expand_path("${APEKEY_TEST_UNSET}/$APEKEY_TEST_UNSET.hs").unwrap(),
+            "${APEKEY_TEST_UNSET}/$APEKEY_TEST_UNSET.hs"
+        );
+        assert!(expand_path("${APEKEY_TEST_HOME/xmonad.hs").is_err());
+        let config: UserConfig = toml::from_str(
+            r#"
+xmonad_config = "${APEKEY_TEST_HOME}/xmonad.hs"
+usage_log = "$APEKEY_TEST_HOME/keys.log"
+color_scheme = "$APEKEY_TEST_UNSET/colors.json"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.xmonad_config, "/home/ape/xmonad.hs");
+        assert_eq!(config.usage_log.as_deref(), Some("/home/ape/keys.log"));
+        // only the path of the unset variable is wrong
+        assert_eq!(
+            config.color_scheme.as_deref(),
+            Some("$APEKEY_TEST_UNSET/colors.json")
+        );
+    }
+
+    #[test]
+    fn layout_table() {
+        let config: UserConfig = toml::from_str(
+            r#"
+xmonad_config = "xmonad.hs"
+density = "compact"
+[font]
+autoscale = false
+[layout]
+row_spacing = 4
+keymap_padding = [10, 20, 10, 20]
+section_padding = 6
+search_width = 320
+sub_columns_from = 0
+"#,
+        )
+        .unwrap();
+        assert!(unknown_keys(
+            &toml::from_str(
+                "[layout]
+row_spacing = 4"
+            )
+            .unwrap()
+        )
+        .is_empty());
+        let metrics = AppConfig::from(config).ui.metrics;
+        assert_eq!(metrics.row_spacing, 4);
+        let sides = |p: Padding| [p.top, p.right, p.bottom, p.left];
+        assert_eq!(sides(metrics.keymap_padding), [10.0, 20.0, 10.0, 20.0]);
+        assert_eq!(sides(metrics.section_padding), [6.0; 4]);
+        assert_eq!(metrics.search_width, 320.0);
+        assert_eq!(metrics.sub_columns_from, 0);
+        // the compact density ones
+        assert_eq!(metrics.section_spacing, 12);
+        assert_eq!(metrics.rule_thickness, 1);
+    }
+
+    #[test]
+    fn values_linting() {
+        let config: UserConfig = toml::from_str(
+            r#"
+xmonad_config = "xmonad.hs"
+theme = "drak"
+[font]
+title_size = 200
+text_size = 14
+[colors]
+primary = "blue"
+[window]
+opacity = 1.5
+mode = "fullscreen"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.window.as_ref().and_then(|w| w.mode),
+            Some(WindowMode::Fullscreen)
+        );
+        let warnings = config.lint();
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].starts_with("unknown theme `drak`"));
+        assert_eq!(
+            warnings[1],
+            "font.title_size = 200 is out of the 6..96 range"
+        );
+        assert_eq!(
+            warnings[2],
+            "colors.primary = \"blue\" is not a #rrggbb color"
+        );
+        assert_eq!(
+            warnings[3],
+            "window.opacity = 1.5 is out of the 0.0..1.0 range"
+        );
     }
 }
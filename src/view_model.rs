@@ -0,0 +1,415 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// What the keymap view shows: the grouping, the filters, the collapsed
+// sections and the search state. It knows nothing of iced, `Apekey`
+// turns the messages into calls to it and its results into commands.
+
+use std::{borrow::Cow, collections::HashSet, time::Duration};
+
+use crate::{
+    app::{ActionFilter, LayoutFilter},
+    search::{build_regex, Matcher, SearchMode, SearchResults},
+    token::{Chord, ScoredKeybind, Section, Tokens},
+};
+
+// title of the section of the keybinds annotated with `-- !`
+pub const IGNORED_TITLE: &str = "Ignored";
+
+#[derive(Debug, Default)]
+pub struct ViewModel {
+    // tokens parsed from xmonad config
+    pub keymap: Tokens,
+    pub input_value: String,
+    // why the query is not a valid regex, compiled once per query rather
+    // than on each render
+    pub regex_error: Option<String>,
+    // titles of the collapsed sections
+    pub collapsed: HashSet<String>,
+    pub action_filter: ActionFilter,
+    pub layout_filter: LayoutFilter,
+    // group the keybinds by modifier combo instead of by section
+    pub by_modifier: bool,
+    pub search_mode: SearchMode,
+    pub matcher: Matcher,
+    // show the keybinds annotated with `-- !` in their own section
+    pub show_ignored: bool,
+    // the matching keybinds when fuzzy searching, best first
+    pub tokens: Vec<ScoredKeybind>,
+    // chord picked to find the keybinds using it
+    pub chord: Chord,
+    // closest keybinds when the search matches none
+    pub suggestions: Vec<ScoredKeybind>,
+    // duration of the last search, shown with the number of matches
+    pub search_time: Duration,
+    // index of the match jumped to in the `jump` search mode
+    pub match_index: usize,
+}
+
+impl ViewModel {
+    pub fn is_searching(&self) -> bool {
+        !self.input_value.is_empty()
+    }
+
+    /// Sets the search query, reporting it if it is not a valid regex with
+    /// the regex matcher
+    pub fn set_query(&mut self, query: String) {
+        self.regex_error = match self.matcher {
+            Matcher::Regex => build_regex(&query).err().map(|e| match e {
+                regex::Error::Syntax(_) => "Invalid regex".to_owned(),
+                e => e.to_string(),
+            }),
+            Matcher::Fuzzy => None,
+        };
+        self.input_value = query;
+    }
+
+    /// The keymap as rendered, grouped by modifier combo, with the
+    /// ignored keybinds if toggled and only the ones having the picked
+    /// chord if any
+    pub fn displayed_keymap(&self) -> Cow<'_, Tokens> {
+        let mut keymap = if self.by_modifier {
+            Cow::Owned(self.keymap.by_modifier())
+        } else {
+            Cow::Borrowed(&self.keymap)
+        };
+        if !self.chord.is_empty() {
+            keymap = Cow::Owned(keymap.with_chord(&self.chord));
+        }
+        if self.show_ignored && !self.keymap.ignored.is_empty() {
+            let keymap = keymap.to_mut();
+            keymap.sections.push(Section {
+                title: Some(IGNORED_TITLE.to_owned()),
+                description: None,
+                keybinds: keymap.ignored.clone(),
+            });
+        }
+        keymap
+    }
+
+    /// The displayed keymap with only the keybinds of the picked action
+    /// kind and layout, as rendered when not searching
+    pub fn filtered_keymap(&self) -> Cow<'_, Tokens> {
+        let keymap = self.displayed_keymap();
+        let keymap = match self.action_filter {
+            ActionFilter::All => keymap,
+            ActionFilter::Kind(kind) => Cow::Owned(keymap.with_kind(kind)),
+        };
+        match self.layout_filter {
+            LayoutFilter::All => keymap,
+            _ => Cow::Owned(keymap.with_layout(&self.layout_filter)),
+        }
+    }
+
+    /// The search matches having the picked action kind, layout and chord
+    pub fn matching(&self) -> impl Iterator<Item = &ScoredKeybind> {
+        self.tokens
+            .iter()
+            .filter(|keybind| self.action_filter.matches(keybind.kind))
+            .filter(|keybind| self.layout_filter.matches(&keybind.layouts))
+            .filter(|keybind| self.chord.is_empty() || self.chord.matches(&keybind.keys))
+    }
+
+    /// The keybinds listed, the search matches in their sections when
+    /// searching
+    pub fn results(&self) -> Cow<'_, Tokens> {
+        let keymap = self.filtered_keymap();
+        if self.is_searching() {
+            let matches: Vec<ScoredKeybind> = self.matching().cloned().collect();
+            Cow::Owned(keymap.matching(&matches))
+        } else {
+            keymap
+        }
+    }
+
+    /// The first match shown, e.g. picked by `Enter`
+    pub fn best_match(&self) -> Option<&ScoredKeybind> {
+        if !self.is_searching() {
+            return None;
+        }
+        self.matching().next()
+    }
+
+    /// Command spawned by the best match, if it spawns one
+    pub fn best_command(&self) -> Option<String> {
+        self.best_match()?.command.clone()
+    }
+
+    pub fn toggle_section(&mut self, title: String) {
+        if !self.collapsed.remove(&title) {
+            self.collapsed.insert(title);
+        }
+    }
+
+    /// Collapses every section, or expands them all if one is collapsed
+    pub fn toggle_all_sections(&mut self) {
+        if self.collapsed.is_empty() {
+            self.collapsed = self
+                .displayed_keymap()
+                .sections
+                .iter()
+                .filter_map(|s| s.title.clone())
+                .collect();
+        } else {
+            self.collapsed.clear();
+        }
+    }
+
+    pub fn cycle_action_filter(&mut self) {
+        let current = ActionFilter::ALL
+            .iter()
+            .position(|filter| *filter == self.action_filter)
+            .unwrap_or_default();
+        self.action_filter = ActionFilter::ALL[(current + 1) % ActionFilter::ALL.len()];
+    }
+
+    /// Goes to the next search mode, returns true when the first match
+    /// is to be jumped to
+    pub fn cycle_search_mode(&mut self) -> bool {
+        self.search_mode = match self.search_mode {
+            SearchMode::Flat => SearchMode::Sections,
+            SearchMode::Sections => SearchMode::Jump,
+            SearchMode::Jump => SearchMode::Flat,
+        };
+        self.match_index = 0;
+        self.is_jumping()
+    }
+
+    /// Stores the search matches, returns true when the first match is to
+    /// be jumped to
+    pub fn set_results(&mut self, (tokens, elapsed): SearchResults) -> bool {
+        self.tokens = tokens;
+        self.search_time = elapsed;
+        self.suggestions.clear();
+        self.match_index = 0;
+        self.is_jumping() && !self.tokens.is_empty()
+    }
+
+    /// Moves `step` matches forward or backward, wrapping around, returns
+    /// false when there is no match to go to
+    pub fn cycle_match(&mut self, step: isize) -> bool {
+        let count = self.matching().count();
+        if !self.is_jumping() || count == 0 {
+            return false;
+        }
+        self.match_index = (self.match_index as isize + step).rem_euclid(count as isize) as usize;
+        true
+    }
+
+    /// The current match, with its vertical offset in the filtered
+    /// keymap, its section is expanded if collapsed
+    pub fn jump_target(&mut self) -> Option<(ScoredKeybind, f32)> {
+        let keybind = self.matching().nth(self.match_index).cloned()?;
+        let keymap = self.filtered_keymap().into_owned();
+        let (section, row) = keymap.position(&keybind)?;
+        if let Some(title) = &keymap.sections[section].title {
+            self.collapsed.remove(title);
+        }
+        let offset = row_offset(&keymap, section, row + 1, &self.collapsed);
+        Some((keybind, offset))
+    }
+
+    /// Returns the vertical offset of the section in the keymap, from 0.0
+    /// (top) to 1.0 (bottom), estimated from the rows above it
+    pub fn section_offset(&self, title: &str) -> Option<f32> {
+        let index = self
+            .keymap
+            .sections
+            .iter()
+            .position(|s| s.title.as_deref() == Some(title))?;
+        Some(row_offset(&self.keymap, index, 0, &self.collapsed))
+    }
+
+    fn is_jumping(&self) -> bool {
+        self.search_mode == SearchMode::Jump && self.is_searching()
+    }
+}
+
+/// Returns the vertical offset of a row of a section, its title being
+/// the row 0, like `section_offset`
+fn row_offset(tokens: &Tokens, section: usize, row: usize, collapsed: &HashSet<String>) -> f32 {
+    let rows = |section: &Section| match &section.title {
+        Some(title) if collapsed.contains(title) => 1,
+        _ => 1 + section.keybinds.len(),
+    };
+    let above: usize = tokens.sections[..section].iter().map(rows).sum::<usize>() + row;
+    let total: usize = tokens.sections.iter().map(rows).sum();
+    above as f32 / total.max(1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{ActionKind, Keybind};
+
+    fn view_model() -> ViewModel {
+        let section = |title: &str, keybinds| Section {
+            title: Some(title.to_owned()),
+            description: None,
+            keybinds,
+        };
+        ViewModel {
+            keymap: Tokens {
+                title: None,
+                sections: vec![
+                    section(
+                        "Launchers",
+                        vec![
+                            Keybind::new("M-p", "dmenu", Some("spawn \"dmenu_run\"")),
+                            Keybind::new("M-S-<Return>", "Terminal", Some("spawn \"alacritty\"")),
+                        ],
+                    ),
+                    section(
+                        "Windows",
+                        vec![
+                            Keybind::new("M-S-c", "Close", Some("kill")),
+                            Keybind::new("M-j", "Focus down", Some("windows W.focusDown")),
+                        ],
+                    ),
+                ],
+                ignored: vec![Keybind::new("M-q", "Restart", None)],
+                warnings: vec![],
+            },
+            ..ViewModel::default()
+        }
+    }
+
+    fn search(view_model: &mut ViewModel, query: &str, keys: &[&str]) -> bool {
+        view_model.input_value = query.to_owned();
+        let matches = keys
+            .iter()
+            .filter_map(|k| {
+                view_model
+                    .keymap
+                    .keybinds()
+                    .into_iter()
+                    .find(|m| m.keys == *k)
+            })
+            .collect();
+        view_model.set_results((matches, Duration::ZERO))
+    }
+
+    #[test]
+    fn filtering_and_collapsing() {
+        let mut view_model = view_model();
+        assert_eq!(view_model.displayed_keymap().section_count(), 2);
+        view_model.show_ignored = true;
+        assert_eq!(
+            view_model.displayed_keymap().sections[2].title.as_deref(),
+            Some(IGNORED_TITLE)
+        );
+        view_model.show_ignored = false;
+
+        view_model.action_filter = ActionFilter::Kind(ActionKind::Spawn);
+        assert_eq!(view_model.filtered_keymap().keybind_count(), 2);
+        view_model.cycle_action_filter();
+        assert_eq!(
+            view_model.action_filter,
+            ActionFilter::Kind(ActionKind::Window)
+        );
+        view_model.action_filter = ActionFilter::All;
+
+        view_model.toggle_all_sections();
+        assert_eq!(view_model.collapsed.len(), 2);
+        assert_eq!(view_model.section_offset("Windows"), Some(0.5));
+        view_model.toggle_section("Windows".to_owned());
+        assert_eq!(view_model.section_offset("Windows"), Some(1.0 / 4.0));
+        view_model.toggle_all_sections();
+        assert!(view_model.collapsed.is_empty());
+    }
+
+    #[test]
+    fn layout_filtering() {
+        let mut view_model = view_model();
+        view_model.keymap.sections[1].keybinds[1].layouts = vec!["Tall".to_owned()];
+        assert_eq!(view_model.keymap.layouts(), vec!["Tall"]);
+        view_model.layout_filter = LayoutFilter::Global;
+        assert_eq!(view_model.filtered_keymap().keybind_count(), 3);
+        view_model.layout_filter = LayoutFilter::Layout("Full".to_owned());
+        assert_eq!(view_model.filtered_keymap().keybind_count(), 3);
+        view_model.layout_filter = LayoutFilter::Layout("Tall".to_owned());
+        assert_eq!(view_model.filtered_keymap().keybind_count(), 4);
+
+        view_model.layout_filter = LayoutFilter::Global;
+        search(&mut view_model, "m", &["M-j", "M-p"]);
+        let keys: Vec<_> = view_model.matching().map(|k| k.keys.as_str()).collect();
+        assert_eq!(keys, vec!["M-p"]);
+    }
+
+    #[test]
+    fn searching_and_jumping() {
+        let mut view_model = view_model();
+        assert!(!search(&mut view_model, "m", &["M-j", "M-p"]));
+        assert_eq!(view_model.results().keybind_count(), 2);
+        assert_eq!(
+            view_model.best_command(),
+            None,
+            "the best match spawns nothing, the next ones are not run"
+        );
+        assert!(!view_model.cycle_match(1), "not in the jump mode");
+
+        view_model.toggle_all_sections();
+        view_model.search_mode = SearchMode::Sections;
+        assert!(view_model.cycle_search_mode());
+        let (keybind, offset) = view_model.jump_target().unwrap();
+        assert_eq!(keybind.keys, "M-j");
+        assert!(!view_model.collapsed.contains("Windows"));
+        assert_eq!(offset, 3.0 / 4.0);
+
+        assert!(view_model.cycle_match(1));
+        assert_eq!(view_model.jump_target().unwrap().0.keys, "M-p");
+        assert!(view_model.cycle_match(1));
+        assert_eq!(view_model.match_index, 0, "wraps around");
+
+        assert!(!search(&mut view_model, "zz", &[]));
+        assert!(!view_model.cycle_match(1));
+        view_model.input_value.clear();
+        assert_eq!(view_model.results().keybind_count(), 4);
+        assert_eq!(view_model.best_command(), None);
+    }
+
+    #[test]
+    fn best_match_picking() {
+        let mut view_model = view_model();
+        assert!(
+            view_model.best_match().is_none(),
+            "nothing picked unsearched"
+        );
+        search(&mut view_model, "d", &["M-j", "M-p"]);
+        assert_eq!(view_model.best_match().unwrap().keys, "M-j");
+        // the first match shown, the hidden ones are skipped
+        view_model.action_filter = ActionFilter::Kind(ActionKind::Spawn);
+        assert_eq!(view_model.best_match().unwrap().keys, "M-p");
+        search(&mut view_model, "zz", &[]);
+        assert!(view_model.best_match().is_none());
+    }
+
+    #[test]
+    fn chord_hiding_commands() {
+        let mut view_model = view_model();
+        search(&mut view_model, "e", &["M-p", "M-S-<Return>"]);
+        assert_eq!(view_model.best_command().as_deref(), Some("dmenu_run"));
+        // Super and Shift hide `M-p`
+        view_model.chord = Chord {
+            modifiers: vec![0, 7],
+            key: None,
+        };
+        assert_eq!(view_model.best_command().as_deref(), Some("alacritty"));
+    }
+
+    #[test]
+    fn regex_errors() {
+        let mut view_model = ViewModel::default();
+        view_model.set_query("(".to_owned());
+        assert_eq!(
+            view_model.regex_error, None,
+            "fuzzy queries are not regexes"
+        );
+        view_model.matcher = Matcher::Regex;
+        view_model.set_query("(".to_owned());
+        assert_eq!(view_model.regex_error.as_deref(), Some("Invalid regex"));
+        view_model.set_query("M-[0-9]".to_owned());
+        assert_eq!(view_model.regex_error, None);
+    }
+}
@@ -0,0 +1,96 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use iced::{subscription, Subscription};
+use std::time::{Duration, SystemTime};
+use tokio::{fs, time};
+use tracing::debug;
+
+use crate::app::Message;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// a change is reported once the file stayed the same this long, an
+// editor may write it in several steps, read mid-save it is truncated
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// modification time and length of the file
+type Stamp = Option<(SystemTime, u64)>;
+
+/// Emits `message` when the file is modified. The modification time is
+/// polled, editors often replace the file instead of writing it.
+pub fn subscription(path: String, message: Message) -> Subscription<Message> {
+    struct Watcher;
+
+    subscription::unfold(
+        (std::any::TypeId::of::<Watcher>(), path.clone()),
+        (path, None),
+        move |(path, mut last)| {
+            let message = message.clone();
+            async move {
+                if last.is_none() {
+                    last = stamp(&path).await;
+                }
+                loop {
+                    time::sleep(POLL_INTERVAL).await;
+                    let current = stamp(&path).await;
+                    if current.is_some() && current != last {
+                        let current = settle(&path, current).await;
+                        debug!("{path} modified");
+                        return (message, (path, current));
+                    }
+                }
+            }
+        },
+    )
+}
+
+// waits for the writes to the file to stop
+async fn settle(path: &str, mut current: Stamp) -> Stamp {
+    loop {
+        time::sleep(DEBOUNCE).await;
+        let next = stamp(path).await;
+        if next == current {
+            return current;
+        }
+        debug!("{path} still being written");
+        current = next;
+    }
+}
+
+async fn stamp(path: &str) -> Stamp {
+    let metadata = fs::metadata(path).await.ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_debouncing() {
+        let path = std::env::temp_dir().join(format!("apekey-watch-{}.hs", std::process::id()));
+        let path = path.to_str().unwrap().to_owned();
+        std::fs::write(&path, "-- #").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let settled = runtime.block_on(async {
+            let writer = tokio::spawn({
+                let path = path.clone();
+                async move {
+                    for part in ["\n-- ## Apps", "\n-- #\n"] {
+                        time::sleep(DEBOUNCE / 3).await;
+                        let mut content = fs::read_to_string(&path).await.unwrap();
+                        content.push_str(part);
+                        fs::write(&path, content).await.unwrap();
+                    }
+                }
+            });
+            let settled = settle(&path, stamp(&path).await).await;
+            writer.await.unwrap();
+            settled
+        });
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(settled.map(|(_, len)| len), Some(content.len() as u64));
+    }
+}
@@ -0,0 +1,55 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+// Detection of the running window manager, to pick its `[wm.<name>]`
+// table of apekey.toml. The name an EWMH window manager sets on its
+// `_NET_SUPPORTING_WM_CHECK` window comes first, e.g. `xmonad` with
+// `ewmh` or `i3`, then the desktop of `$XDG_CURRENT_DESKTOP`, e.g.
+// `Hyprland` on Wayland.
+
+use eyre::{eyre, Result};
+use std::env;
+use tracing::{debug, instrument};
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{AtomEnum, ConnectionExt},
+};
+
+/// Name of the running window manager, lowercase
+#[instrument]
+pub fn detect() -> Option<String> {
+    let name = ewmh_name()
+        .map_err(|e| debug!("no EWMH window manager name: {e}"))
+        .ok()
+        .or_else(|| {
+            env::var("XDG_CURRENT_DESKTOP")
+                .ok()
+                .and_then(|desktops| desktops.split(':').next().map(str::to_owned))
+        })?;
+    let name = name.trim().to_lowercase();
+    debug!("window manager {name:?}");
+    (!name.is_empty()).then_some(name)
+}
+
+fn ewmh_name() -> Result<String> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let atom =
+        |name: &str| -> Result<u32> { Ok(conn.intern_atom(false, name.as_bytes())?.reply()?.atom) };
+    let (check, wm_name, utf8) = (
+        atom("_NET_SUPPORTING_WM_CHECK")?,
+        atom("_NET_WM_NAME")?,
+        atom("UTF8_STRING")?,
+    );
+    let window = conn
+        .get_property(false, root, check, AtomEnum::WINDOW, 0, 1)?
+        .reply()?
+        .value32()
+        .and_then(|mut windows| windows.next())
+        .ok_or_else(|| eyre!("_NET_SUPPORTING_WM_CHECK not set"))?;
+    let reply = conn
+        .get_property(false, window, wm_name, utf8, 0, 256)?
+        .reply()?;
+    Ok(String::from_utf8_lossy(&reply.value).into_owned())
+}